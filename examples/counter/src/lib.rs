@@ -28,6 +28,8 @@ impl Update<Msg> for Model {
     }
 }
 
+impl Subscriptions<Msg> for Model {}
+
 fn button(text: &str, msg: Msg) -> Dom<Msg> {
     Dom::elem("button")
         .event("click", msg)
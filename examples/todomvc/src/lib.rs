@@ -194,30 +194,20 @@ impl Update<Message, Command> for Todo {
 }
 
 impl SideEffect<Message> for Command {
-    fn process(self, _: &Dispatcher<Message, Command>) {
+    fn process(self, dispatcher: &Dispatcher<Message, Command>) {
         use Command::*;
 
         match self {
             FocusPending => {
-                let pending_input = web_sys::window()
-                    .expect("couldn't get window handle")
-                    .document()
-                    .expect("couldn't get document handle")
-                    .query_selector("section.todoapp header.header input.new-todo")
-                    .expect("error querying for element")
-                    .expect("expected to find an input element")
+                let pending_input = dispatcher.node_ref("new-todo")
+                    .expect_throw("expected to find an input element")
                     .dyn_into::<web_sys::HtmlInputElement>()
                     .expect_throw("expected web_sys::HtmlInputElement");
 
                 pending_input.focus().expect_throw("error focusing input");
             }
             FocusEdit => {
-                let edit_input = web_sys::window()
-                    .expect_throw("couldn't get window handle")
-                    .document()
-                    .expect_throw("couldn't get document handle")
-                    .query_selector("section.todoapp section.main input.edit")
-                    .expect_throw("error querying for element")
+                let edit_input = dispatcher.node_ref("edit")
                     .expect_throw("expected to find an input element")
                     .dyn_into::<web_sys::HtmlInputElement>()
                     .expect_throw("expected web_sys::HtmlInputElement");
@@ -265,6 +255,8 @@ fn read_items_from_storage() -> Vec<Item> {
         )
 }
 
+impl Subscriptions<Message> for Todo {}
+
 impl Render<dom::DomVec<Message, Command>> for Todo {
     fn render(&self) -> dom::DomVec<Message, Command> {
         use dom::Dom;
@@ -276,6 +268,7 @@ impl Render<dom::DomVec<Message, Command>> for Todo {
             .push(Dom::elem("h1").push("todos"))
             .push(Dom::elem("input")
                 .attr("class", "new-todo")
+                .node_ref("new-todo")
                 .attr("placeholder", "What needs to be done?")
                 .attr("autofocus", "true")
                 .attr("value", self.pending_item.to_owned())
@@ -425,6 +418,7 @@ impl Item {
             e.attr("class", "editing")
                 .push(Dom::elem("input")
                     .attr("class", "edit")
+                    .node_ref("edit")
                     .attr("value", pending_edit)
                     .on("input", InputValue(|s| {
                         Some(Message::UpdateEdit(s))
@@ -506,7 +500,8 @@ pub fn start() -> Result<(), JsValue> {
         .router(Router::default())
         .attach(parent, Todo::with_items(items));
 
-    Command::FocusPending.process(&app.into());
+    // process the command through the app so it can look up refs
+    Application::process(&**app.borrow(), Command::FocusPending, &Dispatcher::from(&app));
 
     info!("{} initialized", TITLE);
     
@@ -0,0 +1,603 @@
+//! Parsing and expansion of the `html!` macro.
+
+use proc_macro2::{Span, TokenStream};
+use quote::{quote, quote_spanned, ToTokens};
+use syn::ext::IdentExt;
+use syn::parse::{Parse, ParseStream};
+use syn::punctuated::Punctuated;
+use syn::spanned::Spanned;
+use syn::{braced, parenthesized, token, Expr, ExprCall, ExprPath, Ident, Lit, LitStr, Path, Token};
+
+/// The input to the `html!` macro, one or more nodes.
+pub struct Html {
+    nodes: Vec<Node>,
+}
+
+/// A node in the markup.
+enum Node {
+    /// An html element, e.g. `<div class="x">...</div>`.
+    Element(Element),
+    /// A component, e.g. `<Counter msg={Msg::Init} />`.
+    Component(Box<Component>),
+    /// A text literal, e.g. `"text"`.
+    Text(LitStr),
+    /// A rust expression that can be converted into a dom node, e.g. `{item.name()}`.
+    Expr(Expr),
+    /// A rust expression producing an iterator of dom nodes, e.g. `{..items}`.
+    Splice(Expr),
+}
+
+struct Element {
+    name: Name,
+    attributes: Vec<Attribute>,
+    children: Vec<Node>,
+}
+
+struct Component {
+    path: Path,
+    msg: Expr,
+    key: Option<Expr>,
+}
+
+/// An element or attribute name, possibly containing dashes (e.g. `data-id`).
+struct Name {
+    value: String,
+    span: Span,
+}
+
+enum Attribute {
+    /// A plain attribute, e.g. `class="x"`.
+    Attr { name: Name, value: TokenStream },
+    /// An event that dispatches a message, e.g. `onclick=Msg::Click`.
+    Event { trigger: Name, msg: Expr },
+    /// An event with a handler, e.g. `on:input={Handler::InputValue(f)}`.
+    Handler { trigger: Name, handler: Expr },
+    /// A property, e.g. `prop:indeterminate={true}`.
+    Property { name: Name, value: Expr },
+    /// The key of the element, e.g. `key={item.id}`.
+    Key(Expr),
+}
+
+/// Returns true if the given element is a void element, meaning it has no closing tag and cannot
+/// contain children.
+fn is_void(name: &str) -> bool {
+    matches!(name,
+        "area" | "base" | "br" | "col" | "embed" | "hr" | "img" | "input"
+        | "link" | "meta" | "param" | "source" | "track" | "wbr"
+    )
+}
+
+/// Returns true if the children of the given element are svg or MathML elements, given whether
+/// the element itself is one.
+///
+/// Inside svg and MathML there are no void elements and any element can be self closing. Some
+/// elements (e.g. `foreignObject`) switch back to html for their children.
+fn is_foreign(name: &str, foreign: bool) -> bool {
+    match name {
+        "svg" | "math" => true,
+        "foreignObject" | "desc" | "title" | "mi" | "mo" | "mn" | "ms" | "mtext" => false,
+        _ => foreign,
+    }
+}
+
+impl Parse for Html {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut nodes = vec![];
+        while !input.is_empty() {
+            if input.peek(Token![<]) && input.peek2(Token![/]) {
+                input.parse::<Token![<]>()?;
+                input.parse::<Token![/]>()?;
+                let name: Name = input.parse()?;
+                return Err(syn::Error::new(name.span, format!("unexpected closing tag `</{}>`", name.value)));
+            }
+            nodes.push(input.parse()?);
+        }
+
+        if nodes.is_empty() {
+            return Err(input.error("expected at least one node"));
+        }
+
+        Ok(Html { nodes })
+    }
+}
+
+impl Parse for Node {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Node::parse_in(input, false)
+    }
+}
+
+impl Node {
+    /// Parse a node, `foreign` is true if the node is inside an svg or MathML element.
+    fn parse_in(input: ParseStream, foreign: bool) -> syn::Result<Self> {
+        if input.peek(Token![<]) {
+            let fork = input.fork();
+            fork.parse::<Token![<]>()?;
+            if is_component(&fork) {
+                input.parse().map(|c| Node::Component(Box::new(c)))
+            }
+            else {
+                Element::parse_in(input, foreign).map(Node::Element)
+            }
+        }
+        else if input.peek(LitStr) {
+            input.parse().map(Node::Text)
+        }
+        else if input.peek(token::Brace) {
+            let content;
+            braced!(content in input);
+            if content.peek(Token![..]) {
+                content.parse::<Token![..]>()?;
+                content.parse().map(Node::Splice)
+            }
+            else {
+                content.parse().map(Node::Expr)
+            }
+        }
+        else {
+            Err(input.error("expected an element, a text literal, or a `{...}` expression"))
+        }
+    }
+}
+
+/// Components are named with a path to their create function, starting with an uppercase letter
+/// or containing `::`.
+fn is_component(input: ParseStream) -> bool {
+    if input.peek(Token![::]) {
+        return true;
+    }
+
+    match input.call(Ident::parse_any) {
+        Ok(ident) => {
+            input.peek(Token![::])
+            || ident.to_string().starts_with(|c: char| c.is_ascii_uppercase())
+        }
+        Err(_) => false,
+    }
+}
+
+impl Parse for Name {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let ident = input.call(Ident::parse_any)?;
+        let span = ident.span();
+        let mut value = ident.to_string();
+
+        while input.peek(Token![-]) {
+            input.parse::<Token![-]>()?;
+            value.push('-');
+            value.push_str(&input.call(Ident::parse_any)?.to_string());
+        }
+
+        Ok(Name { value, span })
+    }
+}
+
+impl Parse for Element {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        Element::parse_in(input, false)
+    }
+}
+
+impl Element {
+    /// Parse an element, `foreign` is true if the element is inside an svg or MathML element.
+    fn parse_in(input: ParseStream, foreign: bool) -> syn::Result<Self> {
+        input.parse::<Token![<]>()?;
+        let name: Name = input.parse()?;
+
+        // svg and MathML elements have no void elements and can always be self closing
+        let foreign_self = foreign || is_foreign(&name.value, foreign);
+        let foreign_children = is_foreign(&name.value, foreign_self);
+        let void = |name: &str| !foreign_self && is_void(name);
+
+        let mut attributes = vec![];
+        while !(input.peek(Token![>]) || input.peek(Token![/])) {
+            if input.is_empty() {
+                return Err(syn::Error::new(name.span, format!("unclosed start tag `<{}`", name.value)));
+            }
+            attributes.push(input.parse()?);
+        }
+
+        if input.peek(Token![/]) {
+            input.parse::<Token![/]>()?;
+            input.parse::<Token![>]>()?;
+
+            if !foreign_self && !is_void(&name.value) {
+                return Err(syn::Error::new(name.span, format!(
+                    "`{0}` is not a void element and can't be self closing, use `<{0}></{0}>`",
+                    name.value,
+                )));
+            }
+
+            return Ok(Element { name, attributes, children: vec![] });
+        }
+
+        input.parse::<Token![>]>()?;
+
+        // void elements have no contents or closing tag
+        if void(&name.value) {
+            return Ok(Element { name, attributes, children: vec![] });
+        }
+
+        let mut children = vec![];
+        loop {
+            if input.is_empty() {
+                return Err(syn::Error::new(name.span, format!("unclosed element `<{}>`", name.value)));
+            }
+
+            if input.peek(Token![<]) && input.peek2(Token![/]) {
+                break;
+            }
+
+            children.push(Node::parse_in(input, foreign_children)?);
+        }
+
+        input.parse::<Token![<]>()?;
+        input.parse::<Token![/]>()?;
+        let close: Name = input.parse()?;
+        input.parse::<Token![>]>()?;
+
+        if close.value != name.value {
+            let mut error = if void(&close.value) {
+                syn::Error::new(close.span, format!("`{}` is a void element and has no closing tag", close.value))
+            }
+            else {
+                syn::Error::new(close.span, format!("expected `</{}>`, found `</{}>`", name.value, close.value))
+            };
+            error.combine(syn::Error::new(name.span, format!("unclosed element `<{}>`", name.value)));
+            return Err(error);
+        }
+
+        Ok(Element { name, attributes, children })
+    }
+}
+
+impl Parse for Component {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        input.parse::<Token![<]>()?;
+        let path: Path = input.call(Path::parse_mod_style)?;
+
+        let mut msg = None;
+        let mut key = None;
+        while !(input.peek(Token![>]) || input.peek(Token![/])) {
+            if input.is_empty() {
+                return Err(syn::Error::new(path.span(), "unclosed component tag"));
+            }
+
+            let name: Name = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let value = parse_expr(input)?;
+            match name.value.as_str() {
+                "msg" => msg = Some(value),
+                "key" => key = Some(value),
+                _ => return Err(syn::Error::new(name.span, "components only accept `msg` and `key` attributes")),
+            }
+        }
+
+        if input.peek(Token![>]) {
+            return Err(syn::Error::new(path.span(), "components can't have children and must be self closing"));
+        }
+        input.parse::<Token![/]>()?;
+        input.parse::<Token![>]>()?;
+
+        let msg = msg.ok_or_else(|| syn::Error::new(path.span(), "components require a `msg` attribute"))?;
+
+        Ok(Component { path, msg, key })
+    }
+}
+
+impl Parse for Attribute {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let name: Name = input.parse()?;
+
+        if name.value == "on" && input.peek(Token![:]) && !input.peek(Token![::]) {
+            input.parse::<Token![:]>()?;
+            let trigger = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let handler = parse_expr(input)?;
+            return Ok(Attribute::Handler { trigger, handler });
+        }
+
+        if name.value == "prop" && input.peek(Token![:]) && !input.peek(Token![::]) {
+            input.parse::<Token![:]>()?;
+            let name = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let value = parse_expr(input)?;
+            return Ok(Attribute::Property { name, value });
+        }
+
+        // namespaced attributes, e.g. `xlink:href`
+        let name = if input.peek(Token![:]) && !input.peek(Token![::]) {
+            input.parse::<Token![:]>()?;
+            let local: Name = input.parse()?;
+            Name {
+                value: format!("{}:{}", name.value, local.value),
+                span: name.span,
+            }
+        }
+        else {
+            name
+        };
+
+        if name.value == "key" {
+            input.parse::<Token![=]>()?;
+            return parse_expr(input).map(Attribute::Key);
+        }
+
+        if name.value.len() > 2 && name.value.starts_with("on") {
+            input.parse::<Token![=]>()?;
+            let msg = parse_expr(input)?;
+            let trigger = Name {
+                value: name.value[2..].into(),
+                span: name.span,
+            };
+            return Ok(Attribute::Event { trigger, msg });
+        }
+
+        // attributes without a value (e.g. `disabled`) are set to "true"
+        if !input.peek(Token![=]) {
+            let value = LitStr::new("true", name.span).into_token_stream();
+            return Ok(Attribute::Attr { name, value });
+        }
+
+        input.parse::<Token![=]>()?;
+        let value = if input.peek(token::Brace) {
+            let content;
+            braced!(content in input);
+            content.parse::<Expr>()?.into_token_stream()
+        }
+        else if input.peek(Lit) {
+            match input.parse()? {
+                Lit::Str(s) => s.into_token_stream(),
+                lit @ Lit::Int(_) | lit @ Lit::Float(_) | lit @ Lit::Bool(_) => {
+                    LitStr::new(&lit.to_token_stream().to_string(), lit.span()).into_token_stream()
+                }
+                lit => return Err(syn::Error::new(lit.span(), "unsupported attribute value")),
+            }
+        }
+        else {
+            return Err(input.error("attribute values must be literals or `{...}` expressions"));
+        };
+
+        Ok(Attribute::Attr { name, value })
+    }
+}
+
+/// Parse an attribute value that is a rust expression. This is either a `{...}` block, a
+/// literal, or a path optionally followed by arguments (e.g. `Msg::Edit(id)`).
+fn parse_expr(input: ParseStream) -> syn::Result<Expr> {
+    if input.peek(token::Brace) {
+        let content;
+        braced!(content in input);
+        return content.parse();
+    }
+
+    if input.peek(Lit) {
+        return input.parse().map(Expr::Lit);
+    }
+
+    let path: ExprPath = input.parse()?;
+    if !input.peek(token::Paren) {
+        return Ok(Expr::Path(path));
+    }
+
+    let content;
+    let paren_token = parenthesized!(content in input);
+    let args = Punctuated::parse_terminated(&content)?;
+    Ok(Expr::Call(ExprCall {
+        attrs: vec![],
+        func: Box::new(Expr::Path(path)),
+        paren_token,
+        args,
+    }))
+}
+
+impl ToTokens for Html {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        match &self.nodes[..] {
+            [Node::Splice(expr)] => tokens.extend(quote! {
+                ::euca::dom::DomVec::from(::std::iter::Iterator::collect::<::std::vec::Vec<_>>(
+                    ::std::iter::IntoIterator::into_iter(#expr)
+                ))
+            }),
+            [node] => node.to_tokens(tokens),
+            nodes => {
+                let nodes = nodes.iter().map(|node| match node {
+                    Node::Splice(expr) => quote! { #expr },
+                    node => quote! { ::std::iter::once(#node) },
+                });
+                tokens.extend(quote! {
+                    ::euca::dom::DomVec::from(::std::iter::Iterator::collect::<::std::vec::Vec<_>>(
+                        ::std::iter::empty()
+                            #(.chain(#nodes))*
+                    ))
+                });
+            }
+        }
+    }
+}
+
+impl ToTokens for Node {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        match self {
+            Node::Element(element) => element.to_tokens(tokens),
+            Node::Component(component) => component.to_tokens(tokens),
+            Node::Text(text) => tokens.extend(quote_spanned! { text.span() =>
+                ::euca::dom::Dom::text(#text)
+            }),
+            Node::Expr(expr) => tokens.extend(quote_spanned! { expr.span() =>
+                ::std::convert::Into::<::euca::dom::Dom<_, _, _>>::into(#expr)
+            }),
+            Node::Splice(expr) => tokens.extend(quote_spanned! { expr.span() =>
+                ::std::iter::IntoIterator::into_iter(#expr)
+            }),
+        }
+    }
+}
+
+impl ToTokens for Element {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let name = LitStr::new(&self.name.value, self.name.span);
+        let attributes = &self.attributes;
+        let children = self.children.iter().map(|child| match child {
+            Node::Splice(expr) => quote_spanned! { expr.span() => .extend(#expr) },
+            child => quote! { .push(#child) },
+        });
+
+        tokens.extend(quote_spanned! { self.name.span =>
+            ::euca::dom::Dom::elem(#name)
+                #(#attributes)*
+                #(#children)*
+        });
+    }
+}
+
+impl ToTokens for Component {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        let Component { path, msg, key } = self;
+        let key = key.iter().map(|key| quote_spanned! { key.span() => .key(#key) });
+
+        tokens.extend(quote_spanned! { path.span() =>
+            ::euca::dom::Dom::component(#msg, #path)
+                #(#key)*
+        });
+    }
+}
+
+impl ToTokens for Attribute {
+    fn to_tokens(&self, tokens: &mut TokenStream) {
+        match self {
+            Attribute::Attr { name, value } => {
+                let name_lit = LitStr::new(&name.value, name.span);
+                tokens.extend(quote_spanned! { name.span => .attr(#name_lit, #value) });
+            }
+            Attribute::Event { trigger, msg } => {
+                let trigger_lit = LitStr::new(&trigger.value, trigger.span);
+                tokens.extend(quote_spanned! { trigger.span => .event(#trigger_lit, #msg) });
+            }
+            Attribute::Handler { trigger, handler } => {
+                let trigger_lit = LitStr::new(&trigger.value, trigger.span);
+                tokens.extend(quote_spanned! { trigger.span => .on(#trigger_lit, #handler) });
+            }
+            Attribute::Property { name, value } => {
+                let name_lit = LitStr::new(&name.value, name.span);
+                tokens.extend(quote_spanned! { name.span => .property(#name_lit, #value) });
+            }
+            Attribute::Key(key) => {
+                tokens.extend(quote_spanned! { key.span() => .key(#key) });
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn error(input: &str) -> String {
+        match syn::parse_str::<Html>(input) {
+            Ok(_) => panic!("expected an error parsing: {}", input),
+            Err(e) => e.to_string(),
+        }
+    }
+
+    fn expand(input: &str) -> String {
+        syn::parse_str::<Html>(input)
+            .unwrap()
+            .into_token_stream()
+            .to_string()
+    }
+
+    #[test]
+    fn nested() {
+        assert_eq!(
+            expand(r#"<div class="x" data-id=1 disabled><span>"text"</span></div>"#),
+            quote! {
+                ::euca::dom::Dom::elem("div")
+                    .attr("class", "x")
+                    .attr("data-id", "1")
+                    .attr("disabled", "true")
+                    .push(::euca::dom::Dom::elem("span")
+                        .push(::euca::dom::Dom::text("text")))
+            }.to_string(),
+        );
+    }
+
+    #[test]
+    fn events_and_keys() {
+        assert_eq!(
+            expand(r#"<li key={id} onclick=Msg::Edit(id) on:input={handler}><br></li>"#),
+            quote! {
+                ::euca::dom::Dom::elem("li")
+                    .key(id)
+                    .event("click", Msg::Edit(id))
+                    .on("input", handler)
+                    .push(::euca::dom::Dom::elem("br"))
+            }.to_string(),
+        );
+    }
+
+    #[test]
+    fn properties() {
+        assert_eq!(
+            expand(r#"<input type="checkbox" prop:indeterminate=true prop:scrollTop={offset}>"#),
+            quote! {
+                ::euca::dom::Dom::elem("input")
+                    .attr("type", "checkbox")
+                    .property("indeterminate", true)
+                    .property("scrollTop", offset)
+            }.to_string(),
+        );
+    }
+
+    #[test]
+    fn component() {
+        assert_eq!(
+            expand(r#"<counter::Counter msg={Msg::Init} key=1 />"#),
+            quote! {
+                ::euca::dom::Dom::component(Msg::Init, counter::Counter).key(1)
+            }.to_string(),
+        );
+    }
+
+    #[test]
+    fn unbalanced() {
+        assert_eq!(error("<div><span></div>"), "expected `</span>`, found `</div>`");
+        assert_eq!(error("<div>"), "unclosed element `<div>`");
+        assert_eq!(error("<div></input>"), "`input` is a void element and has no closing tag");
+        assert_eq!(error("<br></br>"), "unexpected closing tag `</br>`");
+    }
+
+    #[test]
+    fn void() {
+        assert_eq!(
+            error("<div />"),
+            "`div` is not a void element and can't be self closing, use `<div></div>`",
+        );
+        assert_eq!(expand("<input />"), expand("<input>"));
+    }
+
+    #[test]
+    fn svg() {
+        assert_eq!(
+            expand(r##"<svg viewBox="0 0 10 10"><circle r=5 /><use xlink:href="#dot" /></svg>"##),
+            quote! {
+                ::euca::dom::Dom::elem("svg")
+                    .attr("viewBox", "0 0 10 10")
+                    .push(::euca::dom::Dom::elem("circle")
+                        .attr("r", "5"))
+                    .push(::euca::dom::Dom::elem("use")
+                        .attr("xlink:href", "#dot"))
+            }.to_string(),
+        );
+        assert_eq!(
+            error("<svg><foreignObject><div /></foreignObject></svg>"),
+            "`div` is not a void element and can't be self closing, use `<div></div>`",
+        );
+    }
+
+    #[test]
+    fn component_errors() {
+        assert_eq!(error("<Counter />"), "components require a `msg` attribute");
+        assert_eq!(error("<Counter msg=1></Counter>"), "components can't have children and must be self closing");
+    }
+}
@@ -0,0 +1,25 @@
+//! Procedural macros for [euca].
+//!
+//! These are re-exported by euca and should be used from there.
+//!
+//! [euca]: https://docs.rs/euca
+
+#![deny(missing_docs)]
+
+extern crate proc_macro;
+
+mod html;
+
+use proc_macro::TokenStream;
+use quote::ToTokens;
+use syn::parse_macro_input;
+
+/// Build a `euca::dom::Dom` tree from html-like markup.
+///
+/// See the documentation of `euca::html` for the supported syntax.
+#[proc_macro]
+pub fn html(input: TokenStream) -> TokenStream {
+    parse_macro_input!(input as html::Html)
+        .into_token_stream()
+        .into()
+}
@@ -14,12 +14,18 @@ pub mod model;
 pub mod dispatch;
 pub mod side_effect;
 pub mod application;
+pub mod task;
+pub mod subscription;
+pub mod history;
 
 pub use crate::app::detach::Detach;
-pub use crate::app::model::{Update, Render};
+pub use crate::app::model::{Update, Subscriptions, Render};
 pub use crate::app::dispatch::Dispatcher;
 pub use crate::app::side_effect::{SideEffect, Processor, Commands};
 pub use crate::app::application::{Application, ScheduledRender};
+pub use crate::app::task::Task;
+pub use crate::app::subscription::{Subscription, SubscriptionSet};
+pub use crate::app::history::{History, MessageLog, Record};
 
 use web_sys;
 use wasm_bindgen::prelude::*;
@@ -33,9 +39,20 @@ use crate::vdom::DomIter;
 use crate::vdom::Storage;
 use crate::vdom::WebItem;
 use crate::route::Route;
+use crate::app::task::Tasks;
+use crate::app::dispatch::Refs;
+use crate::app::subscription::Subscribed;
+use crate::backend::{Backend, Web, Delegated};
+
+/// A newly created app, its top level nodes, and the dispatcher its lifecycle hooks are queued on.
+pub(crate) type Created<Message, Command> = (
+    Rc<RefCell<Box<dyn Application<Message, Command>>>>,
+    Vec<web_sys::Node>,
+    Dispatcher<Message, Command>,
+);
 
 /// Struct used to configure and attach an application to the DOM.
-pub struct AppBuilder<Message, Command, Processor, Router>
+pub struct AppBuilder<Message, Command, Processor, Router, Recorder = ()>
 where
     Command: SideEffect<Message>,
     Processor: side_effect::Processor<Message, Command>,
@@ -44,6 +61,8 @@ where
     router: Option<Rc<Router>>,
     processor: Processor,
     clear_parent: bool,
+    delegate_events: bool,
+    history: Recorder,
     message: std::marker::PhantomData<Message>,
     command: std::marker::PhantomData<Command>,
 }
@@ -63,14 +82,16 @@ where
             router: None,
             processor: side_effect::DefaultProcessor::default(),
             clear_parent: false,
+            delegate_events: false,
+            history: (),
             message: std::marker::PhantomData,
             command: std::marker::PhantomData,
         }
     }
 }
 
-impl<Message, Command, Processor, Router>
-AppBuilder<Message, Command, Processor, Router>
+impl<Message, Command, Processor, Router, Recorder>
+AppBuilder<Message, Command, Processor, Router, Recorder>
 where
     Command: SideEffect<Message> + 'static,
     Processor: side_effect::Processor<Message, Command> + 'static,
@@ -82,12 +103,14 @@ where
     ///
     /// [`Route`]: ../route/trait.Route.html
     #[must_use]
-    pub fn router<R: Route<Message>>(self, router: R) -> AppBuilder<Message, Command, Processor, R> {
+    pub fn router<R: Route<Message>>(self, router: R) -> AppBuilder<Message, Command, Processor, R, Recorder> {
         let AppBuilder {
             message,
             command,
             processor,
             clear_parent,
+            delegate_events,
+            history,
             router: _router,
         } = self;
 
@@ -96,18 +119,22 @@ where
             command: command,
             processor,
             clear_parent: clear_parent,
+            delegate_events,
+            history,
             router: Some(Rc::new(router)),
         }
     }
 
     /// Process side-effecting commands.
     #[must_use]
-    pub(crate) fn processor<P: side_effect::Processor<Message, Command>>(self, processor: P) -> AppBuilder<Message, Command, P, Router> {
+    pub(crate) fn processor<P: side_effect::Processor<Message, Command>>(self, processor: P) -> AppBuilder<Message, Command, P, Router, Recorder> {
         let AppBuilder {
             message,
             command,
             router,
             clear_parent,
+            delegate_events,
+            history,
             processor: _processor,
         } = self;
 
@@ -117,6 +144,8 @@ where
             processor: processor,
             router: router,
             clear_parent: clear_parent,
+            delegate_events,
+            history,
         }
     }
 
@@ -130,26 +159,102 @@ where
         self
     }
 
+    /// Handle the app's events with one listener per event type on the parent element, instead of
+    /// a listener on each element with a handler.
+    ///
+    /// Handlers are looked up by walking from the event's target up to the parent. See the
+    /// [`delegated`] backend for how this differs from registering listeners directly.
+    ///
+    /// Events are only delegated for apps with a parent element, attached with [`attach`] or
+    /// [`hydrate`]. Components are created without one, so they ignore this setting and register
+    /// a listener on each element with a handler.
+    ///
+    /// [`delegated`]: ../backend/delegated/index.html
+    /// [`attach`]: #method.attach
+    /// [`hydrate`]: #method.hydrate
+    #[must_use]
+    pub fn delegate_events(mut self) -> Self {
+        self.delegate_events = true;
+        self
+    }
+
+    /// Record the messages processed by the app, and snapshots of its model, in the given
+    /// [`History`].
+    ///
+    /// The history must be for the same model and message types as the app, otherwise attaching
+    /// the app doesn't compile.
+    ///
+    /// [`History`]: history/struct.History.html
+    #[must_use]
+    pub fn history<Model>(self, history: &History<Model, Message>)
+    -> AppBuilder<Message, Command, Processor, Router, History<Model, Message>>
+    {
+        let AppBuilder {
+            message,
+            command,
+            router,
+            processor,
+            clear_parent,
+            delegate_events,
+            history: _history,
+        } = self;
+
+        AppBuilder {
+            message,
+            command,
+            router,
+            processor,
+            clear_parent,
+            delegate_events,
+            history: history.clone(),
+        }
+    }
+
     /// Create an app, but don't attach it yet.
     ///
     /// Initialize everything, but don't actually attach the app to the dom. Instead return all of
-    /// the top level nodes.
+    /// the top level nodes. Lifecycle hooks of the initial render are left queued on the returned
+    /// dispatcher, run them with [`run_hooks`] once the nodes are attached. Without a parent
+    /// element to listen on, events are never delegated.
+    ///
+    /// [`run_hooks`]: fn.run_hooks.html
     #[must_use]
-    pub(crate) fn create<Model, DomTree, Key>(self, mut model: Model)
-    -> (Rc<RefCell<Box<dyn Application<Message, Command>>>>, Vec<web_sys::Node>)
+    pub(crate) fn create<Model, DomTree, Key>(self, model: Model)
+    -> Created<Message, Command>
     where
-        Model: Update<Message, Command> + Render<DomTree> + 'static,
+        Model: Update<Message, Command> + Subscriptions<Message> + Render<DomTree> + 'static,
         DomTree: DomIter<Message, Command, Key> + 'static,
         Message: fmt::Debug + Clone + PartialEq + 'static,
         Command: SideEffect<Message> + fmt::Debug + 'static,
         Key: Eq + Hash + 'static,
+        Recorder: Record<Model, Message>,
+    {
+        self.initialize(model, None, false)
+    }
+
+    /// Initialize everything, either creating new dom nodes or hydrating the existing children
+    /// of the given parent. Lifecycle hooks of the initial render are left queued on the returned
+    /// dispatcher.
+    fn initialize<Model, DomTree, Key>(self, mut model: Model, parent: Option<&web_sys::Element>, hydrate: bool)
+    -> Created<Message, Command>
+    where
+        Model: Update<Message, Command> + Subscriptions<Message> + Render<DomTree> + 'static,
+        DomTree: DomIter<Message, Command, Key> + 'static,
+        Message: fmt::Debug + Clone + PartialEq + 'static,
+        Command: SideEffect<Message> + fmt::Debug + 'static,
+        Key: Eq + Hash + 'static,
+        Recorder: Record<Model, Message>,
     {
         let AppBuilder {
             router,
             processor,
+            history,
+            delegate_events,
             ..
         } = self;
 
+        let history = history.into_history();
+
         let mut commands = Commands::default();
 
         if let Some(ref router) = router {
@@ -166,30 +271,27 @@ where
             }
         }
 
+        let router = router.map(|router| router as Rc<dyn Route<Message>>);
+
         // create the app
-        let (app_rc, nodes) = App::create(model, processor);
-        let dispatcher = Dispatcher::from(&app_rc);
+        let (app_rc, nodes, dispatcher) = match parent {
+            Some(parent) if delegate_events => {
+                let backend = Delegated::new(parent.clone());
+                App::create(model, processor, router.clone(), history, backend, Some(parent), hydrate)
+            }
+            _ => App::create(model, processor, router.clone(), history, Web::default(), parent, hydrate),
+        };
 
-        if let Some(ref router) = router {
+        if router.is_some() {
             let window = web_sys::window()
                 .expect("couldn't get window handle");
 
-            let document = window.document()
-                .expect("couldn't get document handle");
-
             // register event handlers
             for event in ["popstate", "hashchange"].iter() {
                 let dispatcher = dispatcher.clone();
-                let document = document.clone();
-                let router = router.clone();
                 let closure = Closure::wrap(
                     Box::new(move |_event| {
-                        let url = document.url()
-                            .expect_throw("couldn't get document url");
-
-                        if let Some(msg) = router.route(&url) {
-                            dispatcher.dispatch(msg);
-                        }
+                        dispatcher.route();
                     }) as Box<dyn FnMut(web_sys::Event)>
                 );
 
@@ -209,7 +311,7 @@ where
             }
         }
 
-        (app_rc, nodes)
+        (app_rc, nodes, dispatcher)
     }
 
     /// Attach an app to the dom.
@@ -219,11 +321,12 @@ where
     pub fn attach<Model, DomTree, Key>(self, parent: web_sys::Element, model: Model)
     -> Rc<RefCell<Box<dyn Application<Message, Command>>>>
     where
-        Model: Update<Message, Command> + Render<DomTree> + 'static,
+        Model: Update<Message, Command> + Subscriptions<Message> + Render<DomTree> + 'static,
         DomTree: DomIter<Message, Command, Key> + 'static,
         Message: fmt::Debug + Clone + PartialEq + 'static,
         Command: SideEffect<Message> + fmt::Debug + 'static,
         Key: Eq + Hash + 'static,
+        Recorder: Record<Model, Message>,
     {
         if self.clear_parent {
             // remove all children of our parent element
@@ -234,7 +337,7 @@ where
         }
 
         // create the app
-        let (app_rc, nodes) = self.create(model);
+        let (app_rc, nodes, dispatcher) = self.initialize(model, Some(&parent), false);
 
         // attach this app to the dom
         for node in nodes.iter() {
@@ -242,28 +345,70 @@ where
                 .expect("failed to append child to parent element");
         }
 
+        run_hooks(&app_rc, &dispatcher);
+        app_rc
+    }
+
+    /// Attach an app to existing markup in the dom.
+    ///
+    /// Instead of creating new nodes, the existing children of the given parent node (for example
+    /// markup pre-rendered on a server with [`ssr::render`]) are adopted by the app, which is
+    /// initialized with the given model. Event handlers will be registered as necessary. Any
+    /// differences between the existing children and the initial render of the app are repaired
+    /// and logged as warnings.
+    ///
+    /// [`ssr::render`]: ../ssr/fn.render.html
+    pub fn hydrate<Model, DomTree, Key>(self, parent: web_sys::Element, model: Model)
+    -> Rc<RefCell<Box<dyn Application<Message, Command>>>>
+    where
+        Model: Update<Message, Command> + Subscriptions<Message> + Render<DomTree> + 'static,
+        DomTree: DomIter<Message, Command, Key> + 'static,
+        Message: fmt::Debug + Clone + PartialEq + 'static,
+        Command: SideEffect<Message> + fmt::Debug + 'static,
+        Key: Eq + Hash + 'static,
+        Recorder: Record<Model, Message>,
+    {
+        let (app_rc, _, dispatcher) = self.initialize(model, Some(&parent), true);
+        run_hooks(&app_rc, &dispatcher);
         app_rc
     }
 }
 
-impl<Model, DomTree, Processor, Message, Command, Key>
+impl<Model, DomTree, Processor, Message, Command, Key, B>
 Application<Message, Command>
-for App<Model, DomTree, Processor, Message, Command, Key>
+for App<Model, DomTree, Processor, Message, Command, Key, B>
 where
-    Model: Update<Message, Command> + Render<DomTree> + 'static,
+    Model: Update<Message, Command> + Subscriptions<Message> + Render<DomTree> + 'static,
     Command: SideEffect<Message> + fmt::Debug + 'static,
     Processor: side_effect::Processor<Message, Command> + 'static,
     Message: fmt::Debug + Clone + PartialEq + 'static,
     DomTree: DomIter<Message, Command, Key> + 'static,
     Key: Eq + Hash + 'static,
+    B: Backend<Node = web_sys::Node, Element = web_sys::Element, Text = web_sys::Text> + 'static,
 {
     fn update(&mut self, msg: Message) -> Commands<Command> {
+        self.restore();
+
         // update the model
         let mut commands = Commands::default();
-        self.model.update(msg, &mut commands);
+        match self.history {
+            Some(ref history) => {
+                let recorded = msg.clone();
+                self.model.update(msg, &mut commands);
+                history.record(recorded, &self.model);
+            }
+            None => self.model.update(msg, &mut commands),
+        }
         commands
     }
 
+    fn subscribe(&mut self, app: &Dispatcher<Message, Command>) {
+        self.restore();
+
+        let subscriptions = self.model.subscriptions();
+        self.subscriptions.update(subscriptions, app);
+    }
+
     fn get_scheduled_render(&mut self) -> &mut Option<ScheduledRender<Command>> {
         &mut self.animation_frame_handle
     }
@@ -273,15 +418,18 @@ where
     }
 
     fn render(&mut self, app_rc: &Dispatcher<Message, Command>) -> Vec<Command> {
-        let parent = self.node()
-            .expect("empty app?")
-            .parent_element()
-            .expect("app not attached to the dom");
+        self.restore();
+
+        // elements with refs are recorded in our ref table
+        let app_rc = &app_rc.clone().with_refs(Rc::clone(&self.refs));
+
+        let parent = self.parent();
 
         let App {
             ref mut model,
             ref mut storage,
             ref dom,
+            ref backend,
             ..
         } = *self;
 
@@ -292,16 +440,17 @@ where
         let old = dom.dom_iter();
         let new = new_dom.dom_iter();
         let patch_set = diff::diff(old, new, storage);
-        self.storage = patch_set.apply(&parent, app_rc);
+        self.storage = patch_set.apply_with(backend, &parent, app_rc);
 
         self.dom = new_dom;
 
-        let commands;
+        // commands from lifecycle hooks are processed first, their messages are processed after
+        // rendering is done
+        let (messages, mut commands) = app_rc.run_hooks();
+        app_rc.dispatch_batch(messages);
+
         if let Some((cmds, _, _)) = self.animation_frame_handle.take() {
-            commands = cmds;
-        }
-        else {
-            commands = vec![];
+            commands.extend(cmds);
         }
 
         commands
@@ -311,7 +460,11 @@ where
     }
 
     fn process(&self, cmd: Command, app: &Dispatcher<Message, Command>) {
-        Processor::process(&self.processor, cmd, app);
+        // futures spawned by commands are canceled when this app is detached
+        let app = app.clone()
+            .with_tasks(Rc::clone(&self.tasks))
+            .with_refs(Rc::clone(&self.refs));
+        Processor::process(&self.processor, cmd, &app);
     }
 
     fn push_listener(&mut self, listener: (String, Closure<dyn FnMut(web_sys::Event)>)) {
@@ -321,15 +474,13 @@ where
     fn detach(&mut self, app: &Dispatcher<Message, Command>) {
         use std::iter;
 
-        let parent = self.node()
-            .expect("empty app?")
-            .parent_element()
-            .expect("app not attached to the dom");
+        let parent = self.parent();
 
         let App {
             ref mut storage,
             ref dom,
             ref mut listeners,
+            ref backend,
             ..
         } = *self;
 
@@ -343,37 +494,55 @@ where
                 .expect("failed to remove event listener");
         }
 
+        // cancel running futures
+        self.tasks.borrow_mut().cancel();
+
+        // remove subscriptions
+        self.subscriptions.clear();
+
         // remove the current app from the browser's dom by diffing it with an empty virtual dom.
         let o = dom.dom_iter();
         let patch_set = diff::diff(o, iter::empty(), storage);
-        self.storage = patch_set.apply(&parent, app);
+        self.storage = patch_set.apply_with(backend, &parent, app);
+
+        // forget the removed elements
+        self.refs.borrow_mut().clear();
+
+        // the app is gone, so only the commands from unmount hooks are processed
+        let (_, commands) = app.run_hooks();
+        for cmd in commands {
+            self.process(cmd, app);
+        }
     }
 
     fn node(&self) -> Option<web_sys::Node> {
-        self.storage.first()
-            .and_then(|item| -> Option<web_sys::Node> {
-                match item {
-                    WebItem::Element(ref node) => Some(node.clone().into()),
-                    WebItem::Text(ref node) => Some(node.clone().into()),
-                    WebItem::Component(component) => component.node(),
-                    i => panic!("unknown item, expected something with a node in it: {:?}", i)
-                }
-            })
+        // the first node may be in a fragment, or follow an empty fragment or a portal
+        self.nodes().into_iter().next()
     }
 
     fn nodes(&self) -> Vec<web_sys::Node> {
         let mut nodes = vec![];
         let mut depth = 0;
+        // the depth at the start of each fragment we are in, the nodes in a fragment are at the
+        // same depth as the fragment
+        let mut fragments = vec![];
         for item in &self.storage {
             match item {
                 // ignore nodes that are not top level
                 WebItem::Element(_)
                 | WebItem::Text(_)
                 | WebItem::Component(_)
+                | WebItem::Portal(_)
                 if depth > 0
                 => {
                     depth += 1;
                 }
+                // the nodes in a portal are not in our parent
+                WebItem::Portal(_) => depth += 1,
+                WebItem::Fragment => fragments.push(depth),
+                WebItem::Up if fragments.last() == Some(&depth) => {
+                    fragments.pop();
+                }
                 WebItem::Up => depth -= 1,
                 WebItem::Closure(_) => {}
                 WebItem::Element(ref node) => {
@@ -398,67 +567,136 @@ where
         // render the initial app
         use std::iter;
 
+        let app = &app.clone().with_refs(Rc::clone(&self.refs));
+
         let App {
             ref mut storage,
             ref dom,
+            ref backend,
             ..
         } = *self;
 
         let n = dom.dom_iter();
         let patch_set = diff::diff(iter::empty(), n, storage);
 
-        let (storage, pending) = patch_set.prepare(app);
+        let (storage, pending) = patch_set.prepare_with(backend, app);
         self.storage = storage;
         pending
     }
+
+    fn hydrate(&mut self, parent: &web_sys::Element, app: &Dispatcher<Message, Command>) {
+        use std::iter;
+
+        let app = &app.clone().with_refs(Rc::clone(&self.refs));
+
+        let App {
+            ref mut storage,
+            ref dom,
+            ref backend,
+            ..
+        } = *self;
+
+        let n = dom.dom_iter();
+        let patch_set = diff::diff(iter::empty(), n, storage);
+
+        self.storage = patch_set.hydrate_with(backend, parent, app);
+    }
+}
+
+/// Run the lifecycle hooks queued on the given dispatcher while creating or hydrating an app.
+///
+/// Commands are processed first, then messages are dispatched, like after rendering.
+pub(crate) fn run_hooks<Message, Command>(
+    app: &Rc<RefCell<Box<dyn Application<Message, Command>>>>,
+    dispatcher: &Dispatcher<Message, Command>,
+) where
+    Message: fmt::Debug + Clone + PartialEq + 'static,
+    Command: SideEffect<Message> + 'static,
+{
+    let (messages, commands) = dispatcher.run_hooks();
+    for cmd in commands {
+        app.borrow().process(cmd, dispatcher);
+    }
+    dispatcher.dispatch_batch(messages);
 }
 
 /// A wasm application consisting of a model, a virtual dom representation, and the parent element
 /// where this app lives in the dom.
-struct App<Model, DomTree, Processor, Message, Command, Key>
+struct App<Model, DomTree, Processor, Message, Command, Key, B>
 where
     Command: SideEffect<Message>,
     Processor: side_effect::Processor<Message, Command>,
+    B: Backend,
 {
     dom: DomTree,
     model: Model,
-    storage: Storage<Message>,
+    storage: Storage<Message, B>,
+    backend: B,
+    /// The element the app is attached to, `None` for components.
+    parent: Option<web_sys::Element>,
     listeners: Vec<(String, Closure<dyn FnMut(web_sys::Event)>)>,
     animation_frame_handle: Option<ScheduledRender<Command>>,
     processor: Processor,
+    tasks: Rc<RefCell<Tasks>>,
+    refs: Rc<RefCell<Refs>>,
+    subscriptions: Subscribed<Message>,
+    history: Option<History<Model, Message>>,
     command: std::marker::PhantomData<Command>,
     key: std::marker::PhantomData<Key>,
 }
 
-impl<Model, DomTree, Processor, Message, Command, Key>
-App<Model, DomTree, Processor, Message, Command, Key>
+impl<Model, DomTree, Processor, Message, Command, Key, B>
+App<Model, DomTree, Processor, Message, Command, Key, B>
 where
     Command: SideEffect<Message>,
     Processor: side_effect::Processor<Message, Command> + 'static,
+    B: Backend,
 {
     /// Create an application.
     ///
-    /// The app will be initialized with the given model.  Dom nodes will be created and event
-    /// handlers will be registered as necessary.
-    fn create(model: Model, processor: Processor)
-    -> (Rc<RefCell<Box<dyn Application<Message, Command>>>>, Vec<web_sys::Node>)
+    /// The app will be initialized with the given model.  Dom nodes will be created, or the
+    /// children of the given parent will be hydrated, and event handlers will be registered as
+    /// necessary. The dom is manipulated via the given backend. Components don't have a parent,
+    /// they are attached by their parent app. Returns the app, its top level nodes, and a
+    /// dispatcher for it.
+    fn create(
+        model: Model,
+        processor: Processor,
+        router: Option<Rc<dyn Route<Message>>>,
+        history: Option<History<Model, Message>>,
+        backend: B,
+        parent: Option<&web_sys::Element>,
+        hydrate: bool,
+    )
+    -> Created<Message, Command>
     where
-        Model: Update<Message, Command> + Render<DomTree> + 'static,
+        Model: Update<Message, Command> + Subscriptions<Message> + Render<DomTree> + 'static,
         DomTree: DomIter<Message, Command, Key> + 'static,
         Message: fmt::Debug + Clone + PartialEq + 'static,
         Command: SideEffect<Message> + fmt::Debug + 'static,
         Key: Eq + Hash + 'static,
+        B: Backend<Node = web_sys::Node, Element = web_sys::Element, Text = web_sys::Text> + 'static,
     {
 
+        if let Some(ref history) = history {
+            history.start(&model);
+        }
+
         // render our initial model
         let dom = model.render();
         let app = App {
             dom: dom,
             model: model,
             storage: vec![],
+            backend,
+            parent: parent.cloned(),
             listeners: vec![],
             animation_frame_handle: None,
             processor: processor,
+            tasks: Rc::new(RefCell::new(Tasks::default())),
+            refs: Rc::new(RefCell::new(Refs::default())),
+            subscriptions: Subscribed::default(),
+            history,
             command: std::marker::PhantomData,
             key: std::marker::PhantomData,
         };
@@ -469,8 +707,38 @@ where
         let app_rc = Rc::new(RefCell::new(Box::new(app) as Box<dyn Application<Message, Command>>));
 
         // create the initial app
-        let nodes = Application::create(&mut **app_rc.borrow_mut(), &Dispatcher::from(&app_rc));
+        let dispatcher = Dispatcher::from(&app_rc).with_router(router);
+        let nodes = match parent {
+            Some(parent) if hydrate => {
+                Application::hydrate(&mut **app_rc.borrow_mut(), parent, &dispatcher);
+                vec![]
+            }
+            _ => Application::create(&mut **app_rc.borrow_mut(), &dispatcher),
+        };
+        Application::subscribe(&mut **app_rc.borrow_mut(), &dispatcher);
+
+        (app_rc, nodes, dispatcher)
+    }
 
-        (app_rc, nodes)
+    /// The element the app's top level nodes are in.
+    fn parent(&self) -> web_sys::Element
+    where
+        Self: Application<Message, Command>,
+    {
+        match self.parent {
+            Some(ref parent) => parent.clone(),
+            // components are attached wherever their parent app put their nodes
+            None => self.node()
+                .expect("component without any top level nodes")
+                .parent_element()
+                .expect("component not attached to the dom"),
+        }
+    }
+
+    /// Replace the model with one restored from the history, if requested.
+    fn restore(&mut self) {
+        if let Some(model) = self.history.as_ref().and_then(History::take_restore) {
+            self.model = model;
+        }
     }
 }
@@ -17,6 +17,9 @@ pub type ScheduledRender<Command> = (Vec<Command>, i32, Closure<dyn FnMut(f64)>)
 pub trait Application<Message, Command> {
     /// Update the application with a message.
     fn update(&mut self, msg: Message) -> Commands<Command>;
+    /// Update the application's subscriptions to match its current state. By default the
+    /// application has no subscriptions.
+    fn subscribe(&mut self, _app: &Dispatcher<Message, Command>) { }
     /// Tell the application to render itself.
     fn render(&mut self, app: &Dispatcher<Message, Command>) -> Vec<Command>;
     /// Process side effecting commands.
@@ -33,13 +36,26 @@ pub trait Application<Message, Command> {
     fn nodes(&self) -> Vec<web_sys::Node>;
     /// Create the dom nodes for this app.
     fn create(&mut self, app: &Dispatcher<Message, Command>) -> Vec<web_sys::Node>;
+    /// Adopt the existing children of the given parent as the dom nodes for this app. By default
+    /// the existing children are replaced with newly created nodes.
+    fn hydrate(&mut self, parent: &web_sys::Element, app: &Dispatcher<Message, Command>) {
+        while let Some(child) = parent.first_child() {
+            parent.remove_child(&child)
+                .expect("failed to remove child of parent element");
+        }
+
+        for node in self.create(app) {
+            parent.append_child(&node)
+                .expect("failed to append child to parent element");
+        }
+    }
     /// Detach the app from the dom.
     fn detach(&mut self, app: &Dispatcher<Message, Command>);
 }
 
 impl<Message, Command> Detach<Message> for Rc<RefCell<Box<dyn Application<Message, Command>>>>
 where
-    Message: fmt::Debug + Clone + PartialEq,
+    Message: fmt::Debug + Clone + PartialEq + 'static,
     Command: SideEffect<Message>,
 {
     /// Detach the app from the dom.
@@ -4,105 +4,425 @@ use web_sys;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen::JsCast;
 
-use std::rc::Rc;
+use std::rc::{Rc, Weak};
+use std::any::Any;
 use std::cell::RefCell;
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
 use std::fmt;
 use crate::app::Application;
 use crate::app::side_effect::{SideEffect, Commands};
+use crate::app::task::Tasks;
+use crate::route::Route;
+use crate::vdom::BoundHook;
 
 /// A shared app handle.
 ///
 /// Since events need to be dispatched from event handlers in the browser, they need a way to relay
 /// messages back to the app.
+///
+/// Messages are processed in the order they are dispatched. Messages dispatched while another
+/// message is being processed (for example from a command) are queued and processed after all
+/// previously queued messages. The queues belong to the app, every dispatcher of an app shares
+/// them, including dispatchers created from the app handle with `Dispatcher::from`.
 pub struct Dispatcher<Message, Command> {
     app: Rc<RefCell<Box<dyn Application<Message, Command>>>>,
-    pending: Rc<RefCell<Vec<Message>>>,
+    queues: Rc<Queues<Message>>,
+    router: Option<Rc<dyn Route<Message>>>,
+    tasks: Rc<RefCell<Tasks>>,
+    refs: Rc<RefCell<Refs>>,
+    hooks: Rc<RefCell<Hooks<Message, Command>>>,
+}
+
+/// Elements recorded by name, see [`Dispatcher::node_ref`]. Elements are stored as the element type
+/// of the backend that created them.
+///
+/// [`Dispatcher::node_ref`]: struct.Dispatcher.html#method.node_ref
+pub(crate) type Refs = HashMap<String, Box<dyn Any>>;
+
+/// Lifecycle hooks waiting to run, bound to the elements to run them with.
+type Hooks<Message, Command> = Vec<BoundHook<Message, Command>>;
+
+/// The message queues of an app.
+struct Queues<Message> {
+    /// Messages waiting to be processed.
+    pending: RefCell<VecDeque<Message>>,
+    /// Low priority messages waiting for the browser to become idle.
+    idle: RefCell<Idle<Message>>,
+}
+
+impl<Message> Default for Queues<Message> {
+    fn default() -> Self {
+        Queues {
+            pending: RefCell::new(VecDeque::new()),
+            idle: RefCell::new(Idle::default()),
+        }
+    }
+}
+
+thread_local! {
+    /// The message queues of each app with a live dispatcher, by the address of the app.
+    static QUEUES: RefCell<HashMap<usize, Weak<dyn Any>>> = RefCell::new(HashMap::new());
+}
+
+/// Get the message queues of the given app, shared with its other dispatchers.
+///
+/// The queues are kept alive by the dispatchers, which in turn keep the app alive, so the address
+/// of the app identifies its queues for as long as they exist.
+fn queues<Message, Command>(app: &Rc<RefCell<Box<dyn Application<Message, Command>>>>) -> Rc<Queues<Message>>
+where
+    Message: 'static,
+{
+    let address = Rc::as_ptr(app) as *const () as usize;
+    QUEUES.with(|registry| {
+        let mut registry = registry.borrow_mut();
+
+        let existing = registry.get(&address)
+            .and_then(Weak::upgrade)
+            .and_then(|queues| queues.downcast::<Queues<Message>>().ok());
+        if let Some(queues) = existing {
+            return queues;
+        }
+
+        // forget the queues of apps that are gone
+        registry.retain(|_, queues| queues.strong_count() > 0);
+
+        let queues = Rc::new(Queues::default());
+        let weak: Weak<dyn Any> = Rc::downgrade(&queues) as Weak<Queues<Message>>;
+        registry.insert(address, weak);
+        queues
+    })
+}
+
+/// Low priority messages waiting for the browser to become idle.
+struct Idle<Message> {
+    /// The queued messages.
+    messages: Vec<Message>,
+    /// The idle callback, if one has been requested.
+    callback: Option<Closure<dyn FnMut(JsValue)>>,
+}
+
+impl<Message> Default for Idle<Message> {
+    fn default() -> Self {
+        Idle {
+            messages: vec![],
+            callback: None,
+        }
+    }
 }
 
 impl<Message, Command> Clone for Dispatcher<Message, Command> {
     fn clone(&self) -> Self {
         Dispatcher {
             app: Rc::clone(&self.app),
-            pending: Rc::clone(&self.pending),
+            queues: Rc::clone(&self.queues),
+            router: self.router.clone(),
+            tasks: Rc::clone(&self.tasks),
+            refs: Rc::clone(&self.refs),
+            hooks: Rc::clone(&self.hooks),
         }
     }
 }
 
-impl<Message, Command> From<Rc<RefCell<Box<dyn Application<Message, Command>>>>> for Dispatcher<Message, Command> {
+impl<Message: 'static, Command> From<Rc<RefCell<Box<dyn Application<Message, Command>>>>> for Dispatcher<Message, Command> {
     fn from(app: Rc<RefCell<Box<dyn Application<Message, Command>>>>) -> Self {
         Dispatcher {
+            queues: queues(&app),
             app: app,
-            pending: Rc::new(RefCell::new(Vec::new())),
+            router: None,
+            tasks: Rc::new(RefCell::new(Tasks::default())),
+            refs: Rc::new(RefCell::new(Refs::default())),
+            hooks: Rc::new(RefCell::new(vec![])),
         }
     }
 }
 
-impl<Message, Command> From<&Rc<RefCell<Box<dyn Application<Message, Command>>>>> for Dispatcher<Message, Command> {
+impl<Message: 'static, Command> From<&Rc<RefCell<Box<dyn Application<Message, Command>>>>> for Dispatcher<Message, Command> {
     fn from(app: &Rc<RefCell<Box<dyn Application<Message, Command>>>>) -> Self {
         Dispatcher {
             app: Rc::clone(app),
-            pending: Rc::new(RefCell::new(Vec::new())),
+            queues: queues(app),
+            router: None,
+            tasks: Rc::new(RefCell::new(Tasks::default())),
+            refs: Rc::new(RefCell::new(Refs::default())),
+            hooks: Rc::new(RefCell::new(vec![])),
         }
     }
 }
 
+impl<Message, Command> Dispatcher<Message, Command> {
+    /// Use the given router to route urls after navigating.
+    pub(crate) fn with_router(mut self, router: Option<Rc<dyn Route<Message>>>) -> Self {
+        self.router = router;
+        self
+    }
+
+    /// Track futures spawned with this dispatcher in the given set of tasks.
+    pub(crate) fn with_tasks(mut self, tasks: Rc<RefCell<Tasks>>) -> Self {
+        self.tasks = tasks;
+        self
+    }
+
+    /// Record and look up refs in the given ref table.
+    pub(crate) fn with_refs(mut self, refs: Rc<RefCell<Refs>>) -> Self {
+        self.refs = refs;
+        self
+    }
+
+    /// Get the browser's element for the node with the given ref name, see [`Dom::node_ref`].
+    ///
+    /// Refs are recorded when the dom is patched, so commands processed after rendering (see
+    /// [`Commands`]) see the elements of the latest render. Returns `None` if no element with the
+    /// given name is in the dom.
+    ///
+    /// [`Dom::node_ref`]: ../../dom/struct.Dom.html#method.node_ref
+    /// [`Commands`]: ../side_effect/struct.Commands.html
+    pub fn node_ref(&self, name: &str) -> Option<web_sys::Element> {
+        match self.get_ref::<web_sys::Element>(name) {
+            Some(element) if element.is_connected() => Some(element),
+            // the element was removed from the dom by something other than the app
+            Some(_) => {
+                self.refs.borrow_mut().remove(name);
+                None
+            }
+            None => None,
+        }
+    }
+
+    /// Get the element recorded under the given ref name, if it is an element of type `E`.
+    pub(crate) fn get_ref<E: Clone + 'static>(&self, name: &str) -> Option<E> {
+        self.refs.borrow()
+            .get(name)
+            .and_then(|element| element.downcast_ref::<E>())
+            .cloned()
+    }
+
+    /// Record the given element under the given ref name.
+    pub(crate) fn set_ref<E: 'static>(&self, name: &str, element: E) {
+        self.refs.borrow_mut().insert(name.to_owned(), Box::new(element));
+    }
+
+    /// Remove the given ref name, if it still refers to the given element.
+    pub(crate) fn remove_ref<E: PartialEq + 'static>(&self, name: &str, element: &E) {
+        let mut refs = self.refs.borrow_mut();
+        if refs.get(name).and_then(|e| e.downcast_ref::<E>()) == Some(element) {
+            refs.remove(name);
+        }
+    }
+
+    /// Queue a bound lifecycle hook, see [`run_hooks`].
+    ///
+    /// [`run_hooks`]: #method.run_hooks
+    pub(crate) fn queue_hook(&self, hook: BoundHook<Message, Command>) {
+        self.hooks.borrow_mut().push(hook);
+    }
+
+    /// Run the queued lifecycle hooks in order, returning the messages and commands they produce.
+    pub(crate) fn run_hooks(&self) -> (Vec<Message>, Vec<Command>) {
+        let hooks = std::mem::take(&mut *self.hooks.borrow_mut());
+
+        let mut messages = vec![];
+        let mut commands = vec![];
+        for hook in hooks {
+            let (message, command) = hook();
+            messages.extend(message);
+            commands.extend(command);
+        }
+        (messages, commands)
+    }
+}
+
 impl<Message, Command> Dispatcher<Message, Command>
 where
     Command: SideEffect<Message> + 'static,
     Message: fmt::Debug + Clone + PartialEq + 'static,
 {
+    /// Route the current url of the document with the app's router, dispatching the resulting
+    /// message (if any).
+    pub(crate) fn route(&self) {
+        let router = match self.router {
+            Some(ref router) => router,
+            None => return,
+        };
+
+        let url = web_sys::window()
+            .expect_throw("couldn't get window handle")
+            .document()
+            .expect_throw("couldn't get document handle")
+            .url()
+            .expect_throw("couldn't get document url");
+
+        if let Some(msg) = router.route(&url) {
+            self.dispatch(msg);
+        }
+    }
+
     /// Dispatch a message to the associated app.
     pub fn dispatch(&self, msg: Message) {
         // queue the message
-        self.pending.borrow_mut().push(msg);
+        self.queues.pending.borrow_mut().push_back(msg);
+        self.process_pending();
+    }
+
+    /// Dispatch a sequence of messages to the associated app.
+    ///
+    /// The messages are processed in order, no other messages will be processed in between them.
+    pub fn dispatch_batch(&self, msgs: Vec<Message>) {
+        self.queues.pending.borrow_mut().extend(msgs);
+        self.process_pending();
+    }
+
+    /// Dispatch a low priority message to the associated app.
+    ///
+    /// The message will be processed the next time the browser is idle (see
+    /// [`requestIdleCallback`]). Low priority messages are coalesced, if an equal message is
+    /// already waiting to be processed this message is dropped. All low priority messages that are
+    /// waiting when the browser becomes idle are processed as a single batch, after any messages
+    /// dispatched normally.
+    ///
+    /// [`requestIdleCallback`]: https://developer.mozilla.org/en-US/docs/Web/API/Window/requestIdleCallback
+    pub fn dispatch_idle(&self, msg: Message) {
+        let mut idle = self.queues.idle.borrow_mut();
+
+        if !idle.messages.contains(&msg) {
+            idle.messages.push(msg);
+        }
+
+        if idle.callback.is_some() {
+            return;
+        }
 
+        let dispatcher = self.clone();
+        let closure = Closure::wrap(
+            Box::new(move |_| {
+                let msgs = {
+                    let mut idle = dispatcher.queues.idle.borrow_mut();
+                    idle.callback = None;
+                    std::mem::take(&mut idle.messages)
+                };
+                dispatcher.dispatch_batch(msgs);
+            }) as Box<dyn FnMut(JsValue)>
+        );
+
+        let window = web_sys::window()
+            .expect_throw("couldn't get window handle");
+
+        // fall back to a timeout in browsers without requestIdleCallback
+        window.request_idle_callback(closure.as_ref().unchecked_ref())
+            .map(|_| ())
+            .or_else(|_| {
+                window.set_timeout_with_callback(closure.as_ref().unchecked_ref())
+                    .map(|_| ())
+            })
+            .expect_throw("error with request_idle_callback");
+
+        idle.callback = Some(closure);
+    }
+
+    /// Spawn the given future, dispatching the message it resolves to (if any).
+    ///
+    /// The future runs on the browser's event loop. If the app is detached before the future
+    /// resolves, the future is canceled and dropped.
+    pub fn spawn<F>(&self, future: F)
+    where
+        F: Future<Output = Option<Message>> + 'static,
+    {
+        let future = self.tasks.borrow_mut().track(future);
+        let dispatcher = self.clone();
+
+        wasm_bindgen_futures::spawn_local(async move {
+            if let Some(Some(msg)) = future.await {
+                dispatcher.dispatch(msg);
+            }
+        });
+    }
+
+    /// Re-render the app and update its subscriptions without processing a message.
+    ///
+    /// Use this after changing the state of the model outside of an update, for example when
+    /// rewinding its [`History`]. Nothing happens if the app is currently processing messages.
+    ///
+    /// [`History`]: ../history/struct.History.html
+    pub fn refresh(&self) {
+        let mut app = match self.app.try_borrow_mut() {
+            Ok(app) => app,
+            Err(_) => return,
+        };
+
+        Application::subscribe(&mut **app, self);
+        self.schedule_render(&mut **app, vec![]);
+    }
+
+    /// Request an animation frame for rendering if we don't already have a request out. The given
+    /// commands will be processed after rendering.
+    fn schedule_render(&self, app: &mut dyn Application<Message, Command>, post_render: Vec<Command>) {
+        if let Some((ref mut cmds, _, _)) = Application::get_scheduled_render(app) {
+            cmds.extend(post_render);
+            return;
+        }
+
+        let dispatcher = self.clone();
+
+        let window = web_sys::window()
+            .expect_throw("couldn't get window handle");
+
+        let closure = Closure::wrap(
+            Box::new(move |_| {
+                {
+                    let mut app = dispatcher.app.borrow_mut();
+                    let commands = Application::render(&mut **app, &dispatcher);
+                    for cmd in commands {
+                        Application::process(&**app, cmd, &dispatcher);
+                    }
+                }
+
+                // process messages dispatched while rendering, e.g. by lifecycle hooks
+                dispatcher.process_pending();
+            }) as Box<dyn FnMut(f64)>
+        );
+
+        let handle = window.request_animation_frame(closure.as_ref().unchecked_ref())
+            .expect_throw("error with requestion_animation_frame");
+
+        Application::set_scheduled_render(app, (post_render, handle, closure));
+    }
+
+    /// Process queued messages, unless they are already being processed.
+    fn process_pending(&self) {
+        self.process_queue(|app, post_render| self.schedule_render(app, post_render));
+    }
+
+    /// Process queued messages, unless they are already being processed, scheduling renders with
+    /// the given function.
+    fn process_queue<F>(&self, schedule_render: F)
+    where
+        F: Fn(&mut dyn Application<Message, Command>, Vec<Command>),
+    {
         // try to borrow the app
         let mut app = match self.app.try_borrow_mut() {
             Ok(app) => app,
-            // already borrowed, the current borrower will process the queue
+            // already borrowed, the current borrower will process the queue, which is shared by
+            // all dispatchers of the app
             Err(_) => return,
         };
 
         // now process queued messages
         loop {
             // grab the first pending message (if any)
-            let msg = match self.pending.borrow_mut().pop() {
+            let msg = match self.queues.pending.borrow_mut().pop_front() {
                 Some(msg) => msg,
                 None => break,
             };
 
             let commands = Application::update(&mut **app, msg);
+            Application::subscribe(&mut **app, self);
 
             let Commands {
                 immediate,
                 post_render,
             } = commands;
 
-            // request an animation frame for rendering if we don't already have a request out
-            if let Some((ref mut cmds, _, _)) = Application::get_scheduled_render(&mut **app) {
-                cmds.extend(post_render);
-            }
-            else {
-                let dispatcher = self.clone();
-
-                let window = web_sys::window()
-                    .expect_throw("couldn't get window handle");
-
-                let closure = Closure::wrap(
-                    Box::new(move |_| {
-                        let mut app = dispatcher.app.borrow_mut();
-                        let commands = Application::render(&mut **app, &dispatcher);
-                        for cmd in commands {
-                            Application::process(&**app, cmd, &dispatcher);
-                        }
-                    }) as Box<dyn FnMut(f64)>
-                );
-
-                let handle = window.request_animation_frame(closure.as_ref().unchecked_ref())
-                    .expect_throw("error with requestion_animation_frame");
-
-                Application::set_scheduled_render(&mut **app, (post_render, handle, closure));
-            }
+            schedule_render(&mut **app, post_render);
 
             // execute side effects
             for cmd in immediate {
@@ -111,3 +431,134 @@ where
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::app::ScheduledRender;
+    use wasm_bindgen_test::*;
+    use wasm_bindgen_test::wasm_bindgen_test_configure;
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    /// A command that dispatches the given messages.
+    enum Cmd {
+        /// Dispatch the messages with the dispatcher the command is processed with.
+        Dispatch(Vec<u32>),
+        /// Dispatch the messages with another dispatcher of the same app.
+        DispatchFrom(Vec<u32>),
+    }
+
+    impl SideEffect<u32> for Cmd {
+        fn process(self, dispatcher: &Dispatcher<u32, Self>) {
+            match self {
+                Cmd::Dispatch(msgs) => for msg in msgs {
+                    dispatcher.dispatch(msg);
+                }
+                Cmd::DispatchFrom(msgs) => {
+                    Dispatcher::from(&dispatcher.app).dispatch_batch(msgs);
+                }
+            }
+        }
+    }
+
+    /// An app that records messages, message `1` dispatches `10` and `11` from a command, message
+    /// `2` dispatches `20` and `21` from a command using another dispatcher.
+    struct Recorder {
+        messages: Rc<RefCell<Vec<u32>>>,
+        render: Option<ScheduledRender<Cmd>>,
+    }
+
+    impl Application<u32, Cmd> for Recorder {
+        fn update(&mut self, msg: u32) -> Commands<Cmd> {
+            self.messages.borrow_mut().push(msg);
+            let mut commands = Commands::default();
+            match msg {
+                1 => commands.push(Cmd::Dispatch(vec![10, 11])),
+                2 => commands.push(Cmd::DispatchFrom(vec![20, 21])),
+                _ => {}
+            }
+            commands
+        }
+        fn subscribe(&mut self, _app: &Dispatcher<u32, Cmd>) { }
+        fn render(&mut self, _app: &Dispatcher<u32, Cmd>) -> Vec<Cmd> { vec![] }
+        fn process(&self, cmd: Cmd, app: &Dispatcher<u32, Cmd>) {
+            cmd.process(app);
+        }
+        fn get_scheduled_render(&mut self) -> &mut Option<ScheduledRender<Cmd>> {
+            &mut self.render
+        }
+        fn set_scheduled_render(&mut self, handle: ScheduledRender<Cmd>) {
+            self.render = Some(handle);
+        }
+        fn push_listener(&mut self, _listener: (String, Closure<dyn FnMut(web_sys::Event)>)) { }
+        fn node(&self) -> Option<web_sys::Node> { None }
+        fn nodes(&self) -> Vec<web_sys::Node> { vec![] }
+        fn create(&mut self, _app: &Dispatcher<u32, Cmd>) -> Vec<web_sys::Node> { vec![] }
+        fn hydrate(&mut self, _parent: &web_sys::Element, _app: &Dispatcher<u32, Cmd>) { }
+        fn detach(&mut self, _app: &Dispatcher<u32, Cmd>) { }
+    }
+
+    fn recorder(messages: &Rc<RefCell<Vec<u32>>>) -> Dispatcher<u32, Cmd> {
+        Dispatcher::from(Rc::new(RefCell::new(Box::new(
+            Recorder {
+                messages: Rc::clone(messages),
+                render: None,
+            }
+        ) as Box<dyn Application<u32, Cmd>>)))
+    }
+
+    #[wasm_bindgen_test]
+    fn dispatch_fifo() {
+        let messages = Rc::new(RefCell::new(vec![]));
+        let dispatcher = recorder(&messages);
+
+        dispatcher.dispatch_batch(vec![1, 2]);
+
+        assert_eq!(*messages.borrow(), vec![1, 2, 10, 11, 20, 21]);
+    }
+
+    #[test]
+    fn queue_fifo() {
+        let messages = Rc::new(RefCell::new(vec![]));
+        let dispatcher = recorder(&messages);
+
+        // a batch is processed in order, followed by the messages dispatched while processing it,
+        // no matter which dispatcher of the app they were dispatched with
+        dispatcher.queues.pending.borrow_mut().extend(vec![2, 1, 3]);
+        dispatcher.process_queue(|_, _| ());
+        assert_eq!(*messages.borrow(), vec![2, 1, 3, 20, 21, 10, 11]);
+
+        // every dispatcher of the app shares the queue
+        messages.borrow_mut().clear();
+        let other = Dispatcher::from(&dispatcher.app);
+        other.queues.pending.borrow_mut().push_back(1);
+        dispatcher.process_queue(|_, _| ());
+        assert_eq!(*messages.borrow(), vec![1, 10, 11]);
+    }
+
+    #[test]
+    fn queue_per_app() {
+        let messages = Rc::new(RefCell::new(vec![]));
+        let dispatcher = recorder(&messages);
+        let other = recorder(&messages);
+
+        other.queues.pending.borrow_mut().push_back(3);
+        dispatcher.process_queue(|_, _| ());
+        assert!(messages.borrow().is_empty());
+        assert!(Rc::ptr_eq(&other.queues, &Dispatcher::from(&other.app).queues));
+    }
+
+    #[wasm_bindgen_test]
+    fn dispatch_idle_coalesced() {
+        let messages = Rc::new(RefCell::new(vec![]));
+        let dispatcher = recorder(&messages);
+
+        dispatcher.dispatch_idle(3);
+        Dispatcher::from(&dispatcher.app).dispatch_idle(3);
+        dispatcher.dispatch_idle(4);
+        dispatcher.dispatch(5);
+
+        assert_eq!(*messages.borrow(), vec![5]);
+        assert_eq!(dispatcher.queues.idle.borrow().messages, vec![3, 4]);
+    }
+}
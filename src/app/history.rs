@@ -0,0 +1,445 @@
+//! Record the messages processed by an app to allow stepping backwards and forwards through its
+//! states.
+//!
+//! Give a [`History`] to [`AppBuilder::history`] to record each message processed by the app,
+//! along with a timestamp and a snapshot of the model after the update. The app can then be
+//! rewound to any recorded point, or the recorded messages can be replayed against the initial
+//! model. After changing the state, call [`Dispatcher::refresh`] to re-render the app.
+//!
+//! If a message is processed while the app is rewound, the messages after the current point are
+//! discarded and recording continues from there.
+//!
+//! ```no_run
+//! use euca::app::{AppBuilder, Dispatcher, History};
+//! # use euca::app::{Update, Subscriptions, Render};
+//! # use euca::dom::Dom;
+//! # #[derive(Clone, Default)]
+//! # struct Model;
+//! # impl Update<Msg> for Model {}
+//! # impl Subscriptions<Msg> for Model {}
+//! # impl Render<Dom<Msg>> for Model { fn render(&self) -> Dom<Msg> { Dom::elem("div") } }
+//! # #[derive(Clone, PartialEq, Debug)]
+//! # struct Msg;
+//! # let parent = web_sys::window().unwrap().document().unwrap().body().unwrap().into();
+//!
+//! let history: History<Model, Msg> = History::new();
+//! let app = AppBuilder::default()
+//!     .history(&history)
+//!     .attach(parent, Model::default());
+//!
+//! // later, go back to the state after the second message
+//! history.rewind(2);
+//! Dispatcher::from(&app).refresh();
+//! ```
+//!
+//! The history must be for the app's model type:
+//!
+//! ```compile_fail
+//! # use euca::app::{AppBuilder, History};
+//! # use euca::app::{Update, Subscriptions, Render};
+//! # use euca::dom::Dom;
+//! # #[derive(Clone, Default)]
+//! # struct Model;
+//! # impl Update<Msg> for Model {}
+//! # impl Subscriptions<Msg> for Model {}
+//! # impl Render<Dom<Msg>> for Model { fn render(&self) -> Dom<Msg> { Dom::elem("div") } }
+//! # #[derive(Clone, PartialEq, Debug)]
+//! # struct Msg;
+//! # let parent = web_sys::window().unwrap().document().unwrap().body().unwrap().into();
+//! let history: History<String, Msg> = History::new();
+//! let app = AppBuilder::default()
+//!     .history(&history)
+//!     .attach(parent, Model::default());
+//! ```
+//!
+//! With the `serde` feature enabled, the recorded messages can be saved as a [`MessageLog`] and
+//! replayed against a fresh model in a native regression test with [`Model::test_replay`].
+//!
+//! ```ignore
+//! // in the app, save the log somewhere
+//! let json = serde_json::to_string(&history.log()).unwrap();
+//!
+//! // in a test, load it and replay it
+//! use euca::test::Model as _;
+//! let log: MessageLog<Msg> = serde_json::from_str(&json).unwrap();
+//! let mut model = Model::default();
+//! let cmds = model.test_replay(log);
+//! assert_eq!(model, expected);
+//! ```
+//!
+//! [`History`]: struct.History.html
+//! [`MessageLog`]: struct.MessageLog.html
+//! [`Model::test_replay`]: ../../test/trait.Model.html#method.test_replay
+//! [`AppBuilder::history`]: ../struct.AppBuilder.html#method.history
+//! [`Dispatcher::refresh`]: ../dispatch/struct.Dispatcher.html#method.refresh
+
+use std::fmt;
+use std::rc::Rc;
+use std::cell::RefCell;
+use crate::app::model::Update;
+use crate::app::side_effect::Commands;
+use log::info;
+
+/// A sequence of messages processed by an app.
+///
+/// When the `serde` feature is enabled this can be serialized, allowing a session captured from
+/// a running app to be replayed later.
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MessageLog<Message> {
+    /// The messages, in the order they were processed.
+    pub messages: Vec<Message>,
+}
+
+impl<Message> From<Vec<Message>> for MessageLog<Message> {
+    fn from(messages: Vec<Message>) -> Self {
+        MessageLog { messages }
+    }
+}
+
+impl<Message> IntoIterator for MessageLog<Message> {
+    type Item = Message;
+    type IntoIter = std::vec::IntoIter<Message>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.messages.into_iter()
+    }
+}
+
+/// A recorded message.
+struct Entry<Model, Message> {
+    /// When the message was processed, in milliseconds since the unix epoch.
+    time: f64,
+    /// The message.
+    msg: Message,
+    /// The model after processing the message.
+    model: Model,
+}
+
+struct State<Model, Message> {
+    /// Copy a model.
+    snapshot: fn(&Model) -> Model,
+    /// The model before any messages were processed.
+    initial: Option<Model>,
+    /// The recorded messages.
+    entries: Vec<Entry<Model, Message>>,
+    /// The number of entries that have been applied to the app's current model.
+    cursor: usize,
+    /// A model that should replace the app's current model.
+    restore: Option<Model>,
+}
+
+/// A shared handle to the recorded history of an app.
+///
+/// Cloning this produces another handle to the same history.
+pub struct History<Model, Message>(Rc<RefCell<State<Model, Message>>>);
+
+impl<Model, Message> Clone for History<Model, Message> {
+    fn clone(&self) -> Self {
+        History(Rc::clone(&self.0))
+    }
+}
+
+impl<Model: Clone, Message> Default for History<Model, Message> {
+    fn default() -> Self {
+        History(Rc::new(RefCell::new(State {
+            snapshot: Model::clone,
+            initial: None,
+            entries: vec![],
+            cursor: 0,
+            restore: None,
+        })))
+    }
+}
+
+impl<Model: Clone, Message> History<Model, Message> {
+    /// Create an empty history.
+    pub fn new() -> Self {
+        History::default()
+    }
+}
+
+impl<Model, Message> History<Model, Message> {
+    /// The number of recorded messages.
+    pub fn len(&self) -> usize {
+        self.0.borrow().entries.len()
+    }
+
+    /// Returns true if no messages have been recorded.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// The current position in the history, this is the number of recorded messages that have
+    /// been applied to the app's model.
+    pub fn cursor(&self) -> usize {
+        self.0.borrow().cursor
+    }
+
+    /// The recorded messages along with the time they were processed (in milliseconds since the
+    /// unix epoch).
+    pub fn messages(&self) -> Vec<(f64, Message)>
+    where
+        Message: Clone,
+    {
+        self.0.borrow().entries.iter()
+            .map(|entry| (entry.time, entry.msg.clone()))
+            .collect()
+    }
+
+    /// The recorded messages up to the current position in the history.
+    pub fn log(&self) -> MessageLog<Message>
+    where
+        Message: Clone,
+    {
+        let state = self.0.borrow();
+        state.entries[..state.cursor].iter()
+            .map(|entry| entry.msg.clone())
+            .collect::<Vec<_>>()
+            .into()
+    }
+
+    /// Restore the model to the state it was in after the given number of messages were
+    /// processed. Use `0` for the initial state.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is greater than the number of recorded messages.
+    pub fn rewind(&self, index: usize) {
+        let mut state = self.0.borrow_mut();
+        assert!(index <= state.entries.len(), "can't rewind past the end of the history");
+
+        let model = match index {
+            0 => state.initial.as_ref().map(state.snapshot),
+            i => Some((state.snapshot)(&state.entries[i - 1].model)),
+        };
+
+        state.cursor = index;
+        state.restore = model;
+    }
+
+    /// Restore the model by processing the given number of recorded messages, starting from the
+    /// initial state. Any commands generated by the updates are discarded.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the index is greater than the number of recorded messages.
+    pub fn replay<Command>(&self, index: usize)
+    where
+        Model: Update<Message, Command>,
+        Message: Clone,
+    {
+        let mut state = self.0.borrow_mut();
+        assert!(index <= state.entries.len(), "can't replay past the end of the history");
+
+        let mut model = match state.initial {
+            Some(ref initial) => (state.snapshot)(initial),
+            None => return,
+        };
+
+        for entry in state.entries[..index].iter() {
+            model.update(entry.msg.clone(), &mut Commands::default());
+        }
+
+        state.cursor = index;
+        state.restore = Some(model);
+    }
+
+    /// Start recording with the given initial model, discarding any existing history.
+    pub(crate) fn start(&self, model: &Model) {
+        let mut state = self.0.borrow_mut();
+        state.initial = Some((state.snapshot)(model));
+        state.entries.clear();
+        state.cursor = 0;
+        state.restore = None;
+    }
+
+    /// Record a processed message and the resulting model.
+    pub(crate) fn record(&self, msg: Message, model: &Model)
+    where
+        Message: fmt::Debug,
+    {
+        let time = now();
+        info!("[{:.0}] {:?}", time, msg);
+
+        let mut state = self.0.borrow_mut();
+        let cursor = state.cursor;
+        state.entries.truncate(cursor);
+
+        let model = (state.snapshot)(model);
+        state.entries.push(Entry { time, msg, model });
+        state.cursor += 1;
+    }
+
+    /// Take the model that should replace the app's current model, if any.
+    pub(crate) fn take_restore(&self) -> Option<Model> {
+        self.0.borrow_mut().restore.take()
+    }
+}
+
+/// The history an app records into, see [`AppBuilder::history`].
+///
+/// This is implemented for [`History`], and for `()` when the app doesn't record its history.
+///
+/// [`AppBuilder::history`]: ../struct.AppBuilder.html#method.history
+/// [`History`]: struct.History.html
+pub trait Record<Model, Message> {
+    /// The history to record into, if any.
+    fn into_history(self) -> Option<History<Model, Message>>;
+}
+
+impl<Model, Message> Record<Model, Message> for () {
+    fn into_history(self) -> Option<History<Model, Message>> {
+        None
+    }
+}
+
+impl<Model, Message> Record<Model, Message> for History<Model, Message> {
+    fn into_history(self) -> Option<History<Model, Message>> {
+        Some(self)
+    }
+}
+
+/// The current time in milliseconds since the unix epoch.
+#[cfg(target_arch = "wasm32")]
+fn now() -> f64 {
+    js_sys::Date::now()
+}
+
+/// The current time in milliseconds since the unix epoch.
+#[cfg(not(target_arch = "wasm32"))]
+fn now() -> f64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_secs_f64() * 1000.0)
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Clone, Debug, Default, PartialEq)]
+    struct Counter(i32);
+
+    impl Update<i32> for Counter {
+        fn simple_update(&mut self, msg: i32) {
+            self.0 += msg;
+        }
+    }
+
+    /// Apply the given messages to a model, recording them in the given history.
+    fn run(history: &History<Counter, i32>, model: &mut Counter, msgs: &[i32]) {
+        for msg in msgs {
+            if let Some(restored) = history.take_restore() {
+                *model = restored;
+            }
+            Update::<i32>::update(model, *msg, &mut Commands::default());
+            history.record(*msg, model);
+        }
+    }
+
+    #[test]
+    fn record_and_rewind() {
+        let history = History::new();
+        let mut model = Counter::default();
+        history.start(&model);
+        run(&history, &mut model, &[1, 2, 3]);
+
+        assert_eq!(history.len(), 3);
+        assert_eq!(history.cursor(), 3);
+        assert_eq!(history.messages().iter().map(|(_, m)| *m).collect::<Vec<_>>(), vec![1, 2, 3]);
+        assert_eq!(model, Counter(6));
+
+        history.rewind(1);
+        assert_eq!(history.cursor(), 1);
+        assert_eq!(history.take_restore(), Some(Counter(1)));
+        assert_eq!(history.take_restore(), None);
+
+        history.rewind(0);
+        assert_eq!(history.take_restore(), Some(Counter(0)));
+
+        history.rewind(3);
+        assert_eq!(history.take_restore(), Some(Counter(6)));
+    }
+
+    #[test]
+    fn replay() {
+        let history = History::new();
+        let mut model = Counter(10);
+        history.start(&model);
+        run(&history, &mut model, &[1, 2, 3]);
+
+        history.replay::<()>(2);
+        assert_eq!(history.cursor(), 2);
+        assert_eq!(history.take_restore(), Some(Counter(13)));
+    }
+
+    #[test]
+    fn record_after_rewind() {
+        let history = History::new();
+        let mut model = Counter::default();
+        history.start(&model);
+        run(&history, &mut model, &[1, 2, 3]);
+
+        history.rewind(1);
+        run(&history, &mut model, &[10]);
+
+        assert_eq!(history.len(), 2);
+        assert_eq!(history.cursor(), 2);
+        assert_eq!(history.messages().iter().map(|(_, m)| *m).collect::<Vec<_>>(), vec![1, 10]);
+        assert_eq!(model, Counter(11));
+    }
+
+    #[test]
+    fn log() {
+        let history = History::new();
+        let mut model = Counter::default();
+        history.start(&model);
+        run(&history, &mut model, &[1, 2, 3]);
+
+        assert_eq!(history.log(), MessageLog::from(vec![1, 2, 3]));
+
+        history.rewind(2);
+        assert_eq!(history.log(), MessageLog::from(vec![1, 2]));
+    }
+
+    /// Emits a command with the running total after each odd message.
+    #[cfg(feature = "serde")]
+    impl Update<i32, i32> for Counter {
+        fn update(&mut self, msg: i32, cmds: &mut Commands<i32>) {
+            self.0 += msg;
+            if msg % 2 == 1 {
+                cmds.push(self.0);
+            }
+        }
+    }
+
+    #[test]
+    #[cfg(feature = "serde")]
+    fn replay_saved_log() {
+        use crate::test::Model as _;
+
+        let history = History::new();
+        let mut model = Counter::default();
+        history.start(&model);
+        run(&history, &mut model, &[1, 2, 3, 4]);
+
+        let json = serde_json::to_string(&history.log()).unwrap();
+        let log: MessageLog<i32> = serde_json::from_str(&json).unwrap();
+
+        let mut fresh = Counter::default();
+        let cmds: Commands<i32> = fresh.test_replay(log);
+        assert_eq!(fresh, model);
+        assert_eq!(cmds.immediate, vec![1, 6]);
+        assert!(cmds.post_render.is_empty());
+    }
+
+    #[test]
+    #[should_panic]
+    fn rewind_past_end() {
+        let history: History<Counter, i32> = History::new();
+        history.start(&Counter::default());
+        history.rewind(1);
+    }
+}
@@ -1,6 +1,7 @@
 //! Traits to implement on a model to allow it to interact with an application.
 
 use crate::app::side_effect::Commands;
+use crate::app::subscription::SubscriptionSet;
 
 /// Process a message that updates the model.
 pub trait Update<Message, Command = ()> {
@@ -17,6 +18,19 @@ pub trait Update<Message, Command = ()> {
 
 impl<M> Update<(), ()> for M { }
 
+/// Subscribe the model to sources of messages outside of the app's dom, like timers and window
+/// events.
+pub trait Subscriptions<Message> {
+    /// The sources of messages the model is interested in given its current state. This is called
+    /// after every update, listeners are added and removed to match. Implement this if your app
+    /// needs to listen to sources outside of its dom.
+    fn subscriptions(&self) -> SubscriptionSet<Message> {
+        SubscriptionSet::new()
+    }
+}
+
+impl<M> Subscriptions<()> for M { }
+
 /// Render (or view) the model as a virtual dom.
 pub trait Render<DomTree> {
     /// Render the model as a virtual dom.
@@ -0,0 +1,384 @@
+//! Subscriptions to sources of messages outside of the app's dom.
+//!
+//! A model declares the sources it is interested in, based on its current state, by implementing
+//! [`Subscriptions`]. After each update the app compares the declared subscriptions with
+//! the active ones, adding and removing listeners as necessary. All listeners are removed when
+//! the app is detached.
+//!
+//! [`Subscriptions`]: ../model/trait.Subscriptions.html
+
+use std::fmt;
+use std::mem;
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use crate::app::{Dispatcher, SideEffect};
+
+/// A function that sends a message to the app.
+pub type Sink<Message> = Rc<dyn Fn(Message)>;
+
+/// Subscribe to an external source, returning a function that will unsubscribe.
+type Subscribe<Message> = Rc<dyn Fn(Sink<Message>) -> Box<dyn FnOnce()>>;
+
+/// A source of messages.
+pub enum Subscription<Message> {
+    /// Dispatch a message at a fixed interval.
+    Interval {
+        /// The interval in milliseconds.
+        ms: i32,
+        /// The message to dispatch.
+        msg: Message,
+    },
+    /// Listen for an event on the window (e.g. `resize` or `keydown`).
+    Window {
+        /// The event trigger.
+        trigger: &'static str,
+        /// A function to convert the event into a message.
+        handler: fn(web_sys::Event) -> Option<Message>,
+    },
+    /// Listen for an event on the document (e.g. `visibilitychange`).
+    Document {
+        /// The event trigger.
+        trigger: &'static str,
+        /// A function to convert the event into a message.
+        handler: fn(web_sys::Event) -> Option<Message>,
+    },
+    /// Any other source of messages.
+    Source {
+        /// Identifies this source, sources with the same key are considered equal even if their
+        /// subscribe functions differ. Changing only the subscribe function keeps the existing
+        /// subscription, use a new key to subscribe with the new function.
+        key: String,
+        /// Subscribe to the source.
+        subscribe: Subscribe<Message>,
+    },
+}
+
+impl<Message: PartialEq> PartialEq for Subscription<Message> {
+    fn eq(&self, other: &Self) -> bool {
+        use Subscription::*;
+
+        match (self, other) {
+            (Interval { ms: m1, msg: msg1 }, Interval { ms: m2, msg: msg2 }) => m1 == m2 && msg1 == msg2,
+            (Window { trigger: t1, handler: h1 }, Window { trigger: t2, handler: h2 })
+            | (Document { trigger: t1, handler: h1 }, Document { trigger: t2, handler: h2 }) => {
+                t1 == t2 && *h1 as usize == *h2 as usize
+            }
+            (Source { key: k1, .. }, Source { key: k2, .. }) => k1 == k2,
+            _ => false,
+        }
+    }
+}
+
+impl<Message: fmt::Debug> fmt::Debug for Subscription<Message> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Subscription::Interval { ms, msg } => {
+                f.debug_struct("Interval").field("ms", ms).field("msg", msg).finish()
+            }
+            Subscription::Window { trigger, .. } => {
+                f.debug_struct("Window").field("trigger", trigger).finish()
+            }
+            Subscription::Document { trigger, .. } => {
+                f.debug_struct("Document").field("trigger", trigger).finish()
+            }
+            Subscription::Source { key, .. } => {
+                f.debug_struct("Source").field("key", key).finish()
+            }
+        }
+    }
+}
+
+/// The set of sources a model is subscribed to.
+#[derive(Debug, PartialEq)]
+pub struct SubscriptionSet<Message>(Vec<Subscription<Message>>);
+
+impl<Message> Default for SubscriptionSet<Message> {
+    fn default() -> Self {
+        SubscriptionSet(vec![])
+    }
+}
+
+impl<Message> SubscriptionSet<Message> {
+    /// No subscriptions.
+    pub fn new() -> Self {
+        SubscriptionSet::default()
+    }
+
+    /// Add a subscription.
+    #[must_use]
+    pub fn push(mut self, subscription: Subscription<Message>) -> Self {
+        self.0.push(subscription);
+        self
+    }
+
+    /// Dispatch the given message every `ms` milliseconds.
+    #[must_use]
+    pub fn interval(self, ms: i32, msg: Message) -> Self {
+        self.push(Subscription::Interval { ms, msg })
+    }
+
+    /// Listen for the given event on the window.
+    #[must_use]
+    pub fn window(self, trigger: &'static str, handler: fn(web_sys::Event) -> Option<Message>) -> Self {
+        self.push(Subscription::Window { trigger, handler })
+    }
+
+    /// Listen for the given event on the document.
+    #[must_use]
+    pub fn document(self, trigger: &'static str, handler: fn(web_sys::Event) -> Option<Message>) -> Self {
+        self.push(Subscription::Document { trigger, handler })
+    }
+
+    /// Subscribe to an external source of messages identified by the given key.
+    ///
+    /// The given function is called with a [`Sink`] for messages when the subscription is added
+    /// and must return a function that unsubscribes from the source. It is not called again while
+    /// a source with the same key remains subscribed, even if a different function is given, so
+    /// change the key to subscribe with a new function.
+    ///
+    /// [`Sink`]: type.Sink.html
+    #[must_use]
+    pub fn source(
+        self,
+        key: impl Into<String>,
+        subscribe: impl Fn(Sink<Message>) -> Box<dyn FnOnce()> + 'static,
+    ) -> Self {
+        self.push(Subscription::Source { key: key.into(), subscribe: Rc::new(subscribe) })
+    }
+}
+
+/// An active subscription.
+enum Listener {
+    Interval(i32, Closure<dyn FnMut()>),
+    Event(web_sys::EventTarget, &'static str, Closure<dyn FnMut(web_sys::Event)>),
+    Source(Box<dyn FnOnce()>),
+}
+
+impl Listener {
+    fn subscribe<Message, Command>(subscription: &Subscription<Message>, app: &Dispatcher<Message, Command>) -> Self
+    where
+        Message: fmt::Debug + Clone + PartialEq + 'static,
+        Command: SideEffect<Message> + 'static,
+    {
+        let window = || web_sys::window()
+            .expect_throw("couldn't get window handle");
+
+        match *subscription {
+            Subscription::Interval { ms, ref msg } => {
+                let app = app.clone();
+                let msg = msg.clone();
+                let closure = Closure::wrap(
+                    Box::new(move || {
+                        app.dispatch(msg.clone());
+                    }) as Box<dyn FnMut()>
+                );
+
+                let handle = window()
+                    .set_interval_with_callback_and_timeout_and_arguments_0(closure.as_ref().unchecked_ref(), ms)
+                    .expect_throw("failed to set interval");
+
+                Listener::Interval(handle, closure)
+            }
+            Subscription::Window { trigger, handler } => {
+                Listener::listen(window().into(), trigger, handler, app)
+            }
+            Subscription::Document { trigger, handler } => {
+                let document = window().document()
+                    .expect_throw("couldn't get document handle");
+                Listener::listen(document.into(), trigger, handler, app)
+            }
+            Subscription::Source { ref subscribe, .. } => {
+                let app = app.clone();
+                Listener::Source(subscribe(Rc::new(move |msg| app.dispatch(msg))))
+            }
+        }
+    }
+
+    fn listen<Message, Command>(
+        target: web_sys::EventTarget,
+        trigger: &'static str,
+        handler: fn(web_sys::Event) -> Option<Message>,
+        app: &Dispatcher<Message, Command>,
+    ) -> Self
+    where
+        Message: fmt::Debug + Clone + PartialEq + 'static,
+        Command: SideEffect<Message> + 'static,
+    {
+        let app = app.clone();
+        let closure = Closure::wrap(
+            Box::new(move |event| {
+                if let Some(msg) = handler(event) {
+                    app.dispatch(msg);
+                }
+            }) as Box<dyn FnMut(web_sys::Event)>
+        );
+
+        target.add_event_listener_with_callback(trigger, closure.as_ref().unchecked_ref())
+            .expect_throw("failed to add event listener");
+
+        Listener::Event(target, trigger, closure)
+    }
+
+    fn unsubscribe(self) {
+        match self {
+            Listener::Interval(handle, _closure) => {
+                web_sys::window()
+                    .expect_throw("couldn't get window handle")
+                    .clear_interval_with_handle(handle);
+            }
+            Listener::Event(target, trigger, closure) => {
+                target.remove_event_listener_with_callback(trigger, closure.as_ref().unchecked_ref())
+                    .expect_throw("failed to remove event listener");
+            }
+            Listener::Source(unsubscribe) => unsubscribe(),
+        }
+    }
+}
+
+/// The active subscriptions of an app.
+pub(crate) struct Subscribed<Message>(Vec<(Subscription<Message>, Listener)>);
+
+impl<Message> Default for Subscribed<Message> {
+    fn default() -> Self {
+        Subscribed(vec![])
+    }
+}
+
+impl<Message> Subscribed<Message> {
+    /// Update the active subscriptions to match the given subscriptions.
+    ///
+    /// Subscriptions that are still present are left as is, new subscriptions are added and
+    /// subscriptions that are no longer present are removed.
+    pub(crate) fn update<Command>(&mut self, subscriptions: SubscriptionSet<Message>, app: &Dispatcher<Message, Command>)
+    where
+        Message: fmt::Debug + Clone + PartialEq + 'static,
+        Command: SideEffect<Message> + 'static,
+    {
+        let mut old = mem::take(&mut self.0);
+
+        for subscription in subscriptions.0 {
+            match old.iter().position(|(s, _)| *s == subscription) {
+                Some(i) => self.0.push(old.swap_remove(i)),
+                None => {
+                    let listener = Listener::subscribe(&subscription, app);
+                    self.0.push((subscription, listener));
+                }
+            }
+        }
+
+        for (_, listener) in old {
+            listener.unsubscribe();
+        }
+    }
+
+    /// Remove all subscriptions.
+    pub(crate) fn clear(&mut self) {
+        for (_, listener) in self.0.drain(..) {
+            listener.unsubscribe();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use crate::test::App;
+
+    fn resize(_: web_sys::Event) -> Option<u32> { Some(1) }
+    fn keydown(_: web_sys::Event) -> Option<u32> { Some(2) }
+
+    #[test]
+    fn subscription_eq() {
+        let subs = SubscriptionSet::new()
+            .interval(1000, 1)
+            .window("resize", resize)
+            .document("visibilitychange", resize)
+            .source("socket", |_| Box::new(|| {}));
+
+        assert_eq!(subs, SubscriptionSet::new()
+            .interval(1000, 1)
+            .window("resize", resize)
+            .document("visibilitychange", resize)
+            .source("socket", |_| Box::new(|| {})));
+
+        assert_ne!(subs.0[0], Subscription::Interval { ms: 1000, msg: 2 });
+        assert_ne!(subs.0[0], Subscription::Interval { ms: 500, msg: 1 });
+        assert_ne!(subs.0[1], Subscription::Window { trigger: "resize", handler: keydown });
+        assert_ne!(subs.0[1], Subscription::Window { trigger: "keydown", handler: resize });
+        assert_ne!(subs.0[1], Subscription::Document { trigger: "resize", handler: resize });
+        assert_ne!(subs.0[3], Subscription::Source { key: "other".into(), subscribe: Rc::new(|_| Box::new(|| {})) });
+    }
+
+    /// A source that logs when it is subscribed and unsubscribed.
+    fn logged(key: &'static str, log: &Rc<RefCell<Vec<String>>>) -> Subscription<()> {
+        let log = Rc::clone(log);
+        Subscription::Source {
+            key: key.into(),
+            subscribe: Rc::new(move |_| {
+                log.borrow_mut().push(format!("subscribe {}", key));
+                let log = Rc::clone(&log);
+                Box::new(move || log.borrow_mut().push(format!("unsubscribe {}", key)))
+            }),
+        }
+    }
+
+    #[test]
+    fn subscribed_update() {
+        let log = Rc::new(RefCell::new(vec![]));
+        let app = App::dispatcher();
+        let mut subscribed = Subscribed::default();
+
+        // new sources are subscribed
+        subscribed.update(SubscriptionSet::new().push(logged("a", &log)).push(logged("b", &log)), &app);
+        assert_eq!(log.take(), vec!["subscribe a", "subscribe b"]);
+
+        // kept sources are left alone, missing ones are unsubscribed
+        subscribed.update(SubscriptionSet::new().push(logged("b", &log)).push(logged("c", &log)), &app);
+        assert_eq!(log.take(), vec!["subscribe c", "unsubscribe a"]);
+
+        // the same subscriptions change nothing
+        subscribed.update(SubscriptionSet::new().push(logged("c", &log)).push(logged("b", &log)), &app);
+        assert!(log.borrow().is_empty());
+
+        // no subscriptions unsubscribes everything
+        subscribed.update(SubscriptionSet::new(), &app);
+        let mut unsubscribed = log.take();
+        unsubscribed.sort();
+        assert_eq!(unsubscribed, vec!["unsubscribe b", "unsubscribe c"]);
+    }
+
+    #[test]
+    fn subscribed_source_key() {
+        let log = Rc::new(RefCell::new(vec![]));
+        let other = Rc::new(RefCell::new(vec![]));
+        let app = App::dispatcher();
+        let mut subscribed = Subscribed::default();
+
+        subscribed.update(SubscriptionSet::new().push(logged("a", &log)), &app);
+        assert_eq!(log.take(), vec!["subscribe a"]);
+
+        // only the key identifies a source, a different subscribe function is ignored
+        subscribed.update(SubscriptionSet::new().push(logged("a", &other)), &app);
+        assert!(log.borrow().is_empty());
+        assert!(other.borrow().is_empty());
+    }
+
+    #[test]
+    fn subscribed_clear() {
+        let log = Rc::new(RefCell::new(vec![]));
+        let app = App::dispatcher();
+        let mut subscribed = Subscribed::default();
+
+        subscribed.update(SubscriptionSet::new().push(logged("a", &log)).push(logged("b", &log)), &app);
+        log.take();
+
+        subscribed.clear();
+        assert_eq!(log.take(), vec!["unsubscribe a", "unsubscribe b"]);
+
+        // nothing is left to unsubscribe
+        subscribed.clear();
+        assert!(log.borrow().is_empty());
+    }
+}
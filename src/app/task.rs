@@ -0,0 +1,196 @@
+//! Asynchronous side effects.
+//!
+//! Futures are spawned on the browser's event loop and the messages they resolve to are
+//! dispatched back to the app. Any futures that are still running when the app is detached are
+//! canceled and dropped.
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::rc::{Rc, Weak};
+use std::cell::{Cell, RefCell};
+use std::task::{Context, Poll, Waker};
+use crate::app::{Dispatcher, SideEffect};
+
+/// A side effect that resolves to a message asynchronously.
+///
+/// When processed the future is spawned and the resulting message, if any, is dispatched to the
+/// app. Apps can include this in their own command type and process it from their
+/// [`SideEffect`] implementation.
+///
+/// ```no_run
+/// use euca::app::{Dispatcher, SideEffect, Task};
+///
+/// #[derive(Clone, PartialEq, Debug)]
+/// enum Message {
+///     Fetched(String),
+/// }
+///
+/// enum Command {
+///     Fetch(Task<Message>),
+/// }
+///
+/// impl SideEffect<Message> for Command {
+///     fn process(self, dispatcher: &Dispatcher<Message, Command>) {
+///         match self {
+///             Command::Fetch(task) => task.process(dispatcher),
+///         }
+///     }
+/// }
+///
+/// # async fn fetch() -> String { String::new() }
+/// let cmd = Command::Fetch(Task::new(async {
+///     Message::Fetched(fetch().await)
+/// }));
+/// ```
+///
+/// [`SideEffect`]: ../side_effect/trait.SideEffect.html
+pub struct Task<Message>(Pin<Box<dyn Future<Output = Option<Message>>>>);
+
+impl<Message> Task<Message> {
+    /// Create a task from a future that resolves to a message.
+    pub fn new(future: impl Future<Output = Message> + 'static) -> Self {
+        Task(Box::pin(async { Some(future.await) }))
+    }
+
+    /// Create a task from a future that may resolve to a message.
+    pub fn optional(future: impl Future<Output = Option<Message>> + 'static) -> Self {
+        Task(Box::pin(future))
+    }
+
+    /// Spawn this task, dispatching the resulting message to the given dispatcher.
+    pub fn process<Command>(self, dispatcher: &Dispatcher<Message, Command>)
+    where
+        Message: fmt::Debug + Clone + PartialEq + 'static,
+        Command: SideEffect<Message> + 'static,
+    {
+        dispatcher.spawn(self.0);
+    }
+}
+
+impl<Message> SideEffect<Message> for Task<Message>
+where
+    Message: fmt::Debug + Clone + PartialEq + 'static,
+{
+    fn process(self, dispatcher: &Dispatcher<Message, Self>) {
+        Task::process(self, dispatcher)
+    }
+}
+
+impl<Message> fmt::Debug for Task<Message> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Task(_)")
+    }
+}
+
+/// Shared state used to cancel a spawned future.
+#[derive(Default)]
+struct Handle {
+    canceled: Cell<bool>,
+    waker: RefCell<Option<Waker>>,
+}
+
+/// The futures spawned for an app.
+#[derive(Default)]
+pub(crate) struct Tasks(Vec<Weak<Handle>>);
+
+impl Tasks {
+    /// Track the given future, it will resolve to `None` if it is canceled.
+    pub(crate) fn track<F: Future>(&mut self, future: F) -> impl Future<Output = Option<F::Output>> {
+        // forget about futures that have completed
+        self.0.retain(|handle| handle.strong_count() > 0);
+
+        let handle = Rc::new(Handle::default());
+        self.0.push(Rc::downgrade(&handle));
+
+        Cancelable {
+            future: Box::pin(future),
+            handle,
+        }
+    }
+
+    /// Cancel all running futures.
+    pub(crate) fn cancel(&mut self) {
+        for handle in self.0.drain(..).filter_map(|handle| handle.upgrade()) {
+            handle.canceled.set(true);
+            // wake the future so the executor drops it
+            if let Some(waker) = handle.waker.borrow_mut().take() {
+                waker.wake();
+            }
+        }
+    }
+
+    /// The number of futures that may still be running.
+    #[cfg(test)]
+    fn len(&self) -> usize {
+        self.0.iter().filter(|handle| handle.strong_count() > 0).count()
+    }
+}
+
+/// A future that can be canceled.
+struct Cancelable<F: Future> {
+    future: Pin<Box<F>>,
+    handle: Rc<Handle>,
+}
+
+impl<F: Future> Future for Cancelable<F> {
+    type Output = Option<F::Output>;
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+        if self.handle.canceled.get() {
+            return Poll::Ready(None);
+        }
+
+        *self.handle.waker.borrow_mut() = Some(cx.waker().clone());
+        self.future.as_mut().poll(cx).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A future that is pending until it is polled the given number of times.
+    struct Countdown(usize);
+
+    impl Future for Countdown {
+        type Output = &'static str;
+
+        fn poll(mut self: Pin<&mut Self>, cx: &mut Context) -> Poll<Self::Output> {
+            if self.0 == 0 {
+                Poll::Ready("done")
+            }
+            else {
+                self.0 -= 1;
+                cx.waker().wake_by_ref();
+                Poll::Pending
+            }
+        }
+    }
+
+    #[test]
+    fn track_completes() {
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut tasks = Tasks::default();
+
+        let mut future = Box::pin(tasks.track(Countdown(1)));
+        assert_eq!(tasks.len(), 1);
+        assert_eq!(future.as_mut().poll(&mut cx), Poll::Pending);
+        assert_eq!(future.as_mut().poll(&mut cx), Poll::Ready(Some("done")));
+
+        drop(future);
+        assert_eq!(tasks.len(), 0);
+    }
+
+    #[test]
+    fn track_canceled() {
+        let mut cx = Context::from_waker(Waker::noop());
+        let mut tasks = Tasks::default();
+
+        let mut future = Box::pin(tasks.track(Countdown(5)));
+        assert_eq!(future.as_mut().poll(&mut cx), Poll::Pending);
+
+        tasks.cancel();
+        assert_eq!(future.as_mut().poll(&mut cx), Poll::Ready(None));
+    }
+}
@@ -0,0 +1,159 @@
+//! Pluggable DOM backends.
+//!
+//! A [`Backend`] provides the primitive DOM operations used by [`PatchSet::apply`] to move a dom
+//! tree to the state described by a patch set. The default backend, [`Web`], operates on the
+//! browser's dom via [`web_sys`]. Alternative implementations allow patch sets to be applied
+//! without a browser, for example from native unit tests. The [`Delegated`] backend also operates
+//! on the browser's dom, but handles events with a single listener per event type at the root of
+//! the app.
+//!
+//! [`Backend`]: trait.Backend.html
+//! [`Web`]: web/struct.Web.html
+//! [`Delegated`]: delegated/struct.Delegated.html
+//! [`PatchSet::apply`]: ../patch/struct.PatchSet.html#method.apply
+//! [`web_sys`]: https://rustwasm.github.io/wasm-bindgen/api/web_sys/
+
+pub mod web;
+pub mod delegated;
+
+pub use crate::backend::web::Web;
+pub use crate::backend::delegated::Delegated;
+
+use std::fmt;
+use crate::vdom::{BoundHook, EventHandler, Hook, Lifecycle, ListenerOptions, Namespace, PortalTarget, PropertyValue};
+use crate::app::{Dispatcher, SideEffect};
+
+/// The operations a dom implementation must provide in order to have patches applied to it.
+pub trait Backend {
+    /// A generic node in the dom tree.
+    type Node: Clone;
+    /// An element node.
+    type Element: Clone + PartialEq + fmt::Debug + Into<Self::Node> + 'static;
+    /// A text node.
+    type Text: Clone + fmt::Debug + Into<Self::Node>;
+    /// A handle to a registered event listener.
+    type Listener;
+
+    /// Create an element with the given name in the given namespace.
+    fn create_element(&self, name: &str, namespace: Namespace) -> Self::Element;
+
+    /// Create a text node with the given value.
+    fn create_text(&self, text: &str) -> Self::Text;
+
+    /// Replace the value of a text node.
+    fn set_text(&self, node: &Self::Text, text: &str);
+
+    /// Set an attribute on an element.
+    fn set_attribute(&self, element: &Self::Element, name: &str, value: &str);
+
+    /// Set an attribute in the given namespace on an element. The name is the qualified name of the
+    /// attribute (e.g. `xlink:href`). Namespaced attributes are removed by their qualified name
+    /// with [`remove_attribute`].
+    ///
+    /// [`remove_attribute`]: #tymethod.remove_attribute
+    fn set_attribute_ns(&self, element: &Self::Element, namespace: &str, name: &str, value: &str);
+
+    /// Remove an attribute from an element.
+    fn remove_attribute(&self, element: &Self::Element, name: &str);
+
+    /// Add a class to an element.
+    fn add_class(&self, element: &Self::Element, name: &str);
+
+    /// Remove a class from an element.
+    fn remove_class(&self, element: &Self::Element, name: &str);
+
+    /// Set an inline style property on an element.
+    fn set_style(&self, element: &Self::Element, property: &str, value: &str);
+
+    /// Remove an inline style property from an element.
+    fn remove_style(&self, element: &Self::Element, property: &str);
+
+    /// Set a property on an element.
+    fn set_property(&self, element: &Self::Element, name: &str, value: &PropertyValue);
+
+    /// Remove a property from an element. Properties can't really be removed from DOM objects,
+    /// so this resets the property to `null`.
+    fn remove_property(&self, element: &Self::Element, name: &str);
+
+    /// Replace the children of an element with the given raw html.
+    fn set_inner_html(&self, element: &Self::Element, html: &str);
+
+    /// Remove all of the children of the given node.
+    fn remove_children(&self, node: &Self::Node);
+
+    /// Insert `child` into `parent` before `sibling`, or at the end if there is no sibling.
+    fn insert_before(&self, parent: &Self::Node, child: &Self::Node, sibling: Option<&Self::Node>);
+
+    /// Get the parent of the given node, if any.
+    fn parent(&self, node: &Self::Node) -> Option<Self::Node>;
+
+    /// Remove the given node from its parent.
+    fn remove(&self, node: &Self::Node);
+
+    /// Possibly get the element represented by the given node.
+    fn as_element<'n>(&self, node: &'n Self::Node) -> Option<&'n Self::Element>;
+
+    /// Possibly get the text node represented by the given node.
+    fn as_text<'n>(&self, node: &'n Self::Node) -> Option<&'n Self::Text>;
+
+    /// Get the children of the given node.
+    fn children(&self, node: &Self::Node) -> Vec<Self::Node>;
+
+    /// Get the name of the given element.
+    fn element_name(&self, element: &Self::Element) -> String;
+
+    /// Get the namespace of the given element.
+    fn element_namespace(&self, element: &Self::Element) -> Namespace;
+
+    /// Get the value of a text node.
+    fn text(&self, node: &Self::Text) -> String;
+
+    /// Get the value of an attribute of the given element, if it is set.
+    fn attribute(&self, element: &Self::Element, name: &str) -> Option<String>;
+
+    /// Get the names of all the attributes set on the given element.
+    fn attribute_names(&self, element: &Self::Element) -> Vec<String>;
+
+    /// Register an event listener on the given element. Messages generated by the handler are
+    /// dispatched via the given [`Dispatcher`].
+    ///
+    /// [`Dispatcher`]: ../app/dispatch/struct.Dispatcher.html
+    fn add_listener<Message, Command>(
+        &self,
+        element: &Self::Element,
+        trigger: &str,
+        handler: EventHandler<Message>,
+        options: ListenerOptions,
+        app: &Dispatcher<Message, Command>,
+    ) -> Self::Listener
+    where
+        Message: Clone + PartialEq + fmt::Debug + 'static,
+        Command: SideEffect<Message> + 'static;
+
+    /// Unregister an event listener from the given element. The options are the options the
+    /// listener was added with.
+    fn remove_listener(&self, element: &Self::Element, trigger: &str, listener: &Self::Listener, options: ListenerOptions);
+
+    /// Find the element a portal renders its children into, `None` if there is no such element.
+    fn portal_target(&self, target: &PortalTarget) -> Option<Self::Element>;
+
+    /// Bind a lifecycle hook to the given element. The returned closure runs the hook, it is
+    /// called at the point in the element's lifecycle the hook was declared for.
+    fn bind_hook<Message, Command>(
+        &self,
+        lifecycle: Lifecycle,
+        hook: Hook<Message, Command>,
+        element: &Self::Element,
+    ) -> BoundHook<Message, Command>
+    where
+        Message: 'static,
+        Command: 'static;
+
+    /// Convert a node created by a [`Component`] into a node of this backend.
+    ///
+    /// Components always render into the browser's dom. Backends that cannot host browser nodes
+    /// don't support components and return `None`; such components are left out of the dom.
+    ///
+    /// [`Component`]: ../component/trait.Component.html
+    fn component_node(&self, node: web_sys::Node) -> Option<Self::Node>;
+}
@@ -0,0 +1,495 @@
+//! A backend that delegates event handling to listeners at the root of an app.
+//!
+//! Instead of registering a listener on every element with a handler, [`Delegated`] registers
+//! one listener per event type on the app's root element and keeps a map from each element to
+//! its handlers. When an event reaches the root, the handlers are found by walking from the
+//! event's target up through its ancestors. This greatly reduces the number of listeners
+//! registered with the browser for apps that render many elements with handlers.
+//!
+//! Use [`AppBuilder::delegate_events`] to enable this for an app.
+//!
+//! Delegation changes a few details of how handlers run:
+//!
+//! - Handlers for bubbling events run once the event reaches the root, after any listeners
+//!   registered directly on elements by other code.
+//! - `event.current_target()` is the root element, not the element the handler is attached to.
+//! - Events that don't bubble (e.g. `focus` or `mouseenter`) are caught at the root during the
+//!   capture phase and only dispatched to handlers on the event's target.
+//!
+//! Listeners with the `capture`, `passive`, or `once` [`ListenerOptions`], and link handlers
+//! (which need the element they are attached to), can't be delegated. These are registered
+//! directly on their element, as with the [`Web`] backend.
+//!
+//! The target elements of portals also get root listeners, so events in portal content are
+//! delegated as well. Each root handles the part of an event's path below it, up to the next root
+//! in the path. The listeners on portal targets remain until the backend is dropped.
+//!
+//! [`Delegated`]: struct.Delegated.html
+//! [`Web`]: ../web/struct.Web.html
+//! [`ListenerOptions`]: ../../vdom/struct.ListenerOptions.html
+//! [`AppBuilder::delegate_events`]: ../../app/struct.AppBuilder.html#method.delegate_events
+
+use std::fmt;
+use std::rc::{Rc, Weak};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use crate::backend::Backend;
+use crate::backend::web::{Web, event_handler};
+use crate::vdom::{BoundHook, EventHandler, Hook, Lifecycle, ListenerOptions, Namespace, PortalTarget, PropertyValue};
+use crate::app::{Dispatcher, SideEffect};
+
+/// Used to give each registry a unique property name for storing element ids.
+static REGISTRIES: AtomicU32 = AtomicU32::new(0);
+
+type Handler = Rc<RefCell<Box<dyn FnMut(web_sys::Event)>>>;
+type Handlers = Vec<(u32, Handler, ListenerOptions)>;
+type RootListeners = (Closure<dyn FnMut(web_sys::Event)>, Closure<dyn FnMut(web_sys::Event)>);
+
+/// The handlers registered on the elements of an app.
+struct Registry {
+    /// The root element of the app.
+    root: web_sys::Element,
+    /// The target elements of portals, these get root listeners in addition to the app's root.
+    portals: Vec<web_sys::Element>,
+    /// The name of the property used to store an id on each element with a handler.
+    property: JsValue,
+    /// The next id to hand out for elements and handlers.
+    next_id: u32,
+    /// The handlers for each (element id, trigger) pair, in the order they were added.
+    handlers: HashMap<(u32, String), Handlers>,
+    /// The (bubble, capture) phase listeners registered on the roots for each trigger.
+    roots: HashMap<String, RootListeners>,
+}
+
+/// Add the given root listeners to the given element.
+fn add_root_listeners(root: &web_sys::EventTarget, trigger: &str, (bubble, capture): &RootListeners) {
+    root.add_event_listener_with_callback_and_bool(trigger, bubble.as_ref().unchecked_ref(), false)
+        .expect("failed to add event listener");
+    root.add_event_listener_with_callback_and_bool(trigger, capture.as_ref().unchecked_ref(), true)
+        .expect("failed to add event listener");
+}
+
+/// Remove the given root listeners from the given element.
+fn remove_root_listeners(root: &web_sys::EventTarget, trigger: &str, (bubble, capture): &RootListeners) {
+    root.remove_event_listener_with_callback_and_bool(trigger, bubble.as_ref().unchecked_ref(), false)
+        .expect("failed to remove event listener");
+    root.remove_event_listener_with_callback_and_bool(trigger, capture.as_ref().unchecked_ref(), true)
+        .expect("failed to remove event listener");
+}
+
+impl Registry {
+    fn next_id(&mut self) -> u32 {
+        self.next_id += 1;
+        self.next_id
+    }
+
+    /// Get the id of the given node, if it has one.
+    fn id(&self, node: &web_sys::Node) -> Option<u32> {
+        js_sys::Reflect::get(node, &self.property)
+            .ok()
+            .and_then(|id| id.as_f64())
+            .map(|id| id as u32)
+    }
+
+    /// Get the id of the given element, assigning one if necessary.
+    fn element_id(&mut self, element: &web_sys::Element) -> u32 {
+        if let Some(id) = self.id(element.as_ref()) {
+            return id;
+        }
+
+        let id = self.next_id();
+        js_sys::Reflect::set(element, &self.property, &JsValue::from(id))
+            .expect("failed to set element id");
+        id
+    }
+
+    /// The handlers registered on the given node for the given trigger.
+    fn handlers(&self, node: &web_sys::Node, trigger: &str) -> Vec<(Handler, ListenerOptions)> {
+        self.id(node)
+            .and_then(|id| self.handlers.get(&(id, trigger.to_owned())))
+            .map(|handlers| {
+                handlers.iter()
+                    .map(|(_, handler, options)| (Rc::clone(handler), *options))
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    /// The elements root listeners are registered on.
+    fn root_elements(&self) -> impl Iterator<Item = &web_sys::Element> {
+        std::iter::once(&self.root).chain(self.portals.iter())
+    }
+
+    /// Returns true if root listeners are registered on the given node.
+    fn is_root(&self, node: &web_sys::Node) -> bool {
+        self.root_elements().any(|root| root.is_same_node(Some(node)))
+    }
+
+    /// Register the existing root listeners on the target of a portal, if we haven't already.
+    fn add_portal(&mut self, target: &web_sys::Element) {
+        if self.is_root(target.as_ref()) {
+            return;
+        }
+
+        for (trigger, listeners) in self.roots.iter() {
+            add_root_listeners(target.as_ref(), trigger, listeners);
+        }
+        self.portals.push(target.clone());
+    }
+
+    fn remove(&mut self, node: u32, trigger: &str, id: u32) {
+        let key = (node, trigger.to_owned());
+        if let Some(handlers) = self.handlers.get_mut(&key) {
+            handlers.retain(|(i, _, _)| *i != id);
+            if handlers.is_empty() {
+                self.handlers.remove(&key);
+            }
+        }
+    }
+
+    /// Register listeners for the given trigger on the roots, if we haven't already.
+    fn listen(registry: &Rc<RefCell<Registry>>, trigger: &str) {
+        if registry.borrow().roots.contains_key(trigger) {
+            return;
+        }
+
+        let listener = |bubbles| {
+            let registry = Rc::downgrade(registry);
+            let trigger = trigger.to_owned();
+            Closure::wrap(
+                Box::new(move |event| {
+                    dispatch(&registry, &trigger, event, bubbles);
+                }) as Box<dyn FnMut(web_sys::Event)>
+            )
+        };
+        let listeners = (listener(true), listener(false));
+
+        let mut registry = registry.borrow_mut();
+        for root in registry.root_elements() {
+            add_root_listeners(root.as_ref(), trigger, &listeners);
+        }
+
+        registry.roots.insert(trigger.to_owned(), listeners);
+    }
+}
+
+impl Drop for Registry {
+    fn drop(&mut self) {
+        for (trigger, listeners) in self.roots.iter() {
+            for root in self.root_elements() {
+                remove_root_listeners(root.as_ref(), trigger, listeners);
+            }
+        }
+    }
+}
+
+/// Dispatch an event caught at a root to the handlers of the target and its ancestors.
+///
+/// Bubbling events are handled by the bubble phase listener, other events are handled by the
+/// capture phase listener and are only dispatched to the target. Only the part of the event's path
+/// below the root, up to the next root in the path, is handled. The rest of the path is handled
+/// by the listeners of the other root.
+fn dispatch(registry: &Weak<RefCell<Registry>>, trigger: &str, event: web_sys::Event, bubbles: bool) {
+    if event.bubbles() != bubbles {
+        return;
+    }
+
+    let registry = match registry.upgrade() {
+        Some(registry) => registry,
+        None => return,
+    };
+    let root = match event.current_target().and_then(|root| root.dyn_into::<web_sys::Node>().ok()) {
+        Some(root) => root,
+        None => return,
+    };
+
+    let mut path = vec![];
+    let mut node = event.target()
+        .and_then(|target| target.dyn_into::<web_sys::Node>().ok());
+
+    while let Some(current) = node {
+        if root.is_same_node(Some(&current)) {
+            break;
+        }
+
+        // the path below another root was handled by that root
+        if registry.borrow().is_root(&current) {
+            if !bubbles {
+                return;
+            }
+            path.clear();
+        }
+        else {
+            path.push(current.clone());
+        }
+
+        node = current.parent_node();
+    }
+
+    for current in path {
+        // handlers may modify the registry, so don't hold on to it while they run
+        let handlers = registry.borrow().handlers(&current, trigger);
+        for (handler, options) in handlers {
+            if options.prevent_default {
+                event.prevent_default();
+            }
+            if options.stop_propagation && bubbles {
+                event.stop_propagation();
+            }
+            if let Ok(mut handler) = handler.try_borrow_mut() {
+                handler(event.clone());
+            }
+        }
+
+        if !bubbles || event.cancel_bubble() {
+            break;
+        }
+    }
+}
+
+/// A handle to an event listener registered by the [`Delegated`] backend.
+///
+/// Dropping the handle unregisters the listener.
+///
+/// [`Delegated`]: struct.Delegated.html
+pub struct Listener(Registration);
+
+enum Registration {
+    /// A listener registered directly on an element.
+    Direct(Closure<dyn FnMut(web_sys::Event)>),
+    /// A handler in the registry.
+    Delegated {
+        registry: Weak<RefCell<Registry>>,
+        node: u32,
+        trigger: String,
+        id: u32,
+    },
+}
+
+impl Drop for Listener {
+    fn drop(&mut self) {
+        if let Registration::Delegated { ref registry, node, ref trigger, id } = self.0 {
+            if let Some(registry) = registry.upgrade() {
+                registry.borrow_mut().remove(node, trigger, id);
+            }
+        }
+    }
+}
+
+/// A backend that applies patches to the browser's dom and delegates event handling to
+/// listeners on a root element.
+#[derive(Clone)]
+pub struct Delegated {
+    web: Web,
+    registry: Rc<RefCell<Registry>>,
+}
+
+impl Delegated {
+    /// Create a backend delegating events to listeners on the given root element.
+    ///
+    /// Only events on descendants of the root, or of the target of a portal, are dispatched. The
+    /// root listeners are removed when the last clone of this backend is dropped.
+    pub fn new(root: web_sys::Element) -> Self {
+        let registry = REGISTRIES.fetch_add(1, Ordering::Relaxed);
+
+        Delegated {
+            web: Web::default(),
+            registry: Rc::new(RefCell::new(Registry {
+                root,
+                portals: vec![],
+                property: JsValue::from(format!("__euca_{}", registry)),
+                next_id: 0,
+                handlers: HashMap::new(),
+                roots: HashMap::new(),
+            })),
+        }
+    }
+}
+
+impl fmt::Debug for Delegated {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Delegated")
+            .field("root", &self.registry.borrow().root)
+            .finish()
+    }
+}
+
+impl Backend for Delegated {
+    type Node = web_sys::Node;
+    type Element = web_sys::Element;
+    type Text = web_sys::Text;
+    type Listener = Listener;
+
+    fn create_element(&self, name: &str, namespace: Namespace) -> web_sys::Element {
+        self.web.create_element(name, namespace)
+    }
+
+    fn create_text(&self, text: &str) -> web_sys::Text {
+        self.web.create_text(text)
+    }
+
+    fn set_text(&self, node: &web_sys::Text, text: &str) {
+        self.web.set_text(node, text)
+    }
+
+    fn set_attribute(&self, element: &web_sys::Element, name: &str, value: &str) {
+        self.web.set_attribute(element, name, value)
+    }
+
+    fn set_attribute_ns(&self, element: &web_sys::Element, namespace: &str, name: &str, value: &str) {
+        self.web.set_attribute_ns(element, namespace, name, value)
+    }
+
+    fn remove_attribute(&self, element: &web_sys::Element, name: &str) {
+        self.web.remove_attribute(element, name)
+    }
+
+    fn add_class(&self, element: &web_sys::Element, name: &str) {
+        self.web.add_class(element, name)
+    }
+
+    fn remove_class(&self, element: &web_sys::Element, name: &str) {
+        self.web.remove_class(element, name)
+    }
+
+    fn set_style(&self, element: &web_sys::Element, property: &str, value: &str) {
+        self.web.set_style(element, property, value)
+    }
+
+    fn remove_style(&self, element: &web_sys::Element, property: &str) {
+        self.web.remove_style(element, property)
+    }
+
+    fn set_property(&self, element: &web_sys::Element, name: &str, value: &PropertyValue) {
+        self.web.set_property(element, name, value)
+    }
+
+    fn remove_property(&self, element: &web_sys::Element, name: &str) {
+        self.web.remove_property(element, name)
+    }
+
+    fn set_inner_html(&self, element: &web_sys::Element, html: &str) {
+        self.web.set_inner_html(element, html)
+    }
+
+    fn remove_children(&self, node: &web_sys::Node) {
+        self.web.remove_children(node)
+    }
+
+    fn insert_before(&self, parent: &web_sys::Node, child: &web_sys::Node, sibling: Option<&web_sys::Node>) {
+        self.web.insert_before(parent, child, sibling)
+    }
+
+    fn parent(&self, node: &web_sys::Node) -> Option<web_sys::Node> {
+        self.web.parent(node)
+    }
+
+    fn remove(&self, node: &web_sys::Node) {
+        self.web.remove(node)
+    }
+
+    fn as_element<'n>(&self, node: &'n web_sys::Node) -> Option<&'n web_sys::Element> {
+        self.web.as_element(node)
+    }
+
+    fn as_text<'n>(&self, node: &'n web_sys::Node) -> Option<&'n web_sys::Text> {
+        self.web.as_text(node)
+    }
+
+    fn children(&self, node: &web_sys::Node) -> Vec<web_sys::Node> {
+        self.web.children(node)
+    }
+
+    fn element_name(&self, element: &web_sys::Element) -> String {
+        self.web.element_name(element)
+    }
+
+    fn element_namespace(&self, element: &web_sys::Element) -> Namespace {
+        self.web.element_namespace(element)
+    }
+
+    fn text(&self, node: &web_sys::Text) -> String {
+        self.web.text(node)
+    }
+
+    fn attribute(&self, element: &web_sys::Element, name: &str) -> Option<String> {
+        self.web.attribute(element, name)
+    }
+
+    fn attribute_names(&self, element: &web_sys::Element) -> Vec<String> {
+        self.web.attribute_names(element)
+    }
+
+    fn add_listener<Message, Command>(
+        &self,
+        element: &web_sys::Element,
+        trigger: &str,
+        handler: EventHandler<Message>,
+        options: ListenerOptions,
+        app: &Dispatcher<Message, Command>,
+    ) -> Listener
+    where
+        Message: Clone + PartialEq + fmt::Debug + 'static,
+        Command: SideEffect<Message> + 'static,
+    {
+        // these can't be emulated from the root
+        if options.capture || options.passive || options.once || matches!(handler, EventHandler::Link) {
+            let closure = self.web.add_listener(element, trigger, handler, options, app);
+            return Listener(Registration::Direct(closure));
+        }
+
+        let handler = Rc::new(RefCell::new(event_handler(handler, app)));
+
+        let (node, id) = {
+            let mut registry = self.registry.borrow_mut();
+            let node = registry.element_id(element);
+            let id = registry.next_id();
+            registry.handlers.entry((node, trigger.to_owned()))
+                .or_default()
+                .push((id, handler, options));
+            (node, id)
+        };
+        Registry::listen(&self.registry, trigger);
+
+        Listener(Registration::Delegated {
+            registry: Rc::downgrade(&self.registry),
+            node,
+            trigger: trigger.to_owned(),
+            id,
+        })
+    }
+
+    fn remove_listener(&self, element: &web_sys::Element, trigger: &str, listener: &Listener, options: ListenerOptions) {
+        // delegated handlers are removed from the registry when the listener is dropped
+        if let Registration::Direct(ref closure) = listener.0 {
+            self.web.remove_listener(element, trigger, closure, options);
+        }
+    }
+
+    fn portal_target(&self, target: &PortalTarget) -> Option<web_sys::Element> {
+        let target = self.web.portal_target(target)?;
+        self.registry.borrow_mut().add_portal(&target);
+        Some(target)
+    }
+
+    fn bind_hook<Message, Command>(
+        &self,
+        _lifecycle: Lifecycle,
+        hook: Hook<Message, Command>,
+        element: &web_sys::Element,
+    ) -> BoundHook<Message, Command>
+    where
+        Message: 'static,
+        Command: 'static,
+    {
+        let element = element.clone();
+        Box::new(move || hook.call(&element))
+    }
+
+    fn component_node(&self, node: web_sys::Node) -> Option<web_sys::Node> {
+        Some(node)
+    }
+}
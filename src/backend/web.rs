@@ -0,0 +1,549 @@
+//! A backend operating on the browser's dom via web_sys.
+
+use std::fmt;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use crate::backend::Backend;
+use crate::vdom::{BoundHook, EventHandler, Hook, Lifecycle, ListenerOptions, Namespace, PortalTarget, PropertyValue};
+use crate::app::{Dispatcher, SideEffect};
+use crate::route::Navigate;
+use crate::route::navigate::is_internal;
+use log::warn;
+
+macro_rules! attribute_setter_match_arm {
+    ( $node:ident, $setter:ident, $attr:literal, $value:ident, [ $node_type1:path $(, $node_type:path )* ] ) => {
+        {
+            if let Some(elem) = $node.dyn_ref::<$node_type1>() {
+                if let Ok(value) = $value.parse() {
+                    elem.$setter(value);
+                }
+                else if $value == $attr {
+                    elem.$setter(true);
+                }
+                else {
+                    warn!("non boolean value '{}' set for '{}' attribute", $value, $attr);
+                    $node.set_attribute($attr, $value)
+                        .expect("failed to set attribute");
+                }
+            }
+            $(else if let Some(elem) = $node.dyn_ref::<$node_type>() {
+                if let Ok(value) = $value.parse() {
+                    elem.$setter(value);
+                }
+                else if $value == $attr {
+                    elem.$setter(true);
+                }
+                else {
+                    warn!("non boolean value '{}' set for '{}' attribute", $value, $attr);
+                    $node.set_attribute($attr, $value)
+                        .expect("failed to set attribute");
+                }
+            })*
+            else {
+                $node.set_attribute($attr, $value)
+                    .expect("failed to set attribute");
+                warn!("attribute '{}' set for '{}' element, expected one of {}",
+                    $attr, $node.node_name(), stringify!($($node_type),*));
+            }
+        }
+    };
+}
+
+macro_rules! attribute_setter {
+    ( $node:ident, $name:ident, $value:ident, [ $( $attr:literal => $setter:ident [ $( $node_type:path ,)* ] ,)* ] ) => {
+        attribute_setter!($node, $name, $value, [ $( $attr => $setter [ $( $node_type ),* ] ),* ] )
+    };
+    ( $node:ident, $name:ident, $value:ident, [ $( $attr:literal => $setter:ident [ $( $node_type:path ),* ] ),* ] ) => {
+        match $name {
+            $( $attr => { attribute_setter_match_arm!($node, $setter, $attr, $value, [ $($node_type),* ]) } )*
+            _ => {
+                $node.set_attribute($name, $value)
+                    .expect("failed to set attribute");
+            }
+        }
+    };
+}
+
+macro_rules! attribute_unsetter_match_arm {
+    ( $node:ident, $setter:ident, $attr:literal, [ $node_type1:path $(, $node_type:path )* ] ) => {
+        if let Some(elem) = $node.dyn_ref::<$node_type1>() {
+            elem.$setter(false);
+        }
+        $(else if let Some(elem) = $node.dyn_ref::<$node_type>() {
+            elem.$setter(false);
+        })*
+        else {
+            $node.remove_attribute($attr)
+                .expect("failed to set attribute");
+            warn!("attribute '{}' removed for '{}' element, expected one of {}",
+                $attr, $node.node_name(), stringify!($($node_type),*));
+        }
+    };
+}
+
+macro_rules! attribute_unsetter {
+    ( $node:ident, $name:ident, [ $( $attr:literal => $setter:ident [ $( $node_type:path ,)* ] ,)* ] ) => {
+        attribute_unsetter!($node, $name, [ $( $attr => $setter [ $( $node_type ),* ] ),* ] )
+    };
+    ( $node:ident, $name:ident, [ $( $attr:literal => $setter:ident [ $( $node_type:path ),* ] ),* ] ) => {
+        match $name {
+            $( $attr => { attribute_unsetter_match_arm!($node, $setter, $attr, [ $($node_type),* ]) } )*
+            _ => {
+                $node.remove_attribute($name)
+                    .expect("failed to remove attribute");
+            }
+        }
+    };
+}
+
+/// A backend that applies patches to the browser's dom.
+#[derive(Debug, Clone)]
+pub struct Web {
+    document: web_sys::Document,
+}
+
+impl Default for Web {
+    fn default() -> Self {
+        Web {
+            document: web_sys::window().expect("expected window")
+                .document().expect("expected document"),
+        }
+    }
+}
+
+/// Build a function that handles an event by dispatching the message produced by the given
+/// handler to the app.
+pub(crate) fn event_handler<Message, Command>(
+    handler: EventHandler<Message>,
+    app: &Dispatcher<Message, Command>,
+) -> Box<dyn FnMut(web_sys::Event)>
+where
+    Message: Clone + PartialEq + fmt::Debug + 'static,
+    Command: SideEffect<Message> + 'static,
+{
+    let app = app.clone();
+    match handler {
+        EventHandler::Msg(msg) => {
+            let msg = msg.clone();
+            Box::new(move |_| {
+                app.dispatch(msg.clone());
+            }) as Box<dyn FnMut(web_sys::Event)>
+        }
+        EventHandler::Fn(fun) => {
+            Box::new(move |event| {
+                if let Some(msg) = fun(event) {
+                    app.dispatch(msg);
+                }
+            }) as Box<dyn FnMut(web_sys::Event)>
+        }
+        EventHandler::FnMsg(msg, fun) => {
+            let msg = msg.clone();
+            Box::new(move |event| {
+                if let Some(msg) = fun(msg.clone(), event) {
+                    app.dispatch(msg);
+                }
+            }) as Box<dyn FnMut(web_sys::Event)>
+        }
+        EventHandler::InputValue(fun) => {
+            Box::new(move |event: web_sys::Event| {
+                let value = match event.target() {
+                    None => String::new(),
+                    Some(target) => {
+                        if let Some(input) = target.dyn_ref::<web_sys::HtmlInputElement>() {
+                            input.value()
+                        }
+                        else if let Some(input) = target.dyn_ref::<web_sys::HtmlTextAreaElement>() {
+                            input.value()
+                        }
+                        else if let Some(input) = target.dyn_ref::<web_sys::HtmlSelectElement>() {
+                            input.value()
+                        }
+                        else {
+                            String::new()
+                        }
+                    }
+                };
+                if let Some(msg) = fun(value) {
+                    app.dispatch(msg);
+                }
+            }) as Box<dyn FnMut(web_sys::Event)>
+        }
+        EventHandler::InputEvent(fun) => {
+            Box::new(move |event: web_sys::Event| {
+                let event = event.dyn_into::<web_sys::InputEvent>().expect_throw("expected web_sys::InputEvent");
+                if let Some(msg) = fun(event) {
+                    app.dispatch(msg);
+                }
+            }) as Box<dyn FnMut(web_sys::Event)>
+        }
+        EventHandler::Closure(callback) => {
+            let callback = callback.clone();
+            Box::new(move |event| {
+                if let Some(msg) = callback.call(event) {
+                    app.dispatch(msg);
+                }
+            }) as Box<dyn FnMut(web_sys::Event)>
+        }
+        EventHandler::Link => {
+            Box::new(move |event: web_sys::Event| {
+                // leave modified clicks (e.g. open in a new tab) to the browser
+                if let Some(mouse) = event.dyn_ref::<web_sys::MouseEvent>() {
+                    if mouse.button() != 0 || mouse.ctrl_key() || mouse.meta_key()
+                    || mouse.shift_key() || mouse.alt_key() {
+                        return;
+                    }
+                }
+
+                let link = match event.current_target()
+                    .and_then(|target| target.dyn_into::<web_sys::Element>().ok())
+                {
+                    Some(link) => link,
+                    None => return,
+                };
+
+                if link.get_attribute("target").is_some_and(|t| t != "_self") {
+                    return;
+                }
+
+                match link.get_attribute("href") {
+                    Some(href) if is_internal(&href) => {
+                        event.prevent_default();
+                        Navigate::Push(href).process(&app);
+                    }
+                    _ => {}
+                }
+            }) as Box<dyn FnMut(web_sys::Event)>
+        }
+    }
+}
+
+/// The inline style of an html, svg, or MathML element.
+fn inline_style(element: &web_sys::Element) -> Option<web_sys::CssStyleDeclaration> {
+    if let Some(element) = element.dyn_ref::<web_sys::HtmlElement>() {
+        Some(element.style())
+    }
+    else if let Some(element) = element.dyn_ref::<web_sys::SvgElement>() {
+        Some(element.style())
+    }
+    else {
+        element.dyn_ref::<web_sys::MathMlElement>().map(|element| element.style())
+    }
+}
+
+impl Backend for Web {
+    type Node = web_sys::Node;
+    type Element = web_sys::Element;
+    type Text = web_sys::Text;
+    type Listener = Closure<dyn FnMut(web_sys::Event)>;
+
+    fn create_element(&self, name: &str, namespace: Namespace) -> web_sys::Element {
+        match namespace {
+            Namespace::Html => self.document.create_element(name),
+            ns => self.document.create_element_ns(Some(ns.uri()), name),
+        }
+        .expect("failed to create element")
+    }
+
+    fn create_text(&self, text: &str) -> web_sys::Text {
+        self.document.create_text_node(text)
+    }
+
+    fn set_text(&self, node: &web_sys::Text, text: &str) {
+        node.set_data(text);
+    }
+
+    fn set_attribute(&self, node: &web_sys::Element, name: &str, value: &str) {
+        // handle the "value" attribute for non boolean values
+        if name == "value" {
+            if let Some(input) = node.dyn_ref::<web_sys::HtmlInputElement>() {
+                input.set_value(value);
+                return;
+            }
+            else if let Some(input) = node.dyn_ref::<web_sys::HtmlTextAreaElement>() {
+                input.set_value(value);
+                return;
+            }
+            else if let Some(input) = node.dyn_ref::<web_sys::HtmlSelectElement>() {
+                input.set_value(value);
+                return;
+            }
+        }
+
+        // properly handle boolean attributes using special setters
+        attribute_setter!(node, name, value, [
+            "autofocus" => set_autofocus [
+                web_sys::HtmlButtonElement,
+                web_sys::HtmlInputElement,
+                web_sys::HtmlSelectElement,
+                web_sys::HtmlTextAreaElement,
+            ],
+            "checked" => set_checked [
+                web_sys::HtmlInputElement,
+                web_sys::HtmlMenuItemElement,
+            ],
+            "disabled" => set_disabled [
+                web_sys::HtmlButtonElement,
+                web_sys::HtmlFieldSetElement,
+                web_sys::HtmlInputElement,
+                web_sys::HtmlLinkElement,
+                web_sys::HtmlMenuItemElement,
+                web_sys::HtmlOptGroupElement,
+                web_sys::HtmlOptionElement,
+                web_sys::HtmlSelectElement,
+                web_sys::HtmlStyleElement,
+                web_sys::HtmlTextAreaElement,
+            ],
+            "draggable" => set_draggable [
+                web_sys::HtmlElement,
+            ],
+            "hidden" => set_hidden [
+                web_sys::HtmlElement,
+            ],
+            "selected" => set_selected [
+                web_sys::HtmlOptionElement,
+            ],
+            "spellcheck" => set_spellcheck [
+                web_sys::HtmlElement,
+            ],
+        ]);
+    }
+
+    fn set_attribute_ns(&self, node: &web_sys::Element, namespace: &str, name: &str, value: &str) {
+        node.set_attribute_ns(Some(namespace), name, value)
+            .expect("failed to set attribute");
+    }
+
+    fn remove_attribute(&self, node: &web_sys::Element, name: &str) {
+        // properly handle boolean attributes using special setters
+        attribute_unsetter!(node, name, [
+            "autofocus" => set_autofocus [
+                web_sys::HtmlButtonElement,
+                web_sys::HtmlInputElement,
+                web_sys::HtmlSelectElement,
+                web_sys::HtmlTextAreaElement,
+            ],
+            "checked" => set_checked [
+                web_sys::HtmlInputElement,
+                web_sys::HtmlMenuItemElement,
+            ],
+            "disabled" => set_disabled [
+                web_sys::HtmlButtonElement,
+                web_sys::HtmlFieldSetElement,
+                web_sys::HtmlInputElement,
+                web_sys::HtmlLinkElement,
+                web_sys::HtmlMenuItemElement,
+                web_sys::HtmlOptGroupElement,
+                web_sys::HtmlOptionElement,
+                web_sys::HtmlSelectElement,
+                web_sys::HtmlStyleElement,
+                web_sys::HtmlTextAreaElement,
+            ],
+            "draggable" => set_draggable [
+                web_sys::HtmlElement,
+            ],
+            "hidden" => set_hidden [
+                web_sys::HtmlElement,
+            ],
+            "selected" => set_selected [
+                web_sys::HtmlOptionElement,
+            ],
+            "spellcheck" => set_spellcheck [
+                web_sys::HtmlElement,
+            ],
+        ]);
+    }
+
+    fn add_class(&self, element: &web_sys::Element, name: &str) {
+        element.class_list()
+            .add_1(name)
+            .expect("failed to add class");
+    }
+
+    fn remove_class(&self, element: &web_sys::Element, name: &str) {
+        element.class_list()
+            .remove_1(name)
+            .expect("failed to remove class");
+    }
+
+    fn set_style(&self, element: &web_sys::Element, property: &str, value: &str) {
+        match inline_style(element) {
+            Some(style) => {
+                style.set_property(property, value)
+                    .expect("failed to set style property");
+            }
+            None => warn!("style '{}' set on '{}' element, which has no inline style", property, element.node_name()),
+        }
+    }
+
+    fn remove_style(&self, element: &web_sys::Element, property: &str) {
+        if let Some(style) = inline_style(element) {
+            style.remove_property(property)
+                .expect("failed to remove style property");
+        }
+    }
+
+    fn set_property(&self, element: &web_sys::Element, name: &str, value: &PropertyValue) {
+        js_sys::Reflect::set(element, &JsValue::from_str(name), &value.into())
+            .expect("failed to set property");
+    }
+
+    fn remove_property(&self, element: &web_sys::Element, name: &str) {
+        js_sys::Reflect::set(element, &JsValue::from_str(name), &JsValue::NULL)
+            .expect("failed to remove property");
+    }
+
+    fn set_inner_html(&self, element: &web_sys::Element, html: &str) {
+        element.set_inner_html(html);
+    }
+
+    fn remove_children(&self, node: &web_sys::Node) {
+        while let Some(child) = node.first_child() {
+            node.remove_child(&child)
+                .expect("failed to remove child node");
+        }
+    }
+
+    fn insert_before(&self, parent: &web_sys::Node, child: &web_sys::Node, sibling: Option<&web_sys::Node>) {
+        parent
+            .insert_before(child, sibling)
+            .expect("failed to insert child node");
+    }
+
+    fn parent(&self, node: &web_sys::Node) -> Option<web_sys::Node> {
+        node.parent_node()
+    }
+
+    fn remove(&self, node: &web_sys::Node) {
+        if let Some(parent) = node.parent_node() {
+            parent.remove_child(node)
+                .expect("failed to remove child node");
+        }
+    }
+
+    fn as_element<'n>(&self, node: &'n web_sys::Node) -> Option<&'n web_sys::Element> {
+        node.dyn_ref::<web_sys::Element>()
+    }
+
+    fn as_text<'n>(&self, node: &'n web_sys::Node) -> Option<&'n web_sys::Text> {
+        node.dyn_ref::<web_sys::Text>()
+    }
+
+    fn children(&self, node: &web_sys::Node) -> Vec<web_sys::Node> {
+        let mut children = vec![];
+        let mut child = node.first_child();
+        while let Some(node) = child {
+            child = node.next_sibling();
+            children.push(node);
+        }
+        children
+    }
+
+    fn element_name(&self, element: &web_sys::Element) -> String {
+        element.local_name()
+    }
+
+    fn element_namespace(&self, element: &web_sys::Element) -> Namespace {
+        element.namespace_uri()
+            .and_then(|uri| Namespace::from_uri(&uri))
+            .unwrap_or_default()
+    }
+
+    fn text(&self, node: &web_sys::Text) -> String {
+        node.data()
+    }
+
+    fn attribute(&self, element: &web_sys::Element, name: &str) -> Option<String> {
+        element.get_attribute(name)
+    }
+
+    fn attribute_names(&self, element: &web_sys::Element) -> Vec<String> {
+        element.get_attribute_names()
+            .iter()
+            .filter_map(|name| name.as_string())
+            .collect()
+    }
+
+    fn add_listener<Message, Command>(
+        &self,
+        element: &web_sys::Element,
+        trigger: &str,
+        handler: EventHandler<Message>,
+        options: ListenerOptions,
+        app: &Dispatcher<Message, Command>,
+    ) -> Self::Listener
+    where
+        Message: Clone + PartialEq + fmt::Debug + 'static,
+        Command: SideEffect<Message> + 'static,
+    {
+        let mut handler = event_handler(handler, app);
+
+        let closure = if options.prevent_default || options.stop_propagation {
+            Closure::wrap(
+                Box::new(move |event: web_sys::Event| {
+                    if options.prevent_default {
+                        event.prevent_default();
+                    }
+                    if options.stop_propagation {
+                        event.stop_propagation();
+                    }
+                    handler(event);
+                }) as Box<dyn FnMut(web_sys::Event)>
+            )
+        }
+        else {
+            Closure::wrap(handler)
+        };
+
+        let listener_options = web_sys::AddEventListenerOptions::new();
+        listener_options.set_capture(options.capture);
+        listener_options.set_passive(options.passive);
+        listener_options.set_once(options.once);
+
+        (element.as_ref() as &web_sys::EventTarget)
+            .add_event_listener_with_callback_and_add_event_listener_options(
+                trigger,
+                closure.as_ref().unchecked_ref(),
+                &listener_options,
+            )
+            .expect("failed to add event listener");
+
+        closure
+    }
+
+    fn remove_listener(&self, element: &web_sys::Element, trigger: &str, listener: &Self::Listener, options: ListenerOptions) {
+        (element.as_ref() as &web_sys::EventTarget)
+            .remove_event_listener_with_callback_and_bool(trigger, listener.as_ref().unchecked_ref(), options.capture)
+            .expect("failed to remove event listener");
+    }
+
+    fn portal_target(&self, target: &PortalTarget) -> Option<web_sys::Element> {
+        match target {
+            PortalTarget::Selector(selector) => {
+                self.document.query_selector(selector)
+                    .unwrap_or_else(|_| {
+                        warn!("invalid portal target selector: {}", selector);
+                        None
+                    })
+            }
+            PortalTarget::Element(element) => Some(element.clone()),
+        }
+    }
+
+    fn bind_hook<Message, Command>(
+        &self,
+        _lifecycle: Lifecycle,
+        hook: Hook<Message, Command>,
+        element: &web_sys::Element,
+    ) -> BoundHook<Message, Command>
+    where
+        Message: 'static,
+        Command: 'static,
+    {
+        let element = element.clone();
+        Box::new(move || hook.call(&element))
+    }
+
+    fn component_node(&self, node: web_sys::Node) -> Option<web_sys::Node> {
+        Some(node)
+    }
+}
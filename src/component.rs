@@ -8,9 +8,10 @@ use crate::app::Dispatcher;
 use crate::app::Detach;
 use crate::app::Application;
 use crate::app::AppBuilder;
+use crate::app::run_hooks;
 use crate::app::SideEffect;
 use crate::app::side_effect;
-use crate::app::{Update, Render};
+use crate::app::{Update, Subscriptions, Render};
 use crate::vdom::DomIter;
 
 /// A self containted component that can live inside another app.
@@ -29,6 +30,12 @@ pub trait Component<Message> {
 
     /// Get nodes waiting to attach to the parent.
     fn pending(&mut self) -> Vec<web_sys::Node>;
+
+    /// Get the lifecycle hooks of the initial render, waiting for the pending nodes to be attached
+    /// to the parent. The returned function runs them.
+    fn pending_hooks(&mut self) -> Option<Box<dyn FnOnce()>> {
+        None
+    }
 }
 
 /// A builder for constructing a self contained component app that lives inside of another app.
@@ -71,7 +78,7 @@ impl<Message, Command, ParentMessage> ComponentBuilder<Message, Command, ParentM
         ParentCommand: SideEffect<ParentMessage> + 'static,
         Message: fmt::Debug + Clone + PartialEq + 'static,
         Command: SideEffect<Message> + fmt::Debug + Clone + 'static,
-        Model: Update<Message, Command> + Render<DomTree> + 'static,
+        Model: Update<Message, Command> + Subscriptions<Message> + Render<DomTree> + 'static,
         DomTree: DomIter<Message, Command, K> + 'static,
         K: Eq + Hash + 'static,
     {
@@ -81,7 +88,7 @@ impl<Message, Command, ParentMessage> ComponentBuilder<Message, Command, ParentM
         } = self;
 
         let processor = ComponentProcessor::new(parent_app, unmap);
-        let (app, pending) = AppBuilder::default()
+        let (app, pending, dispatcher) = AppBuilder::default()
             .processor(processor)
             .create(model);
 
@@ -89,6 +96,7 @@ impl<Message, Command, ParentMessage> ComponentBuilder<Message, Command, ParentM
             app: app,
             map: map,
             pending: pending,
+            hooks: Some(dispatcher),
         })
     }
 }
@@ -133,6 +141,7 @@ struct ComponentImpl<Message, Command, ParentMessage> {
     app: Rc<RefCell<Box<dyn Application<Message, Command>>>>,
     map: fn(ParentMessage) -> Option<Message>,
     pending: Vec<web_sys::Node>,
+    hooks: Option<Dispatcher<Message, Command>>,
 }
 
 impl<Message, Command, ParentMessage> Component<ParentMessage>
@@ -165,4 +174,10 @@ where
         std::mem::swap(&mut pending, &mut self.pending);
         pending
     }
+
+    fn pending_hooks(&mut self) -> Option<Box<dyn FnOnce()>> {
+        let dispatcher = self.hooks.take()?;
+        let app = Rc::clone(&self.app);
+        Some(Box::new(move || run_hooks(&app, &dispatcher)))
+    }
 }
@@ -3,87 +3,269 @@
 use std::fmt;
 use std::iter;
 use std::collections::HashMap;
+use std::collections::HashSet;
 use std::collections::hash_map::Entry;
 use std::hash::Hash;
 use crate::patch::PatchSet;
 use crate::patch::Patch;
 use crate::vdom::DomItem;
+use crate::vdom::Lifecycle;
+use crate::vdom::LazyDom;
+use crate::vdom::PropertyValue;
 use crate::vdom::WebItem;
+use crate::backend::Backend;
 
 /// Return the series of steps required to move from the given old/existing virtual dom to the
 /// given new virtual dom.
-pub fn diff<'a, Message, Command, O, N, S, K>(
+pub fn diff<'a, Message, Command, O, N, S, K, B>(
     old: O,
     new: N,
     storage: S,
 )
--> PatchSet<'a, Message, Command, K>
+-> PatchSet<'a, Message, Command, K, B>
 where
     Message: 'a + PartialEq + Clone + fmt::Debug,
     O: IntoIterator<Item = DomItem<'a, Message, Command, K>>,
     N: IntoIterator<Item = DomItem<'a, Message, Command, K>>,
-    S: IntoIterator<Item = &'a mut WebItem<Message>>,
+    S: IntoIterator<Item = &'a mut WebItem<Message, B>>,
     K: Eq + Hash,
+    B: Backend,
 {
     DiffImpl::new(old, new, storage).diff()
 }
 
-struct DiffImpl<'a, Message, Command, O, N, S, K>
+struct DiffImpl<'a, Message, Command, O, N, S, K, B>
 where
     Message: 'a + PartialEq + Clone + fmt::Debug,
     O: IntoIterator<Item = DomItem<'a, Message, Command, K>>,
     N: IntoIterator<Item = DomItem<'a, Message, Command, K>>,
-    S: IntoIterator<Item = &'a mut WebItem<Message>>,
+    S: IntoIterator<Item = &'a mut WebItem<Message, B>>,
     K: Eq + Hash,
+    B: Backend,
 {
-    old: O::IntoIter,
-    new: N::IntoIter,
+    old: Expand<'a, O::IntoIter, Message, Command, K>,
+    new: Expand<'a, N::IntoIter, Message, Command, K>,
     sto: S::IntoIter,
-    patch_set: PatchSet<'a, Message, Command, K>,
+    patch_set: PatchSet<'a, Message, Command, K, B>,
     /// list of old keyed DomItems (and their storage)
-    old_def: HashMap<&'a K, (Vec<DomItem<'a, Message, Command, K>>, Vec<&'a mut WebItem<Message>>)>,
+    old_def: HashMap<&'a K, (Vec<DomItem<'a, Message, Command, K>>, Vec<&'a mut WebItem<Message, B>>)>,
     /// list of new keyed DomItems
     new_def: HashMap<&'a K, Vec<DomItem<'a, Message, Command, K>>>,
     /// if true (the default), keyed items will be deferred
     defer_keyed: bool,
+    /// the nodes being compared, used to track the positions of keyed items
+    parents: Parents,
+    /// positions of old keyed items, and their order in the old tree
+    old_pos: HashMap<&'a K, (Position, usize)>,
+    /// positions of new keyed items, in the order of the new tree
+    new_pos: Vec<(&'a K, Position)>,
+    /// keys of deferred new items, in the order of the new tree
+    new_order: Vec<&'a K>,
+    /// keys of deferred old items, in the order of the old tree
+    old_order: Vec<&'a K>,
 }
 
-impl<'a, Message, Command, O, N, S, K>
-DiffImpl<'a, Message, Command, O, N, S, K>
+/// An iterator over [`DomItem`]s that can expand lazy sub trees in place.
+///
+/// [`DomItem`]: ../vdom/enum.DomItem.html
+struct Expand<'a, I, Message, Command, K> {
+    iter: I,
+    /// Iterators over expanded lazy sub trees, these are drained before continuing with `iter`.
+    expanded: Vec<Box<dyn Iterator<Item = DomItem<'a, Message, Command, K>> + 'a>>,
+}
+
+impl<'a, I, Message, Command, K> Expand<'a, I, Message, Command, K> {
+    fn new(iter: I) -> Self {
+        Expand {
+            iter,
+            expanded: vec![],
+        }
+    }
+
+    /// Render the given lazy sub tree, its items will be returned next.
+    fn expand(&mut self, dom: LazyDom<'a, Message, Command, K>) {
+        self.expanded.push(dom.dom_iter());
+    }
+}
+
+impl<'a, I, Message, Command, K> Iterator for Expand<'a, I, Message, Command, K>
+where
+    I: Iterator<Item = DomItem<'a, Message, Command, K>>,
+{
+    type Item = DomItem<'a, Message, Command, K>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while let Some(iter) = self.expanded.last_mut() {
+            match iter.next() {
+                item @ Some(_) => return item,
+                None => { self.expanded.pop(); }
+            }
+        }
+        self.iter.next()
+    }
+}
+
+/// The position of a keyed item: the id of its parent and the number of siblings copied from the
+/// old tree to the new tree before it.
+type Position = (usize, usize);
+
+/// Tracks the nodes being compared so that the positions of keyed items can be recorded.
+#[derive(Default)]
+struct Parents {
+    /// The id of each node we are in and the number of its children copied so far.
+    stack: Vec<(usize, usize)>,
+    /// The number of nodes seen so far, used to generate ids.
+    count: usize,
+}
+
+impl Parents {
+    /// Start tracking at the root of the tree.
+    fn root() -> Self {
+        Parents {
+            stack: vec![(0, 0)],
+            count: 0,
+        }
+    }
+
+    /// A node was copied from the old tree to the new tree, we are now comparing its children.
+    fn enter(&mut self) {
+        self.skip();
+        self.count += 1;
+        self.stack.push((self.count, 0));
+    }
+
+    /// A node and all of its children were copied from the old tree to the new tree.
+    fn skip(&mut self) {
+        if let Some((_, copies)) = self.stack.last_mut() {
+            *copies += 1;
+        }
+    }
+
+    /// We are finished comparing the children of a node.
+    fn leave(&mut self) {
+        self.stack.pop();
+    }
+
+    /// The position of the next item.
+    fn position(&self) -> Option<Position> {
+        self.stack.last().cloned()
+    }
+}
+
+impl<'a, Message, Command, O, N, S, K, B>
+DiffImpl<'a, Message, Command, O, N, S, K, B>
 where
     Message: 'a + PartialEq + Clone + fmt::Debug,
     O: IntoIterator<Item = DomItem<'a, Message, Command, K>>,
     N: IntoIterator<Item = DomItem<'a, Message, Command, K>>,
-    S: IntoIterator<Item = &'a mut WebItem<Message>>,
+    S: IntoIterator<Item = &'a mut WebItem<Message, B>>,
     K: Eq + Hash,
+    B: Backend,
 {
     fn new(old: O, new: N, sto: S) -> Self {
         DiffImpl {
-            old: old.into_iter(),
-            new: new.into_iter(),
+            old: Expand::new(old.into_iter()),
+            new: Expand::new(new.into_iter()),
             sto: sto.into_iter(),
             patch_set: PatchSet::new(),
             old_def: HashMap::new(),
             new_def: HashMap::new(),
             defer_keyed: true,
+            parents: Parents::root(),
+            old_pos: HashMap::new(),
+            new_pos: vec![],
+            new_order: vec![],
+            old_order: vec![],
         }
     }
 
     fn no_defer(old: O, new: N, sto: S) -> Self {
         DiffImpl {
-            old: old.into_iter(),
-            new: new.into_iter(),
+            old: Expand::new(old.into_iter()),
+            new: Expand::new(new.into_iter()),
             sto: sto.into_iter(),
             patch_set: PatchSet::new(),
             old_def: HashMap::new(),
             new_def: HashMap::new(),
             defer_keyed: false,
+            parents: Parents::root(),
+            old_pos: HashMap::new(),
+            new_pos: vec![],
+            new_order: vec![],
+            old_order: vec![],
         }
     }
 
     /// Return the series of steps required to move from the given old/existing virtual dom to the
     /// given new virtual dom.
-    pub fn diff(mut self) -> PatchSet<'a, Message, Command, K> {
+    pub fn diff(mut self) -> PatchSet<'a, Message, Command, K, B> {
+        self.compare_all();
+
+        // find the keyed nodes that don't need to move
+        let mut stationary = self.stationary_keys(&self.old_pos, &self.new_pos);
+
+        // now look for differences between keyed nodes, in the order they appear in the new tree so
+        // that keyed nodes are compared before the keyed nodes nested in them
+        for key in std::mem::take(&mut self.new_order) {
+            let new_items = match self.new_def.remove(key) {
+                Some(new_items) => new_items,
+                None => continue,
+            };
+
+            let mut ps = match self.old_def.remove(key) {
+                Some((old_items, storage)) => {
+                    // there is something to diff, store it
+                    let mut sub = DiffImpl::no_defer(old_items, new_items, storage);
+                    sub.compare_all();
+
+                    // find the keyed nodes nested in this one that don't need to move
+                    stationary.extend(self.stationary_keys(&sub.old_pos, &sub.new_pos));
+
+                    let mut ps = sub.patch_set;
+                    if stationary.contains(&key) {
+                        if let Some(patch) = ps.patches.first_mut() {
+                            *patch = stay(std::mem::replace(patch, Patch::Up));
+                        }
+                    }
+                    ps
+                }
+                None => {
+                    // node is being added
+                    DiffImpl::no_defer(iter::empty(), new_items, iter::empty()).diff()
+                }
+            };
+            ps.root_key(key);
+            self.patch_set.extend(ps);
+        }
+
+        // any nodes left in old are being removed, their removals go before the rest of the top
+        // level patch set, so they are unmounted before the dom around them changes. All of them
+        // are unmounted before any of them are removed, keyed nodes may be nested in each other.
+        let mut unmount = vec![];
+        let mut remove = vec![];
+        for key in std::mem::take(&mut self.old_order) {
+            let (old_items, storage) = match self.old_def.remove(key) {
+                Some(old) => old,
+                None => continue,
+            };
+
+            let ps = DiffImpl::no_defer(old_items, iter::empty(), storage).diff();
+            for patch in ps.patches {
+                match patch {
+                    Patch::RemoveElement(_) | Patch::RemoveText(_) => remove.push(patch),
+                    _ => unmount.push(patch),
+                }
+            }
+        }
+        unmount.extend(remove);
+        unmount.append(&mut self.patch_set.patches);
+        self.patch_set.patches = unmount;
+
+        self.patch_set
+    }
+
+    /// Compare the old and new trees, deferring keyed items.
+    fn compare_all(&mut self) {
         let mut o_item = self.old.next();
         let mut n_item = self.new.next();
 
@@ -105,32 +287,59 @@ where
                 }
             }
         }
+    }
 
-        // now look for differences between keyed nodes
-        for (key, (old_items, storage)) in self.old_def.drain() {
-            if let Some(new_items) = self.new_def.remove(&key) {
-                // there is something to diff, store it
-                let mut ps = DiffImpl::no_defer(old_items, new_items, storage).diff();
-                ps.root_key(key);
-                self.patch_set.extend(ps);
-            }
-            else {
-                // node is being removed, append the removal to the top level patch set
-                let ps = DiffImpl::no_defer(old_items, iter::empty(), storage).diff();
-                self.patch_set.extend(ps);
+    /// Find the keyed items that can stay where they are in the dom.
+    ///
+    /// Keyed items that remain in the same parent and between the same copied siblings can stay
+    /// in place as long as their order relative to each other is unchanged. The longest increasing
+    /// subsequence of their old order, taken in their new order, is the largest set of these items
+    /// that don't need to be moved.
+    fn stationary_keys(
+        &self,
+        old_pos: &HashMap<&'a K, (Position, usize)>,
+        new_pos: &[(&'a K, Position)],
+    ) -> HashSet<&'a K>
+    {
+        let mut groups: HashMap<Position, Vec<(usize, &'a K)>> = HashMap::new();
+        for (key, position) in new_pos.iter() {
+            match old_pos.get(key) {
+                Some((old_position, index))
+                if old_position == position && self.same_root(key)
+                => {
+                    groups.entry(*position)
+                        .or_default()
+                        .push((*index, *key));
+                }
+                _ => {}
             }
         }
 
-        // any nodes left in new need to be added
-        for (key, new_items) in self.new_def.drain() {
-            let mut ps = DiffImpl::no_defer(iter::empty(), new_items, iter::empty()).diff();
-            ps.root_key(key);
-            self.patch_set.extend(ps);
-        }
-
-        self.patch_set
+        groups.values()
+            .flat_map(|group| {
+                let order: Vec<usize> = group.iter().map(|(index, _)| *index).collect();
+                longest_increasing_subsequence(&order)
+                    .into_iter()
+                    .map(move |i| group[i].1)
+            })
+            .collect()
     }
 
+    /// Returns true if the old and new items with the given key have a compatible root node.
+    fn same_root(&self, key: &'a K) -> bool {
+        let old = self.old_def.get(key).and_then(|(items, _)| items.first());
+        let new = self.new_def.get(key).and_then(|items| items.first());
+
+        match (old, new) {
+            (
+                Some(DomItem::Element { name: o_element, namespace: o_namespace, .. }),
+                Some(DomItem::Element { name: n_element, namespace: n_namespace, .. }),
+            ) => o_element == n_element && o_namespace == n_namespace,
+            (Some(DomItem::Component { .. }), Some(DomItem::Component { .. })) => true,
+            (Some(DomItem::Fragment { .. }), Some(DomItem::Fragment { .. })) => true,
+            _ => false,
+        }
+    }
 
     /// Compare two items.
     fn compare(
@@ -143,26 +352,63 @@ where
         let sto = &mut self.sto;
         let old = &mut self.old;
         let new = &mut self.new;
+        let parents = &mut self.parents;
+        let defer_keyed = self.defer_keyed;
 
         match (o_item, n_item) {
             (
-                DomItem::Element { name: o_element, key: Some(o_key) },
-                DomItem::Element { name: n_element, key: Some(n_key) },
-            ) if o_element == n_element && o_key == n_key => { // compare elements and keys
+                DomItem::Element { name: o_element, key: Some(o_key), namespace: o_namespace },
+                DomItem::Element { name: n_element, key: Some(n_key), namespace: n_namespace },
+            ) if !defer_keyed && o_element == n_element && o_namespace == n_namespace && o_key == n_key => { // compare elements and keys
                 let web_item = sto.next().expect("dom storage to match dom iter");
 
                 // move the node
                 patch_set.push(Patch::MoveElement(web_item));
+                parents.enter();
                 (old.next(), new.next())
             }
             (
-                DomItem::Element { name: o_element, key: None },
-                DomItem::Element { name: n_element, key: None },
-            ) if o_element == n_element => { // compare elements
+                DomItem::Element { name: o_element, key: None, namespace: o_namespace },
+                DomItem::Element { name: n_element, key: None, namespace: n_namespace },
+            ) if o_element == n_element && o_namespace == n_namespace => { // compare elements
                 let web_item = sto.next().expect("dom storage to match dom iter");
 
                 // copy the node
                 patch_set.push(Patch::CopyElement(web_item));
+                parents.enter();
+                (old.next(), new.next())
+            }
+            (
+                DomItem::Fragment { key: Some(o_key) },
+                DomItem::Fragment { key: Some(n_key) },
+            ) if !defer_keyed && o_key == n_key => { // compare keyed fragments
+                let web_item = sto.next().expect("dom storage to match dom iter");
+
+                // move the fragment
+                patch_set.push(Patch::MoveFragment(web_item));
+                parents.enter();
+                (old.next(), new.next())
+            }
+            (
+                DomItem::Fragment { key: None },
+                DomItem::Fragment { key: None },
+            ) => { // compare fragments
+                let web_item = sto.next().expect("dom storage to match dom iter");
+
+                // copy the fragment, its children are compared as children of its parent
+                patch_set.push(Patch::CopyFragment(web_item));
+                parents.enter();
+                (old.next(), new.next())
+            }
+            (
+                DomItem::Portal { target: o_target },
+                DomItem::Portal { target: n_target },
+            ) if o_target == n_target => { // compare portals
+                let web_item = sto.next().expect("dom storage to match dom iter");
+
+                // copy the portal, its children are compared as children of the target
+                patch_set.push(Patch::CopyPortal(web_item));
+                parents.enter();
                 (old.next(), new.next())
             }
             (
@@ -181,6 +427,7 @@ where
                     patch_set.push(Patch::ReplaceText { take: web_item, text: n_text });
                 }
 
+                parents.enter();
                 (old.next(), new.next())
             }
             (
@@ -197,7 +444,7 @@ where
                 DomItem::Component { msg: o_msg, create: o_create, key: Some(o_key) },
                 DomItem::Component { msg: n_msg, create: n_create, key: Some(n_key) }
             )
-            if o_create == n_create && o_key == n_key
+            if !defer_keyed && o_create == n_create && o_key == n_key
             => { // compare keyed components
                 let web_item = sto.next().expect("dom storage to match dom iter");
 
@@ -210,6 +457,7 @@ where
                     patch_set.push(Patch::MupdateComponent { take: web_item, msg: n_msg });
                 }
 
+                parents.enter();
                 (old.next(), new.next())
             }
             (
@@ -227,6 +475,7 @@ where
                     patch_set.push(Patch::UpdateComponent { take: web_item, msg: n_msg });
                 }
 
+                parents.enter();
                 (old.next(), new.next())
             }
             (
@@ -262,17 +511,40 @@ where
                 (old.next(), new.next())
             }
             (
-                DomItem::Event { trigger: o_trigger, handler: o_handler },
-                DomItem::Event { trigger: n_trigger, handler: n_handler }
+                DomItem::Ref(o_name),
+                DomItem::Ref(n_name)
+            ) => { // compare refs
+                // the element is already recorded under the old name
+                if o_name != n_name {
+                    patch_set.push(Patch::RemoveRef(o_name));
+                    patch_set.push(Patch::SetRef(n_name));
+                }
+
+                (old.next(), new.next())
+            }
+            (
+                DomItem::Hook { .. },
+                DomItem::Hook { lifecycle: n_lifecycle, hook: n_hook }
+            ) => { // compare hooks
+                // the element is kept, run its update hooks
+                if n_lifecycle == Lifecycle::Update {
+                    patch_set.push(Patch::RunHook { lifecycle: n_lifecycle, hook: n_hook });
+                }
+
+                (old.next(), new.next())
+            }
+            (
+                DomItem::Event { trigger: o_trigger, handler: o_handler, options: o_options },
+                DomItem::Event { trigger: n_trigger, handler: n_handler, options: n_options }
             ) => { // compare event listeners
                 let web_item = sto.next().expect("dom storage to match dom iter");
 
-                if o_trigger != n_trigger || o_handler != n_handler {
+                if o_trigger != n_trigger || o_handler != n_handler || o_options != n_options {
                     // remove old listener
-                    patch_set.push(Patch::RemoveListener { trigger: o_trigger, take: web_item });
+                    patch_set.push(Patch::RemoveListener { trigger: o_trigger, take: web_item, options: o_options });
 
                     // add new listener
-                    patch_set.push(Patch::AddListener { trigger: n_trigger, handler: n_handler.into() });
+                    patch_set.push(Patch::AddListener { trigger: n_trigger, handler: n_handler.into(), options: n_options });
                 }
                 else {
                     // just copy the existing listener
@@ -284,8 +556,65 @@ where
             (DomItem::Up, DomItem::Up) => { // end of two items
                 let _ = sto.next().expect("dom storage to match dom iter");
                 patch_set.push(Patch::Up);
+                parents.leave();
+                (old.next(), new.next())
+            }
+            (
+                DomItem::Lazy { key: o_key, dom: o_dom },
+                DomItem::Lazy { key: n_key, dom: n_dom }
+            ) if o_key == n_key && o_dom == n_dom => { // unchanged lazy sub tree
+                // copy the entire sub tree without rendering it
+                Self::copy_sub_tree(sto, patch_set);
+                parents.skip();
                 (old.next(), new.next())
             }
+            (DomItem::Lazy { dom, .. }, n) => { // render the old sub tree and compare its items
+                old.expand(dom);
+                (old.next(), Some(n))
+            }
+            (o, DomItem::Lazy { dom, .. }) => { // render the new sub tree and compare its items
+                new.expand(dom);
+                (Some(o), new.next())
+            }
+            (o @ DomItem::Class(_), n) | (o, n @ DomItem::Class(_)) => { // compare classes
+                let (o_classes, o_next) = take_classes(o, old);
+                let (n_classes, n_next) = take_classes(n, new);
+
+                for class in o_classes.iter().filter(|c| !n_classes.contains(c)) {
+                    patch_set.push(Patch::RemoveClass(class));
+                }
+                for class in n_classes.iter().filter(|c| !o_classes.contains(c)) {
+                    patch_set.push(Patch::AddClass(class));
+                }
+
+                (o_next, n_next)
+            }
+            (o @ DomItem::Style { .. }, n) | (o, n @ DomItem::Style { .. }) => { // compare styles
+                let (o_styles, o_next) = take_styles(o, old);
+                let (n_styles, n_next) = take_styles(n, new);
+
+                for (property, _) in o_styles.iter().filter(|(p, _)| !n_styles.iter().any(|(n, _)| n == p)) {
+                    patch_set.push(Patch::RemoveStyle(property));
+                }
+                for (property, value) in n_styles.iter().filter(|style| !o_styles.contains(style)) {
+                    patch_set.push(Patch::SetStyle { property, value });
+                }
+
+                (o_next, n_next)
+            }
+            (o @ DomItem::Property { .. }, n) | (o, n @ DomItem::Property { .. }) => { // compare properties
+                let (o_props, o_next) = take_properties(o, old);
+                let (n_props, n_next) = take_properties(n, new);
+
+                for (name, _) in o_props.iter().filter(|(p, _)| !n_props.iter().any(|(n, _)| n == p)) {
+                    patch_set.push(Patch::RemoveProperty(name));
+                }
+                for (name, value) in n_props.iter().filter(|prop| !o_props.contains(prop)) {
+                    patch_set.push(Patch::SetProperty { name, value });
+                }
+
+                (o_next, n_next)
+            }
             (o, n) => { // no match
                 // remove the old item
                 let o_next = self.remove(o);
@@ -309,44 +638,76 @@ where
         let old = &mut self.old;
 
         match item {
-           DomItem::Element { key: Some(_), .. }
+           DomItem::Element { key: Some(key), .. }
             if self.defer_keyed
             => {
-                self.defer_remove_sub_tree(item, None)
+                self.defer_remove(key, item)
             }
             DomItem::Element { .. } => {
                 let web_item = sto.next().expect("dom storage to match dom iter");
-                patch_set.push(Patch::RemoveElement(web_item));
-                self.remove_sub_tree()
+                // unmount the sub tree while it is still attached, then remove it
+                let element = web_item.as_element().cloned();
+                let next = self.remove_sub_tree(element);
+                self.patch_set.push(Patch::RemoveElement(web_item));
+                next
             }
             DomItem::Text(_) => {
                 let web_item = sto.next().expect("dom storage to match dom iter");
                 patch_set.push(Patch::RemoveText(web_item));
-                self.remove_sub_tree()
+                self.remove_sub_tree(None)
             }
-            DomItem::Component { key: Some(_), .. }
+            DomItem::Component { key: Some(key), .. }
             if self.defer_keyed
             => {
-                self.defer_remove_sub_tree(item, None)
+                self.defer_remove(key, item)
             }
             DomItem::Component { .. } => {
                 let web_item = sto.next().expect("dom storage to match dom iter");
                 patch_set.push(Patch::RemoveComponent(web_item));
-                self.remove_sub_tree()
+                self.remove_sub_tree(None)
+            }
+            DomItem::Fragment { key: Some(key) }
+            if self.defer_keyed
+            => {
+                self.defer_remove(key, item)
+            }
+            DomItem::Fragment { .. } | DomItem::Portal { .. } => {
+                let _ = sto.next().expect("dom storage to match dom iter");
+                self.remove_nodes()
             }
             DomItem::UnsafeInnerHtml(_) => {
                 patch_set.push(Patch::UnsetInnerHtml);
                 old.next()
             }
-            DomItem::Event { trigger, .. } => {
+            DomItem::Event { trigger, options, .. } => {
                 let web_item = sto.next().expect("dom storage to match dom iter");
-                patch_set.push(Patch::RemoveListener { trigger, take: web_item });
+                patch_set.push(Patch::RemoveListener { trigger, take: web_item, options });
                 old.next()
             }
             DomItem::Attr { name, .. } => {
                 patch_set.push(Patch::RemoveAttribute(name));
                 old.next()
             }
+            DomItem::Class(name) => {
+                patch_set.push(Patch::RemoveClass(name));
+                old.next()
+            }
+            DomItem::Style { property, .. } => {
+                patch_set.push(Patch::RemoveStyle(property));
+                old.next()
+            }
+            DomItem::Property { name, .. } => {
+                patch_set.push(Patch::RemoveProperty(name));
+                old.next()
+            }
+            DomItem::Ref(name) => {
+                patch_set.push(Patch::RemoveRef(name));
+                old.next()
+            }
+            // the element is kept, so the hook doesn't run
+            DomItem::Hook { .. } => {
+                old.next()
+            }
             // this should only be possible when comparing two nodes, and in that case we expect this
             // to effectively be a noop while we add items to the node we are comparing to. When
             // removing entire elements, remove_sub_tree() is called above and this condition is never
@@ -354,8 +715,14 @@ where
             DomItem::Up => {
                 Some(item)
             }
-            // ignore
-            DomItem::Key(_) => {
+            // a keyed item nested in the one being compared, its position is needed to tell if
+            // it can stay in place
+            DomItem::Key(key) => {
+                self.record_old_position(key);
+                self.old.next()
+            }
+            DomItem::Lazy { dom, .. } => {
+                old.expand(dom);
                 old.next()
             }
         }
@@ -371,31 +738,45 @@ where
         let new = &mut self.new;
 
         match item {
-            DomItem::Element { key: Some(_), .. }
+            DomItem::Element { key: Some(key), .. }
             if self.defer_keyed
             => {
-                self.defer_add_sub_tree(item, None)
+                self.defer_add(key, item)
             }
-            DomItem::Element { name: element, .. } => {
-                patch_set.push(Patch::CreateElement { element });
+            DomItem::Element { name: element, namespace, .. } => {
+                patch_set.push(Patch::CreateElement { element, namespace });
                 self.add_sub_tree()
             }
             DomItem::Text(text) => {
                 patch_set.push(Patch::CreateText { text });
                 self.add_sub_tree()
             }
-            DomItem::Component { key: Some(_), .. }
+            DomItem::Component { key: Some(key), .. }
             if self.defer_keyed
             => {
-                self.defer_add_sub_tree(item, None)
+                self.defer_add(key, item)
             }
             DomItem::Component { msg, create, .. } => {
                 patch_set.push(Patch::CreateComponent { msg, create });
                 self.add_sub_tree()
             }
+            DomItem::Fragment { key: Some(key) }
+            if self.defer_keyed
+            => {
+                self.defer_add(key, item)
+            }
+            DomItem::Fragment { .. } => {
+                patch_set.push(Patch::CreateFragment);
+                self.add_sub_tree()
+            }
+            DomItem::Portal { target } => {
+                patch_set.push(Patch::CreatePortal(target));
+                self.add_sub_tree()
+            }
             DomItem::Key(k) => {
                 patch_set.push(Patch::ReferenceKey(k));
-                new.next()
+                self.record_new_position(k);
+                self.new.next()
             }
             DomItem::UnsafeInnerHtml(html) => {
                 patch_set.push(Patch::SetInnerHtml(html));
@@ -405,8 +786,36 @@ where
                 patch_set.push(Patch::SetAttribute { name, value });
                 new.next()
             }
-            DomItem::Event { trigger, handler } => {
-                patch_set.push(Patch::AddListener { trigger, handler: handler.into() });
+            DomItem::Class(name) => {
+                patch_set.push(Patch::AddClass(name));
+                new.next()
+            }
+            DomItem::Style { property, value } => {
+                patch_set.push(Patch::SetStyle { property, value });
+                new.next()
+            }
+            DomItem::Property { name, value } => {
+                patch_set.push(Patch::SetProperty { name, value });
+                new.next()
+            }
+            DomItem::Ref(name) => {
+                patch_set.push(Patch::SetRef(name));
+                new.next()
+            }
+            // the element is kept, only update hooks run
+            DomItem::Hook { lifecycle: Lifecycle::Update, hook } => {
+                patch_set.push(Patch::RunHook { lifecycle: Lifecycle::Update, hook });
+                new.next()
+            }
+            DomItem::Hook { .. } => {
+                new.next()
+            }
+            DomItem::Event { trigger, handler, options } => {
+                patch_set.push(Patch::AddListener { trigger, handler: handler.into(), options });
+                new.next()
+            }
+            DomItem::Lazy { dom, .. } => {
+                new.expand(dom);
                 new.next()
             }
             // this should only be possible when comparing two nodes, and in that case we expect this
@@ -418,6 +827,95 @@ where
         }
     }
 
+    /// Copy an unchanged sub tree with a single root node from the old tree to the new tree.
+    ///
+    /// The sub tree is copied using only the items in storage, the old and new trees are not
+    /// iterated.
+    fn copy_sub_tree(sto: &mut S::IntoIter, patch_set: &mut PatchSet<'a, Message, Command, K, B>) {
+        let mut depth = 0;
+        loop {
+            let web_item = sto.next().expect("dom storage to match dom iter");
+            match web_item {
+                WebItem::Element(_) => {
+                    patch_set.push(Patch::CopyElement(web_item));
+                    depth += 1;
+                }
+                WebItem::Text(_) => {
+                    patch_set.push(Patch::CopyText(web_item));
+                    depth += 1;
+                }
+                WebItem::Component(_) => {
+                    patch_set.push(Patch::CopyComponent(web_item));
+                    depth += 1;
+                }
+                WebItem::Fragment => {
+                    patch_set.push(Patch::CopyFragment(web_item));
+                    depth += 1;
+                }
+                WebItem::Portal(_) => {
+                    patch_set.push(Patch::CopyPortal(web_item));
+                    depth += 1;
+                }
+                WebItem::Closure(_) => {
+                    patch_set.push(Patch::CopyListener(web_item));
+                }
+                WebItem::Up => {
+                    patch_set.push(Patch::Up);
+                    depth -= 1;
+                    if depth == 0 {
+                        break;
+                    }
+                }
+                WebItem::Taken => {
+                    panic!("unexpected WebItem, expected node from storage");
+                }
+            }
+        }
+    }
+
+    /// Record the position of this keyed item in the old tree and defer processing of it.
+    fn defer_remove(
+        &mut self,
+        key: &'a K,
+        item: DomItem<'a, Message, Command, K>,
+    ) -> Option<DomItem<'a, Message, Command, K>>
+    {
+        // only the first item with a given key is deferred
+        if !self.old_def.contains_key(key) {
+            self.record_old_position(key);
+        }
+        self.defer_remove_sub_tree(item, None)
+    }
+
+    /// Record the position of this keyed item in the new tree and defer processing of it.
+    fn defer_add(
+        &mut self,
+        key: &'a K,
+        item: DomItem<'a, Message, Command, K>,
+    ) -> Option<DomItem<'a, Message, Command, K>>
+    {
+        // only the first item with a given key is deferred
+        if !self.new_def.contains_key(key) {
+            self.record_new_position(key);
+        }
+        self.defer_add_sub_tree(item, None)
+    }
+
+    /// Record the position of the keyed item with the given key in the old tree.
+    fn record_old_position(&mut self, key: &'a K) {
+        if let Some(position) = self.parents.position() {
+            let index = self.old_pos.len();
+            self.old_pos.insert(key, (position, index));
+        }
+    }
+
+    /// Record the position of the keyed item with the given key in the new tree.
+    fn record_new_position(&mut self, key: &'a K) {
+        if let Some(position) = self.parents.position() {
+            self.new_pos.push((key, position));
+        }
+    }
+
     /// Add this entire element tree.
     ///
     /// Expected to be called where `new.next()` just returned a node that may have children. This will
@@ -434,8 +932,8 @@ where
                 => {
                     self.defer_add_sub_tree(item, None)
                 }
-                Some(DomItem::Element { name: element, .. }) => {
-                    self.patch_set.push(Patch::CreateElement { element });
+                Some(DomItem::Element { name: element, namespace, .. }) => {
+                    self.patch_set.push(Patch::CreateElement { element, namespace });
                     depth += 1;
                     self.new.next()
                 }
@@ -453,6 +951,20 @@ where
                     depth += 1;
                     self.new.next()
                 }
+                Some(item @ DomItem::Fragment { key: Some(_) })
+                if self.defer_keyed => {
+                    self.defer_add_sub_tree(item, None)
+                }
+                Some(DomItem::Fragment { .. }) => {
+                    self.patch_set.push(Patch::CreateFragment);
+                    depth += 1;
+                    self.new.next()
+                }
+                Some(DomItem::Portal { target }) => {
+                    self.patch_set.push(Patch::CreatePortal(target));
+                    depth += 1;
+                    self.new.next()
+                }
                 Some(DomItem::Key(k)) => {
                     self.patch_set.push(Patch::ReferenceKey(k));
                     self.new.next()
@@ -461,14 +973,41 @@ where
                     self.patch_set.push(Patch::SetInnerHtml(html));
                     self.new.next()
                 }
-                Some(DomItem::Event { trigger, handler }) => {
-                    self.patch_set.push(Patch::AddListener { trigger, handler: handler.into() });
+                Some(DomItem::Event { trigger, handler, options }) => {
+                    self.patch_set.push(Patch::AddListener { trigger, handler: handler.into(), options });
                     self.new.next()
                 }
                 Some(DomItem::Attr { name, value }) => {
                     self.patch_set.push(Patch::SetAttribute { name, value });
                     self.new.next()
                 }
+                Some(DomItem::Class(name)) => {
+                    self.patch_set.push(Patch::AddClass(name));
+                    self.new.next()
+                }
+                Some(DomItem::Style { property, value }) => {
+                    self.patch_set.push(Patch::SetStyle { property, value });
+                    self.new.next()
+                }
+                Some(DomItem::Property { name, value }) => {
+                    self.patch_set.push(Patch::SetProperty { name, value });
+                    self.new.next()
+                }
+                Some(DomItem::Ref(name)) => {
+                    self.patch_set.push(Patch::SetRef(name));
+                    self.new.next()
+                }
+                Some(DomItem::Hook { lifecycle: Lifecycle::Mount, hook }) => {
+                    self.patch_set.push(Patch::RunHook { lifecycle: Lifecycle::Mount, hook });
+                    self.new.next()
+                }
+                Some(DomItem::Hook { .. }) => {
+                    self.new.next()
+                }
+                Some(DomItem::Lazy { dom, .. }) => {
+                    self.new.expand(dom);
+                    self.new.next()
+                }
                 Some(DomItem::Up) if depth > 0 => {
                     self.patch_set.push(Patch::Up);
                     depth -= 1;
@@ -488,13 +1027,16 @@ where
     /// Skip the items in this sub tree.
     ///
     /// Expected to be called where `old.next()` just returned a node that may have children. This will
-    /// handle removing nodes from storage, up to the matching `DomItem::Up` entry.
-    fn remove_sub_tree(&mut self)
+    /// handle removing nodes from storage, up to the matching `DomItem::Up` entry. The given
+    /// element is the removed node, if it is an element, for running its unmount hooks.
+    fn remove_sub_tree(&mut self, element: Option<B::Element>)
     -> Option<DomItem<'a, Message, Command, K>>
     {
         // skip the rest of the items in the old tree for this element, this
         // will cause attributes and such to be created on the new element
         let mut depth = 0;
+        // the removed elements we are in, for running unmount hooks
+        let mut elements = vec![element];
         let mut item = self.old.next();
         loop {
             item = match item {
@@ -506,13 +1048,15 @@ where
                 }
                 // child element: remove from storage, track sub-tree depth
                 Some(DomItem::Element { .. }) => {
-                    let _ = self.sto.next().expect("dom storage to match dom iter");
+                    let web_item = self.sto.next().expect("dom storage to match dom iter");
+                    elements.push(web_item.as_element().cloned());
                     depth += 1;
                     self.old.next()
                 }
                 // child text: remove from storage, track sub-tree depth
                 Some(DomItem::Text(_)) => {
                     let _ = self.sto.next().expect("dom storage to match dom iter");
+                    elements.push(None);
                     depth += 1;
                     self.old.next()
                 }
@@ -526,9 +1070,28 @@ where
                 Some(DomItem::Component { .. }) => {
                     let web_item = self.sto.next().expect("dom storage to match dom iter");
                     self.patch_set.push(Patch::RemoveComponent(web_item));
+                    elements.push(None);
+                    depth += 1;
+                    self.old.next()
+                }
+                // keyed fragment: defer
+                Some(item @ DomItem::Fragment { key: Some(_) })
+                if self.defer_keyed
+                => {
+                    self.defer_remove_sub_tree(item, None)
+                }
+                // fragment: remove from storage, track sub-tree depth
+                Some(DomItem::Fragment { .. }) => {
+                    let _ = self.sto.next().expect("dom storage to match dom iter");
+                    elements.push(None);
                     depth += 1;
                     self.old.next()
                 }
+                // portal: its nodes are not in this sub tree, remove them from the dom
+                Some(DomItem::Portal { .. }) => {
+                    let _ = self.sto.next().expect("dom storage to match dom iter");
+                    self.remove_nodes()
+                }
                 // key reference: ignore
                 Some(DomItem::Key(_)) => {
                     self.old.next()
@@ -542,13 +1105,38 @@ where
                 Some(DomItem::UnsafeInnerHtml(_)) => {
                     self.old.next()
                 }
-                // attribute: ignore
-                Some(DomItem::Attr { .. }) => {
+                // attribute, class, style, or property: ignore
+                Some(DomItem::Attr { .. }) | Some(DomItem::Class(_)) | Some(DomItem::Style { .. })
+                | Some(DomItem::Property { .. }) => {
+                    self.old.next()
+                }
+                // ref: forget the removed element
+                Some(DomItem::Ref(name)) => {
+                    if let Some(Some(element)) = elements.last() {
+                        self.patch_set.push(Patch::UnmountRef { name, element: element.clone() });
+                    }
+                    self.old.next()
+                }
+                // unmount hook: run it with the removed element
+                Some(DomItem::Hook { lifecycle: Lifecycle::Unmount, hook }) => {
+                    if let Some(Some(element)) = elements.last() {
+                        self.patch_set.push(Patch::RunUnmountHook { hook, element: element.clone() });
+                    }
+                    self.old.next()
+                }
+                // other hooks: ignore
+                Some(DomItem::Hook { .. }) => {
+                    self.old.next()
+                }
+                // lazy sub tree: render it and remove its items
+                Some(DomItem::Lazy { dom, .. }) => {
+                    self.old.expand(dom);
                     self.old.next()
                 }
                 // end of child: track sub-tree depth
                 Some(DomItem::Up) if depth > 0 => {
                     let _ = self.sto.next().expect("dom storage to match dom iter");
+                    elements.pop();
                     depth -= 1;
                     self.old.next()
                 }
@@ -564,6 +1152,32 @@ where
         }
     }
 
+    /// Remove the nodes in this fragment or portal.
+    ///
+    /// Expected to be called where `old.next()` just returned a fragment or portal. The nodes in a
+    /// fragment are children of the fragment's parent and the nodes in a portal are children of
+    /// its target, so each of them is removed, up to the matching `DomItem::Up` entry.
+    fn remove_nodes(&mut self)
+    -> Option<DomItem<'a, Message, Command, K>>
+    {
+        let mut item = self.old.next();
+        loop {
+            item = match item {
+                // end of fragment: stop processing
+                Some(DomItem::Up) => {
+                    let _ = self.sto.next().expect("dom storage to match dom iter");
+                    return self.old.next();
+                }
+                Some(item) => {
+                    self.remove(item)
+                }
+                o @ None => {
+                    return o;
+                }
+            }
+        }
+    }
+
     /// Track the items in this sub tree.
     ///
     /// Expected to be called where `old.next()` just returned a node that may have children. This will
@@ -571,7 +1185,7 @@ where
     fn defer_remove_sub_tree(
         &mut self,
         item: DomItem<'a, Message, Command, K>,
-        mut deferred: Option<(&mut Vec<DomItem<'a, Message, Command, K>>, &mut Vec<&'a mut WebItem<Message>>)>,
+        mut deferred: Option<(&mut Vec<DomItem<'a, Message, Command, K>>, &mut Vec<&'a mut WebItem<Message, B>>)>,
     ) -> Option<DomItem<'a, Message, Command, K>>
     {
         let key = match item {
@@ -586,8 +1200,10 @@ where
                             None
                         }
                         else {
+                            let element = web_item.as_element().cloned();
+                            let next = self.remove_sub_tree(element);
                             self.patch_set.push(Patch::RemoveElement(web_item));
-                            return self.remove_sub_tree();
+                            return next;
                         }
                     }
                     Entry::Vacant(e) => {
@@ -596,6 +1212,7 @@ where
                         }
 
                         e.insert((vec![item], vec![web_item]));
+                        self.old_order.push(key);
                         Some(key)
                     }
                 }
@@ -612,7 +1229,7 @@ where
                         }
                         else {
                             self.patch_set.push(Patch::RemoveComponent(web_item));
-                            return self.remove_sub_tree();
+                            return self.remove_sub_tree(None);
                         }
                     }
                     Entry::Vacant(e) => {
@@ -621,12 +1238,38 @@ where
                         }
 
                         e.insert((vec![item], vec![web_item]));
+                        self.old_order.push(key);
+                        Some(key)
+                    }
+                }
+            }
+            DomItem::Fragment { key: Some(key) } => {
+                let web_item = self.sto.next().expect("dom storage to match dom iter");
+                match self.old_def.entry(key) {
+                    Entry::Occupied(_) => {
+                        // XXX log the error to the debug console? warn?
+                        if let Some((ref mut deferred_items, ref mut deferred_storage)) = deferred {
+                            deferred_items.push(item);
+                            deferred_storage.push(web_item);
+                            None
+                        }
+                        else {
+                            return self.remove_nodes();
+                        }
+                    }
+                    Entry::Vacant(e) => {
+                        if let Some((ref mut deferred_items, _)) = deferred {
+                            deferred_items.push(DomItem::Key(key));
+                        }
+
+                        e.insert((vec![item], vec![web_item]));
+                        self.old_order.push(key);
                         Some(key)
                     }
                 }
             }
             _ => {
-                panic!("expected keyed element or component");
+                panic!("expected keyed element, component, or fragment");
             }
         };
 
@@ -668,6 +1311,17 @@ where
                         depth += 1;
                         self.old.next()
                     }
+                    // keyed fragment: defer
+                    DomItem::Fragment { key: Some(_) } => {
+                        self.defer_remove_sub_tree(i, Some((&mut def_items, &mut def_storage)))
+                    }
+                    // fragment or portal: remove from storage, track sub-tree depth
+                    DomItem::Fragment { .. } | DomItem::Portal { .. } => {
+                        def_storage.push(self.sto.next().expect("dom storage to match dom iter"));
+                        def_items.push(i);
+                        depth += 1;
+                        self.old.next()
+                    }
                     // key reference: defer
                     DomItem::Key(_) => {
                         def_items.push(i);
@@ -684,11 +1338,17 @@ where
                         def_items.push(i);
                         self.old.next()
                     }
-                    // attribute: ignore
-                    DomItem::Attr { .. } => {
+                    // attribute, class, style, property, ref, or hook: ignore
+                    DomItem::Attr { .. } | DomItem::Class(_) | DomItem::Style { .. }
+                    | DomItem::Property { .. } | DomItem::Ref(_) | DomItem::Hook { .. } => {
                         def_items.push(i);
                         self.old.next()
                     }
+                    // lazy sub tree: render it and defer its items
+                    DomItem::Lazy { dom, .. } => {
+                        self.old.expand(dom);
+                        self.old.next()
+                    }
                     // end of child: track sub-tree depth
                     DomItem::Up if depth > 0 => {
                         def_storage.push(self.sto.next().expect("dom storage to match dom iter"));
@@ -739,7 +1399,7 @@ where
     ) -> Option<DomItem<'a, Message, Command, K>>
     {
         let key = match item {
-            DomItem::Element { name: element, key: Some(key) } => {
+            DomItem::Element { name: element, key: Some(key), namespace } => {
                 match self.new_def.entry(key) {
                     Entry::Occupied(_) => {
                         // XXX log the error to the debug console? warn?
@@ -748,7 +1408,7 @@ where
                             None
                         }
                         else {
-                            self.patch_set.push(Patch::CreateElement { element });
+                            self.patch_set.push(Patch::CreateElement { element, namespace });
                             return self.add_sub_tree();
                         }
                     }
@@ -760,6 +1420,7 @@ where
                             self.patch_set.push(Patch::ReferenceKey(key));
                         }
                         e.insert(vec![item]);
+                        self.new_order.push(key);
                         Some(key)
                     }
                 }
@@ -785,12 +1446,39 @@ where
                             self.patch_set.push(Patch::ReferenceKey(key));
                         }
                         e.insert(vec![item]);
+                        self.new_order.push(key);
+                        Some(key)
+                    }
+                }
+            }
+            DomItem::Fragment { key: Some(key) } => {
+                match self.new_def.entry(key) {
+                    Entry::Occupied(_) => {
+                        // XXX log the error to the debug console? warn?
+                        if let Some(ref mut deferred_items) = deferred_items {
+                            deferred_items.push(item);
+                            None
+                        }
+                        else {
+                            self.patch_set.push(Patch::CreateFragment);
+                            return self.add_sub_tree();
+                        }
+                    }
+                    Entry::Vacant(e) => {
+                        if let Some(ref mut deferred_items) = deferred_items {
+                            deferred_items.push(DomItem::Key(key));
+                        }
+                        else {
+                            self.patch_set.push(Patch::ReferenceKey(key));
+                        }
+                        e.insert(vec![item]);
+                        self.new_order.push(key);
                         Some(key)
                     }
                 }
             }
             _ => {
-                panic!("expected keyed element or component");
+                panic!("expected keyed element, component, or fragment");
             }
         };
 
@@ -828,6 +1516,16 @@ where
                         depth += 1;
                         self.new.next()
                     }
+                    // keyed fragment: defer
+                    DomItem::Fragment { key: Some(_) } => {
+                        self.defer_add_sub_tree(i, Some(&mut def))
+                    }
+                    // fragment or portal: track depth
+                    DomItem::Fragment { .. } | DomItem::Portal { .. } => {
+                        def.push(i);
+                        depth += 1;
+                        self.new.next()
+                    }
                     // key reference: defer
                     DomItem::Key(_) => {
                         def.push(i);
@@ -843,11 +1541,17 @@ where
                         def.push(i);
                         self.new.next()
                     }
-                    // attribute: ignore
-                    DomItem::Attr { .. } => {
+                    // attribute, class, style, property, ref, or hook: ignore
+                    DomItem::Attr { .. } | DomItem::Class(_) | DomItem::Style { .. }
+                    | DomItem::Property { .. } | DomItem::Ref(_) | DomItem::Hook { .. } => {
                         def.push(i);
                         self.new.next()
                     }
+                    // lazy sub tree: render it and defer its items
+                    DomItem::Lazy { dom, .. } => {
+                        self.new.expand(dom);
+                        self.new.next()
+                    }
                     // end of child: track sub-tree depth
                     DomItem::Up if depth > 0 => {
                         def.push(i);
@@ -881,3 +1585,139 @@ where
         next
     }
 } // end of impl DiffImpl
+
+/// Collect the classes of a node, starting with the given item. Returns the classes and the item
+/// following them.
+fn take_classes<'a, Message, Command, K>(
+    item: DomItem<'a, Message, Command, K>,
+    iter: &mut impl Iterator<Item = DomItem<'a, Message, Command, K>>,
+) -> (Vec<&'a str>, Option<DomItem<'a, Message, Command, K>>)
+{
+    let mut classes = vec![];
+    let mut item = Some(item);
+    while let Some(DomItem::Class(class)) = item {
+        classes.push(class);
+        item = iter.next();
+    }
+    (classes, item)
+}
+
+/// A style property and its value.
+type Style<'a> = (&'a str, &'a str);
+
+/// Collect the style properties of a node, starting with the given item. Returns the properties
+/// and the item following them.
+fn take_styles<'a, Message, Command, K>(
+    item: DomItem<'a, Message, Command, K>,
+    iter: &mut impl Iterator<Item = DomItem<'a, Message, Command, K>>,
+) -> (Vec<Style<'a>>, Option<DomItem<'a, Message, Command, K>>)
+{
+    let mut styles = vec![];
+    let mut item = Some(item);
+    while let Some(DomItem::Style { property, value }) = item {
+        styles.push((property, value));
+        item = iter.next();
+    }
+    (styles, item)
+}
+
+/// A property name and its value.
+type Property<'a> = (&'a str, &'a PropertyValue);
+
+/// Collect the properties of a node, starting with the given item. Returns the properties and the
+/// item following them.
+fn take_properties<'a, Message, Command, K>(
+    item: DomItem<'a, Message, Command, K>,
+    iter: &mut impl Iterator<Item = DomItem<'a, Message, Command, K>>,
+) -> (Vec<Property<'a>>, Option<DomItem<'a, Message, Command, K>>)
+{
+    let mut properties = vec![];
+    let mut item = Some(item);
+    while let Some(DomItem::Property { name, value }) = item {
+        properties.push((name, value));
+        item = iter.next();
+    }
+    (properties, item)
+}
+
+/// Convert a patch moving a keyed item into one that leaves the item in place.
+fn stay<'a, Message, Command, K, B: Backend>(patch: Patch<'a, Message, Command, K, B>)
+-> Patch<'a, Message, Command, K, B>
+{
+    match patch {
+        Patch::MoveElement(item) => Patch::CopyElement(item),
+        Patch::MoveComponent(item) => Patch::CopyComponent(item),
+        Patch::MupdateComponent { take, msg } => Patch::UpdateComponent { take, msg },
+        Patch::MoveFragment(item) => Patch::CopyFragment(item),
+        patch => patch,
+    }
+}
+
+/// Find the indices of a longest strictly increasing subsequence of the given values.
+fn longest_increasing_subsequence(values: &[usize]) -> Vec<usize> {
+    // the index of the last value of the smallest ending increasing subsequence of each length
+    let mut tails: Vec<usize> = vec![];
+    // the index of the value preceding each value in its increasing subsequence
+    let mut prev: Vec<Option<usize>> = vec![None; values.len()];
+
+    for (i, value) in values.iter().enumerate() {
+        let len = tails.partition_point(|&t| values[t] < *value);
+        if len > 0 {
+            prev[i] = Some(tails[len - 1]);
+        }
+        if len == tails.len() {
+            tails.push(i);
+        }
+        else {
+            tails[len] = i;
+        }
+    }
+
+    let mut subsequence = vec![];
+    let mut next = tails.last().cloned();
+    while let Some(i) = next {
+        subsequence.push(i);
+        next = prev[i];
+    }
+    subsequence.reverse();
+    subsequence
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn lis_empty() {
+        assert!(longest_increasing_subsequence(&[]).is_empty());
+    }
+
+    #[test]
+    fn lis_sorted() {
+        assert_eq!(longest_increasing_subsequence(&[0, 1, 2, 3]), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn lis_reversed() {
+        assert_eq!(longest_increasing_subsequence(&[3, 2, 1, 0]).len(), 1);
+    }
+
+    #[test]
+    fn lis_one_moved() {
+        // the last item moved to the front
+        assert_eq!(longest_increasing_subsequence(&[4, 0, 1, 2, 3]), vec![1, 2, 3, 4]);
+        // the first item moved to the back
+        assert_eq!(longest_increasing_subsequence(&[1, 2, 3, 4, 0]), vec![0, 1, 2, 3]);
+    }
+
+    #[test]
+    fn lis_mixed() {
+        let values = [2, 6, 1, 3, 4, 0, 5];
+        let lis: Vec<usize> = longest_increasing_subsequence(&values)
+            .into_iter()
+            .map(|i| values[i])
+            .collect();
+        assert_eq!(lis.len(), 4);
+        assert!(lis.windows(2).all(|w| w[0] < w[1]), "not increasing: {:?}", lis);
+    }
+}
@@ -4,6 +4,8 @@
 //! structure works with other parts of this library.
 
 use std::iter;
+use std::fmt;
+use std::cell::OnceCell;
 use crate::vdom::*;
 
 /// A DOM event handler.
@@ -27,6 +29,13 @@ pub enum Handler<Message> {
     ///
     /// [`web_sys::InputEvent`]: https://rustwasm.github.io/wasm-bindgen/api/web_sys/struct.InputEvent.html
     InputEvent(fn(web_sys::InputEvent) -> Option<Message>),
+    /// Navigate to the `href` of the link this handler is attached to without loading a new page.
+    Link,
+    /// A closure that will convert a [`web_sys::Event`] event to a Message, see [`Callback`].
+    ///
+    /// [`web_sys::Event`]: https://rustwasm.github.io/wasm-bindgen/api/web_sys/struct.Event.html
+    /// [`Callback`]: ../vdom/struct.Callback.html
+    Closure(Callback<Message>),
 }
 
 /// A DOM event.
@@ -36,15 +45,19 @@ pub struct Event<Message> {
     trigger: &'static str,
     /// The handler for this event.
     handler: Handler<Message>,
+    /// Options for the event listener.
+    options: ListenerOptions,
 }
 
 /// Representation of a DOM node.
 #[derive(Debug)]
-pub enum Node<Message, Command> {
+pub enum Node<Message, Command, Key = ()> {
     /// A DOM element node.
     Elem {
         /// The element name/type.
         name: &'static str,
+        /// The namespace of the element, inherited from its parent if `None`.
+        namespace: Option<Namespace>,
     },
     /// A DOM text node.
     Text {
@@ -58,12 +71,23 @@ pub enum Node<Message, Command> {
         /// A function to create the component.
         create: fn(Dispatcher<Message, Command>) -> Box<dyn Component<Message>>,
     },
+    /// A lazily rendered sub tree.
+    Lazy(Lazy<Message, Command, Key>),
+    /// A fragment, its children are added to the parent of the fragment.
+    Fragment,
+    /// A portal, its children are added to the target element.
+    Portal(PortalTarget),
 }
 
-impl<Message, Command> Node<Message, Command> {
+impl<Message, Command, Key> Node<Message, Command, Key> {
     /// Generate an element node of the given type.
     pub fn elem(name: &'static str) -> Self {
-        Node::Elem { name }
+        Node::Elem { name, namespace: None }
+    }
+
+    /// Generate an element node of the given type in the given namespace.
+    pub fn elem_ns(name: &'static str, namespace: Namespace) -> Self {
+        Node::Elem { name, namespace: Some(namespace) }
     }
 
     /// Generate a text node with the given value.
@@ -77,6 +101,103 @@ impl<Message, Command> Node<Message, Command> {
     }
 }
 
+/// A lazily rendered sub tree.
+///
+/// The sub tree is only rendered if its input or render function differ from those of the sub tree
+/// it is being compared with.
+pub struct Lazy<Message, Command, Key> {
+    /// The input and render function.
+    render: Box<dyn Render<Message, Command, Key>>,
+    /// The rendered sub tree, if it has been rendered.
+    dom: OnceCell<Box<Dom<Message, Command, Key>>>,
+}
+
+impl<Message: Clone, Command, Key> Lazy<Message, Command, Key> {
+    /// Iterate over the sub tree, rendering it if necessary. The key, ref, and hooks of the given
+    /// lazy node are applied to the root of the sub tree.
+    fn dom_iter<'a>(&'a self, node: &'a Dom<Message, Command, Key>)
+        -> Box<dyn Iterator<Item = DomItem<'a, Message, Command, Key>> + 'a>
+    {
+        let mut items = self.dom
+            .get_or_init(|| Box::new(self.render.render()))
+            .dom_iter();
+
+        let root = match items.next() {
+            Some(DomItem::Element { name, namespace, key }) => DomItem::Element {
+                name,
+                namespace,
+                key: node.key.as_ref().or(key),
+            },
+            Some(DomItem::Component { msg, create, key }) => DomItem::Component {
+                msg,
+                create,
+                key: node.key.as_ref().or(key),
+            },
+            Some(DomItem::Fragment { key }) => {
+                // fragments don't have refs or hooks
+                let root = DomItem::Fragment { key: node.key.as_ref().or(key) };
+                return Box::new(iter::once(root).chain(items));
+            }
+            Some(item) => item,
+            None => return items,
+        };
+
+        let iter = iter::once(root)
+            .chain(node.node_ref.iter()
+                .map(|name| DomItem::Ref(name))
+            )
+            .chain(node.hooks.iter()
+                .map(|(lifecycle, hook)| DomItem::Hook { lifecycle: *lifecycle, hook: *hook })
+            )
+            .chain(items);
+
+        Box::new(iter)
+    }
+}
+
+impl<Message, Command, Key> fmt::Debug for Lazy<Message, Command, Key> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Lazy")
+            .field("rendered", &self.dom.get().is_some())
+            .finish()
+    }
+}
+
+/// The input of a lazy sub tree and the function that renders it.
+trait Render<Message, Command, Key> {
+    /// Render the sub tree.
+    fn render(&self) -> Dom<Message, Command, Key>;
+
+    /// Identify the sub tree of the given lazy node by this input and render function.
+    fn lazy_dom<'a>(&'a self, lazy: &'a Lazy<Message, Command, Key>, node: &'a Dom<Message, Command, Key>)
+        -> LazyDom<'a, Message, Command, Key>
+        where Message: Clone;
+}
+
+/// The input of a lazy sub tree along with the function that renders it.
+struct LazyInput<T, Message, Command, Key> {
+    input: T,
+    render: fn(&T) -> Dom<Message, Command, Key>,
+}
+
+impl<Message, Command, Key, T> Render<Message, Command, Key> for LazyInput<T, Message, Command, Key> where
+    T: PartialEq + 'static,
+    Message: 'static,
+    Command: 'static,
+    Key: 'static,
+{
+    fn render(&self) -> Dom<Message, Command, Key> {
+        (self.render)(&self.input)
+    }
+
+    fn lazy_dom<'a>(&'a self, lazy: &'a Lazy<Message, Command, Key>, node: &'a Dom<Message, Command, Key>)
+        -> LazyDom<'a, Message, Command, Key>
+        where Message: Clone
+    {
+        LazyDom::new(&self.input, self.render as usize, move || lazy.dom_iter(node))
+    }
+}
+
 /// An attribute on a node.
 #[derive(PartialEq, Debug)]
 pub struct Attr {
@@ -106,58 +227,146 @@ impl From<(&'static str, String)> for Attr {
     }
 }
 
+/// An inline style property on a node.
+#[derive(PartialEq, Debug)]
+pub struct Style {
+    /// The name of the css property.
+    property: &'static str,
+    /// The value of the property.
+    value: String,
+}
+
+/// A property on a node.
+#[derive(PartialEq, Debug)]
+pub struct Property {
+    /// The name of the property.
+    name: &'static str,
+    /// The value of the property.
+    value: PropertyValue,
+}
+
 /// A node in the DOM.
 #[derive(Debug)]
 pub struct Dom<Message = (), Command = (), Key = ()> {
     /// The element for this node.
-    element: Node<Message, Command>,
+    element: Node<Message, Command, Key>,
     /// The innerHtml value for this node.
     inner_html: Option<String>,
     /// The key for this node.
     key: Option<Key>,
+    /// The name this node can be looked up by.
+    node_ref: Option<&'static str>,
     /// Attributes on this node.
     pub attributes: Vec<Attr>,
+    /// Classes on this node.
+    pub classes: Vec<String>,
+    /// Inline style properties on this node.
+    pub styles: Vec<Style>,
+    /// Properties on this node.
+    pub properties: Vec<Property>,
     /// Event handlers associated with this node.
     pub events: Vec<Event<Message>>,
+    /// Lifecycle hooks of this node.
+    pub hooks: Vec<(Lifecycle, Hook<Message, Command>)>,
     /// Children of this node.
     pub children: Vec<Dom<Message, Command, Key>>,
 }
 
 impl<Message, Command, Key> Dom<Message, Command, Key> {
-    /// Create a new DOM element node.
-    pub fn elem(element: &'static str) -> Self {
+    /// Create a DOM node for the given element, without any attributes or children.
+    fn new(element: Node<Message, Command, Key>) -> Self {
         Dom {
-            element: Node::elem(element),
+            element,
             key: None,
+            node_ref: None,
             events: vec![],
+            hooks: vec![],
             attributes: vec![],
+            classes: vec![],
+            styles: vec![],
+            properties: vec![],
             children: vec![],
             inner_html: None,
         }
     }
 
+    /// Create a new DOM element node.
+    ///
+    /// The element's namespace is inherited from its parent, so descendants of an `svg` element
+    /// are created as svg elements.
+    pub fn elem(element: &'static str) -> Self {
+        Dom::new(Node::elem(element))
+    }
+
+    /// Create a new DOM element node in the given namespace.
+    ///
+    /// This is only necessary for elements whose namespace can't be inherited, for example the
+    /// root of a component that renders into an `svg` element.
+    pub fn elem_ns(element: &'static str, namespace: Namespace) -> Self {
+        Dom::new(Node::elem_ns(element, namespace))
+    }
+
     /// Create a new DOM text node.
     pub fn text(value: impl Into<String>) -> Self {
-        Dom {
-            element: Node::text(value.into()),
-            key: None,
-            events: vec![],
-            attributes: vec![],
-            children: vec![],
-            inner_html: None,
-        }
+        Dom::new(Node::text(value.into()))
     }
 
     /// Create a component.
     pub fn component(msg: Message, create: fn(Dispatcher<Message, Command>) -> Box<dyn Component<Message>>) -> Self {
-        Dom {
-            element: Node::component(msg, create),
-            key: None,
-            events: vec![],
-            attributes: vec![],
-            children: vec![],
-            inner_html: None,
-        }
+        Dom::new(Node::component(msg, create))
+    }
+
+    /// Create a fragment containing the given nodes.
+    ///
+    /// A fragment allows a single child position to hold several sibling nodes without a
+    /// containing element, the nodes are added directly to the parent of the fragment. Fragments
+    /// can be keyed, a keyed fragment and all of its nodes are moved together. Attributes,
+    /// classes, and events can't be added to a fragment, add them to its children instead.
+    pub fn fragment(children: impl IntoIterator<Item = Dom<Message, Command, Key>>) -> Self {
+        Dom::new(Node::Fragment)
+            .extend(children)
+    }
+
+    /// Create a portal rendering the given nodes into the given target element.
+    ///
+    /// The target can be a css selector, resolved when the portal is created, or a
+    /// [`web_sys::Element`]. The nodes are added to the target after any existing children, and
+    /// are removed when the portal is removed from the dom or the app is detached. Events on the
+    /// nodes are dispatched to the app like any other. Like fragments, portals can't have
+    /// attributes, classes, or events.
+    ///
+    /// [`web_sys::Element`]: https://rustwasm.github.io/wasm-bindgen/api/web_sys/struct.Element.html
+    pub fn portal(
+        target: impl Into<PortalTarget>,
+        children: impl IntoIterator<Item = Dom<Message, Command, Key>>,
+    ) -> Self {
+        Dom::new(Node::Portal(target.into()))
+            .extend(children)
+    }
+
+    /// Create a lazily rendered sub tree.
+    ///
+    /// The given render function will be called with the given input to generate the sub tree,
+    /// but only if the input differs from the input of the lazy sub tree in the same position in
+    /// the old dom. If the render functions are the same and the inputs are equal the existing sub
+    /// tree is reused as is, without rendering or diffing it.
+    ///
+    /// The render function must always produce the same dom for the same input and the dom it
+    /// produces must have a single root node. Like lifecycle hooks, it is a plain fn pointer, so it
+    /// can't capture any state. A key, ref, and hooks added to the returned node are applied to
+    /// the root of the rendered dom. Attributes, events, and children added to the returned node
+    /// are ignored, add them to the rendered dom instead.
+    pub fn lazy<T>(input: T, render: fn(&T) -> Self) -> Self
+    where
+        T: PartialEq + 'static,
+        Message: 'static,
+        Command: 'static,
+        Key: 'static,
+    {
+        Dom::new(Node::Lazy(Lazy {
+            render: Box::new(LazyInput { input, render }),
+            dom: OnceCell::new(),
+        }))
     }
 
     /// Add an key to this DOM element.
@@ -180,22 +389,118 @@ impl<Message, Command, Key> Dom<Message, Command, Key> {
         self
     }
 
+    /// Add a class to this DOM element. Multiple classes may be given separated by whitespace.
+    ///
+    /// Classes are added and removed individually when the dom changes, leaving classes added by
+    /// other code alone. Don't combine this with a `class` attribute on the same element.
+    pub fn class(mut self, name: impl Into<String>) -> Self {
+        let name = name.into();
+        self.classes.extend(name.split_whitespace().map(String::from));
+        self
+    }
+
+    /// Add a class to this DOM element if the given condition is true.
+    pub fn class_if(self, name: impl Into<String>, cond: bool) -> Self {
+        if cond {
+            self.class(name)
+        }
+        else {
+            self
+        }
+    }
+
+    /// Set an inline style property on this DOM element.
+    ///
+    /// Properties are set and removed individually when the dom changes. Don't combine this with
+    /// a `style` attribute on the same element.
+    pub fn style(mut self, property: &'static str, value: impl Into<String>) -> Self {
+        self.styles.push(Style { property, value: value.into() });
+        self
+    }
+
+    /// Set a property on this DOM element.
+    ///
+    /// Properties are set directly on the DOM object rather than through an attribute, use this
+    /// for values with no attribute form, like `indeterminate`, or for properties of custom
+    /// elements. Properties aren't included when rendering html on the server.
+    pub fn property(mut self, name: &'static str, value: impl Into<PropertyValue>) -> Self {
+        self.properties.push(Property { name, value: value.into() });
+        self
+    }
+
+    /// Give this DOM element a name it can be looked up by.
+    ///
+    /// The browser's element is recorded by the app when the dom is patched, commands can then
+    /// get it with [`Dispatcher::node_ref`], to focus or scroll it for example. Names should be
+    /// unique within an app, only element nodes can be referenced.
+    ///
+    /// [`Dispatcher::node_ref`]: ../app/dispatch/struct.Dispatcher.html#method.node_ref
+    pub fn node_ref(mut self, name: &'static str) -> Self {
+        self.node_ref = Some(name);
+        self
+    }
+
+    /// Run the given hook at the given point in the lifecycle of this DOM element.
+    ///
+    /// Hooks run after the dom has been patched, with the browser's element. Use them to manage
+    /// state outside of the virtual dom, for example to set up a third party widget when the
+    /// element is mounted and tear it down when it is unmounted.
+    pub fn hook(mut self, lifecycle: Lifecycle, hook: Hook<Message, Command>) -> Self {
+        self.hooks.push((lifecycle, hook));
+        self
+    }
+
     /// Add an event listener to this DOM element.
     pub fn event(self, trigger: &'static str, msg: Message) -> Self {
         self.on(trigger, Handler::Msg(msg))
     }
 
     /// Add an event listener to this DOM element.
-    pub fn on(mut self, trigger: &'static str, handler: Handler<Message>) -> Self {
+    pub fn on(self, trigger: &'static str, handler: Handler<Message>) -> Self {
+        self.on_with_options(trigger, handler, ListenerOptions::default())
+    }
+
+    /// Add an event listener with the given [`ListenerOptions`] to this DOM element.
+    ///
+    /// [`ListenerOptions`]: ../vdom/struct.ListenerOptions.html
+    pub fn on_with_options(mut self, trigger: &'static str, handler: Handler<Message>, options: ListenerOptions) -> Self {
         self.events.push(
             Event {
-                trigger: trigger,
-                handler: handler,
+                trigger,
+                handler,
+                options,
             }
         );
         self
     }
 
+    /// Add an event listener that calls the given closure, identified by the given key.
+    ///
+    /// The closure can capture state, the key should identify everything it captures. See
+    /// [`Callback`] for details.
+    ///
+    /// [`Callback`]: ../vdom/struct.Callback.html
+    pub fn callback<K, F>(self, trigger: &'static str, key: K, fun: F) -> Self
+    where
+        K: PartialEq + 'static,
+        F: Fn(web_sys::Event) -> Option<Message> + 'static,
+    {
+        self.on(trigger, Handler::Closure(Callback::new(key, fun)))
+    }
+
+    /// Create a link to a page within the app.
+    ///
+    /// Clicking the link navigates to the given url with [`Navigate::Push`] and routes it with the
+    /// app's router, instead of loading a new page. Clicks with modifier keys and links to other
+    /// sites are left to the browser.
+    ///
+    /// [`Navigate::Push`]: ../route/navigate/enum.Navigate.html#variant.Push
+    pub fn link(href: impl Into<String>) -> Self {
+        Dom::elem("a")
+            .attr("href", href)
+            .on("click", Handler::Link)
+    }
+
     /// Add a change event listener to this DOM element.
     pub fn onchange(self, handler: fn(String) -> Option<Message>) -> Self {
         self.on("change", Handler::InputValue(handler))
@@ -234,11 +539,37 @@ impl<Message, Command, K> Into<Dom<Message, Command, K>> for &str {
 impl<Message: Clone, Command, K> DomIter<Message, Command, K> for Dom<Message, Command, K> {
     fn dom_iter<'a>(&'a self) -> Box<dyn Iterator<Item = DomItem<'a, Message, Command, K>> + 'a>
     {
+        if let Node::Lazy(lazy) = &self.element {
+            return Box::new(iter::once(DomItem::Lazy {
+                key: self.key.as_ref(),
+                dom: lazy.render.lazy_dom(lazy, self),
+            }));
+        }
+
+        // fragments and portals only have children
+        let group = match self.element {
+            Node::Fragment => Some(DomItem::Fragment { key: self.key.as_ref() }),
+            Node::Portal(ref target) => Some(DomItem::Portal { target }),
+            _ => None,
+        };
+        if let Some(item) = group {
+            let iter = iter::once(item)
+                .chain(self.children.iter()
+                   .flat_map(|c| c.dom_iter())
+                )
+                .chain(iter::once(DomItem::Up));
+
+            return Box::new(iter);
+        }
+
         let iter = iter::once((&self.element, &self.key))
             .map(|(node, key)| match node {
-                Node::Elem { name } => DomItem::Element { name, key: key.as_ref() },
+                Node::Elem { name, namespace } => DomItem::Element { name, key: key.as_ref(), namespace: *namespace },
                 Node::Text { text } => DomItem::Text(text),
                 Node::Component { msg, create } => DomItem::Component { msg: msg.clone(), create: *create, key: key.as_ref() },
+                Node::Lazy(_) | Node::Fragment | Node::Portal(_) => {
+                    unreachable!("lazy nodes, fragments, and portals are handled above")
+                }
             })
             .chain(self.attributes.iter()
                 .map(|attr| DomItem::Attr {
@@ -246,11 +577,32 @@ impl<Message: Clone, Command, K> DomIter<Message, Command, K> for Dom<Message, C
                     value: &attr.value
                 })
             )
+            .chain(self.classes.iter()
+                .map(|class| DomItem::Class(class))
+            )
+            .chain(self.styles.iter()
+                .map(|style| DomItem::Style {
+                    property: style.property,
+                    value: &style.value,
+                })
+            )
+            .chain(self.properties.iter()
+                .map(|prop| DomItem::Property {
+                    name: prop.name,
+                    value: &prop.value,
+                })
+            )
+            .chain(self.node_ref.iter()
+                .map(|name| DomItem::Ref(name))
+            )
+            .chain(self.hooks.iter()
+                .map(|(lifecycle, hook)| DomItem::Hook { lifecycle: *lifecycle, hook: *hook })
+            )
             .chain(self.inner_html.iter()
                 .map(|html| DomItem::UnsafeInnerHtml(html))
             )
             .chain(self.events.iter()
-                .map(|Event { trigger, handler }|
+                .map(|Event { trigger, handler, options }|
                      DomItem::Event {
                          trigger: trigger,
                          handler: match handler {
@@ -259,7 +611,10 @@ impl<Message: Clone, Command, K> DomIter<Message, Command, K> for Dom<Message, C
                              Handler::MsgEvent(m, h) => EventHandler::FnMsg(m, *h),
                              Handler::InputValue(h) => EventHandler::InputValue(*h),
                              Handler::InputEvent(h) => EventHandler::InputEvent(*h),
+                             Handler::Link => EventHandler::Link,
+                             Handler::Closure(c) => EventHandler::Closure(c),
                          },
+                         options: *options,
                      }
                  )
             )
@@ -275,7 +630,10 @@ impl<Message: Clone, Command, K> DomIter<Message, Command, K> for Dom<Message, C
 /// A sequence of DOM entries.
 ///
 /// This structure allows a top level sequence of DOM entries to be represented without requiring a
-/// containing DOM element.
+/// containing DOM element. Use [`Dom::fragment`] for a sequence in a child position, a `DomVec`
+/// can be converted into one.
+///
+/// [`Dom::fragment`]: struct.Dom.html#method.fragment
 #[derive(Debug)]
 pub struct DomVec<Message = (), Command = (), Key = ()>(Vec<Dom<Message, Command, Key>>);
 
@@ -296,6 +654,12 @@ impl<Message, Command, K> From<Vec<Dom<Message, Command, K>>> for DomVec<Message
     }
 }
 
+impl<Message, Command, K> From<DomVec<Message, Command, K>> for Dom<Message, Command, K> {
+    fn from(v: DomVec<Message, Command, K>) -> Self {
+        Dom::fragment(v)
+    }
+}
+
 impl<Message, Command, K> Into<Vec<Dom<Message, Command, K>>> for DomVec<Message, Command, K> {
     fn into(self) -> Vec<Dom<Message, Command, K>> {
         self.0
@@ -0,0 +1,700 @@
+//! Typed builders for html elements and attributes.
+//!
+//! Each html element has a function here that creates a builder for it. Builders only provide
+//! methods for the attributes that apply to their element, so a misspelled attribute or an
+//! attribute on the wrong element is a compile error instead of a silently ignored attribute.
+//! Builders convert into the same [`Dom`] values that [`Dom::elem`] and [`Dom::attr`] produce.
+//!
+//! ```
+//! use euca::html::{self, InputType};
+//! use euca::dom::Dom;
+//!
+//! #[derive(Clone, PartialEq, Debug)]
+//! enum Message {
+//!     ToggleAll,
+//! }
+//!
+//! let dom: Dom<Message> = html::section()
+//!     .class("main")
+//!     .push(html::input()
+//!         .id("toggle-all")
+//!         .type_(InputType::Checkbox)
+//!         .checked(true)
+//!         .onclick(Message::ToggleAll)
+//!     )
+//!     .push(html::label()
+//!         .for_("toggle-all")
+//!         .push("Mark all as complete")
+//!     )
+//!     .into();
+//! ```
+//!
+//! Attributes that don't apply to an element aren't available:
+//!
+//! ```compile_fail
+//! let dom: euca::dom::Dom = euca::html::div().checked(true).into();
+//! ```
+//!
+//! Neither are children for [void elements]:
+//!
+//! ```compile_fail
+//! let dom: euca::dom::Dom = euca::html::input().push("text").into();
+//! ```
+//!
+//! Attributes not covered here, such as `data-*` and `aria-*` attributes, can be set with
+//! [`Element::attr`].
+//!
+//! [`Dom`]: ../dom/struct.Dom.html
+//! [`Dom::elem`]: ../dom/struct.Dom.html#method.elem
+//! [`Dom::attr`]: ../dom/struct.Dom.html#method.attr
+//! [`Element::attr`]: struct.Element.html#method.attr
+//! [void elements]: https://html.spec.whatwg.org/multipage/syntax.html#void-elements
+
+use std::fmt;
+use std::marker::PhantomData;
+use crate::dom::{Dom, Handler};
+use crate::vdom::{ListenerOptions, PropertyValue};
+
+/// An html element type.
+pub trait HtmlElement {
+    /// The name of the element.
+    const NAME: &'static str;
+}
+
+/// An html element that can contain children, meaning it is not a void element.
+pub trait Container: HtmlElement {}
+
+/// A builder for an html element of type `E`.
+#[derive(Debug)]
+pub struct Element<E, Message = (), Command = (), Key = ()> {
+    dom: Dom<Message, Command, Key>,
+    element: PhantomData<E>,
+}
+
+impl<E: HtmlElement, Message, Command, Key> Element<E, Message, Command, Key> {
+    /// Create a new element.
+    pub fn new() -> Self {
+        Element {
+            dom: Dom::elem(E::NAME),
+            element: PhantomData,
+        }
+    }
+
+    /// Add an attribute to this element.
+    ///
+    /// Prefer the typed attribute methods, this is meant for attributes they don't cover.
+    #[must_use]
+    pub fn attr(mut self, name: &'static str, value: impl Into<String>) -> Self {
+        self.dom = self.dom.attr(name, value);
+        self
+    }
+
+    /// Add classes to this element, separated by whitespace.
+    #[must_use]
+    pub fn class(mut self, name: impl Into<String>) -> Self {
+        self.dom = self.dom.class(name);
+        self
+    }
+
+    /// Add a class to this element if the given condition is true.
+    #[must_use]
+    pub fn class_if(mut self, name: impl Into<String>, cond: bool) -> Self {
+        self.dom = self.dom.class_if(name, cond);
+        self
+    }
+
+    /// Set an inline style property on this element.
+    #[must_use]
+    pub fn style(mut self, property: &'static str, value: impl Into<String>) -> Self {
+        self.dom = self.dom.style(property, value);
+        self
+    }
+
+    /// Set a property on this element.
+    #[must_use]
+    pub fn property(mut self, name: &'static str, value: impl Into<PropertyValue>) -> Self {
+        self.dom = self.dom.property(name, value);
+        self
+    }
+
+    /// Add a key to this element.
+    #[must_use]
+    pub fn key(mut self, key: impl Into<Key>) -> Self {
+        self.dom = self.dom.key(key);
+        self
+    }
+
+    /// Add an event listener that dispatches the given message.
+    #[must_use]
+    pub fn event(mut self, trigger: &'static str, msg: Message) -> Self {
+        self.dom = self.dom.event(trigger, msg);
+        self
+    }
+
+    /// Add an event listener.
+    #[must_use]
+    pub fn on(mut self, trigger: &'static str, handler: Handler<Message>) -> Self {
+        self.dom = self.dom.on(trigger, handler);
+        self
+    }
+
+    /// Add an event listener with the given options.
+    #[must_use]
+    pub fn on_with_options(mut self, trigger: &'static str, handler: Handler<Message>, options: ListenerOptions) -> Self {
+        self.dom = self.dom.on_with_options(trigger, handler, options);
+        self
+    }
+
+    /// Add an event listener that calls the given closure, identified by the given key.
+    #[must_use]
+    pub fn callback<K, F>(mut self, trigger: &'static str, key: K, fun: F) -> Self
+    where
+        K: PartialEq + 'static,
+        F: Fn(web_sys::Event) -> Option<Message> + 'static,
+    {
+        self.dom = self.dom.callback(trigger, key, fun);
+        self
+    }
+
+    /// Dispatch the given message when this element is clicked.
+    #[must_use]
+    pub fn onclick(self, msg: Message) -> Self {
+        self.event("click", msg)
+    }
+
+    /// Dispatch the given message when this element is double clicked.
+    #[must_use]
+    pub fn ondblclick(self, msg: Message) -> Self {
+        self.event("dblclick", msg)
+    }
+
+    /// Dispatch the given message when this element gains focus.
+    #[must_use]
+    pub fn onfocus(self, msg: Message) -> Self {
+        self.event("focus", msg)
+    }
+
+    /// Dispatch the given message when this element loses focus.
+    #[must_use]
+    pub fn onblur(self, msg: Message) -> Self {
+        self.event("blur", msg)
+    }
+
+    /// Add a change event listener that converts the value of this element into a message.
+    #[must_use]
+    pub fn onchange(self, handler: fn(String) -> Option<Message>) -> Self {
+        self.on("change", Handler::InputValue(handler))
+    }
+
+    /// Add an input event listener.
+    #[must_use]
+    pub fn oninput(self, handler: fn(web_sys::InputEvent) -> Option<Message>) -> Self {
+        self.on("input", Handler::InputEvent(handler))
+    }
+}
+
+impl<E: HtmlElement, Message, Command, Key> Default for Element<E, Message, Command, Key> {
+    fn default() -> Self {
+        Element::new()
+    }
+}
+
+impl<E: Container, Message, Command, Key> Element<E, Message, Command, Key> {
+    /// Append the given node as a child of this element.
+    #[must_use]
+    pub fn push(mut self, child: impl Into<Dom<Message, Command, Key>>) -> Self {
+        self.dom = self.dom.push(child);
+        self
+    }
+
+    /// Append the nodes returned by the given iterator as children of this element.
+    #[must_use]
+    pub fn extend(mut self, iter: impl IntoIterator<Item = Dom<Message, Command, Key>>) -> Self {
+        self.dom = self.dom.extend(iter);
+        self
+    }
+}
+
+impl<E, Message, Command, Key> From<Element<E, Message, Command, Key>> for Dom<Message, Command, Key> {
+    fn from(element: Element<E, Message, Command, Key>) -> Self {
+        element.dom
+    }
+}
+
+macro_rules! value_enum {
+    (
+        $(#[$meta:meta])*
+        $name:ident { $( $(#[$vmeta:meta])* $variant:ident => $value:literal, )* }
+    ) => {
+        $(#[$meta])*
+        #[derive(Clone, Copy, PartialEq, Eq, Debug)]
+        pub enum $name {
+            $( $(#[$vmeta])* $variant, )*
+        }
+
+        impl $name {
+            /// The value of the attribute.
+            pub fn as_str(&self) -> &'static str {
+                match self {
+                    $( $name::$variant => $value, )*
+                }
+            }
+        }
+
+        impl fmt::Display for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.write_str(self.as_str())
+            }
+        }
+    };
+}
+
+value_enum! {
+    /// The type of an `<input>` element.
+    InputType {
+        /// A push button with no default behavior.
+        Button => "button",
+        /// A check box.
+        Checkbox => "checkbox",
+        /// A color picker.
+        Color => "color",
+        /// A date.
+        Date => "date",
+        /// A date and time with no time zone.
+        DatetimeLocal => "datetime-local",
+        /// An email address.
+        Email => "email",
+        /// A file picker.
+        File => "file",
+        /// A value that is not displayed.
+        Hidden => "hidden",
+        /// A graphical submit button.
+        Image => "image",
+        /// A month and year.
+        Month => "month",
+        /// A number.
+        Number => "number",
+        /// A password.
+        Password => "password",
+        /// A radio button.
+        Radio => "radio",
+        /// A number within a range.
+        Range => "range",
+        /// A button that resets the form.
+        Reset => "reset",
+        /// A search string.
+        Search => "search",
+        /// A button that submits the form.
+        Submit => "submit",
+        /// A telephone number.
+        Tel => "tel",
+        /// A single line of text.
+        Text => "text",
+        /// A time.
+        Time => "time",
+        /// A url.
+        Url => "url",
+        /// A week and year.
+        Week => "week",
+    }
+}
+
+value_enum! {
+    /// The type of a `<button>` element.
+    ButtonType {
+        /// Submit the form.
+        Submit => "submit",
+        /// Reset the form.
+        Reset => "reset",
+        /// No default behavior.
+        Button => "button",
+    }
+}
+
+value_enum! {
+    /// The http method used to submit a `<form>`.
+    FormMethod {
+        /// Submit with a GET request.
+        Get => "get",
+        /// Submit with a POST request.
+        Post => "post",
+        /// Close the dialog the form is in.
+        Dialog => "dialog",
+    }
+}
+
+/// Generate an attribute method.
+///
+/// Text attributes accept anything that converts into a `String`. Boolean attributes are present
+/// when `true` and omitted when `false`. Other attributes are converted using `Display`.
+macro_rules! attribute {
+    (@text $method:ident $name:literal $doc:literal) => {
+        #[doc = $doc]
+        #[must_use]
+        pub fn $method(self, value: impl Into<String>) -> Self {
+            self.attr($name, value)
+        }
+    };
+    (@bool $method:ident $name:literal $doc:literal) => {
+        #[doc = $doc]
+        #[must_use]
+        pub fn $method(self, value: bool) -> Self {
+            if value { self.attr($name, "true") } else { self }
+        }
+    };
+    (@value $method:ident $name:literal $ty:ty, $doc:literal) => {
+        #[doc = $doc]
+        #[must_use]
+        pub fn $method(self, value: $ty) -> Self {
+            self.attr($name, value.to_string())
+        }
+    };
+
+    // global attributes
+    (accesskey) => { attribute!(@text accesskey "accesskey" "A keyboard shortcut for this element."); };
+    (contenteditable) => { attribute!(@value contenteditable "contenteditable" bool, "Whether this element can be edited by the user."); };
+    (dir) => { attribute!(@text dir "dir" "The direction of the text (`ltr`, `rtl`, or `auto`)."); };
+    (draggable) => { attribute!(@value draggable "draggable" bool, "Whether this element can be dragged."); };
+    (hidden) => { attribute!(@bool hidden "hidden" "Hide this element."); };
+    (id) => { attribute!(@text id "id" "The unique id of this element."); };
+    (lang) => { attribute!(@text lang "lang" "The language of this element's content."); };
+    (role) => { attribute!(@text role "role" "The accessibility role of this element."); };
+    (spellcheck) => { attribute!(@value spellcheck "spellcheck" bool, "Whether to check the spelling of this element's content."); };
+    (tabindex) => { attribute!(@value tabindex "tabindex" i32, "The position of this element in the tab order."); };
+    (title) => { attribute!(@text title "title" "Advisory information about this element, usually shown as a tooltip."); };
+    (translate) => { attribute!(@text translate "translate" "Whether this element's content should be translated (`yes` or `no`)."); };
+
+    // element specific attributes
+    (abbr) => { attribute!(@text abbr "abbr" "An abbreviated description of this header cell."); };
+    (accept) => { attribute!(@text accept "accept" "The file types accepted by this input."); };
+    (action) => { attribute!(@text action "action" "The url to submit this form to."); };
+    (allow) => { attribute!(@text allow "allow" "The permissions policy for this frame."); };
+    (alt) => { attribute!(@text alt "alt" "Alternative text for this image."); };
+    (as_) => { attribute!(@text as_ "as" "The type of content being preloaded."); };
+    (async_) => { attribute!(@bool async_ "async" "Execute this script asynchronously."); };
+    (autocomplete) => { attribute!(@text autocomplete "autocomplete" "Hints for the browser's autofill feature."); };
+    (autofocus) => { attribute!(@bool autofocus "autofocus" "Focus this element when the page loads."); };
+    (autoplay) => { attribute!(@bool autoplay "autoplay" "Start playing the media automatically."); };
+    (charset) => { attribute!(@text charset "charset" "The character encoding of the document."); };
+    (checked) => { attribute!(@bool checked "checked" "Whether this input is checked."); };
+    (cite) => { attribute!(@text cite "cite" "The url of the source of a quote or edit."); };
+    (colspan) => { attribute!(@value colspan "colspan" u32, "The number of columns this cell spans."); };
+    (cols) => { attribute!(@value cols "cols" u32, "The visible width of this text area in characters."); };
+    (content) => { attribute!(@text content "content" "The value of this metadata."); };
+    (controls) => { attribute!(@bool controls "controls" "Show the browser's playback controls."); };
+    (coords) => { attribute!(@text coords "coords" "The coordinates of this area."); };
+    (crossorigin) => { attribute!(@text crossorigin "crossorigin" "How to handle cross origin requests for this resource."); };
+    (data) => { attribute!(@text data "data" "The url of this object's resource."); };
+    (datetime) => { attribute!(@text datetime "datetime" "A machine readable date and time."); };
+    (default) => { attribute!(@bool default "default" "Enable this track by default."); };
+    (defer) => { attribute!(@bool defer "defer" "Execute this script after the document is parsed."); };
+    (disabled) => { attribute!(@bool disabled "disabled" "Whether this element is disabled."); };
+    (download) => { attribute!(@text download "download" "Download the linked resource with the given file name."); };
+    (enctype) => { attribute!(@text enctype "enctype" "The encoding used to submit this form."); };
+    (for_) => { attribute!(@text for_ "for" "The id of the element this element is for."); };
+    (form) => { attribute!(@text form "form" "The id of the form this element belongs to."); };
+    (headers) => { attribute!(@text headers "headers" "The ids of the header cells for this cell."); };
+    (height) => { attribute!(@value height "height" u32, "The height in pixels."); };
+    (high) => { attribute!(@value high "high" f64, "The lower bound of the high range."); };
+    (href) => { attribute!(@text href "href" "The url of the linked resource."); };
+    (hreflang) => { attribute!(@text hreflang "hreflang" "The language of the linked resource."); };
+    (http_equiv) => { attribute!(@text http_equiv "http-equiv" "The pragma directive of this metadata."); };
+    (kind) => { attribute!(@text kind "kind" "How this text track is meant to be used."); };
+    (label) => { attribute!(@text label "label" "A user visible label."); };
+    (list) => { attribute!(@text list "list" "The id of a datalist of suggestions for this input."); };
+    (loading) => { attribute!(@text loading "loading" "When to load this resource (`eager` or `lazy`)."); };
+    (loop_) => { attribute!(@bool loop_ "loop" "Play the media in a loop."); };
+    (low) => { attribute!(@value low "low" f64, "The upper bound of the low range."); };
+    (max) => { attribute!(@text max "max" "The maximum allowed value."); };
+    (maxlength) => { attribute!(@value maxlength "maxlength" u32, "The maximum length of the value."); };
+    (media) => { attribute!(@text media "media" "The media query this resource applies to."); };
+    (method) => { attribute!(@value method "method" FormMethod, "The http method used to submit this form."); };
+    (min) => { attribute!(@text min "min" "The minimum allowed value."); };
+    (minlength) => { attribute!(@value minlength "minlength" u32, "The minimum length of the value."); };
+    (multiple) => { attribute!(@bool multiple "multiple" "Allow more than one value to be selected."); };
+    (muted) => { attribute!(@bool muted "muted" "Mute the audio."); };
+    (name) => { attribute!(@text name "name" "The name of this element."); };
+    (novalidate) => { attribute!(@bool novalidate "novalidate" "Don't validate this form when submitting it."); };
+    (open) => { attribute!(@bool open "open" "Whether the contents are visible."); };
+    (optimum) => { attribute!(@value optimum "optimum" f64, "The optimal value."); };
+    (pattern) => { attribute!(@text pattern "pattern" "A regular expression the value must match."); };
+    (placeholder) => { attribute!(@text placeholder "placeholder" "A hint shown when the value is empty."); };
+    (poster) => { attribute!(@text poster "poster" "The url of an image to show before the video plays."); };
+    (preload) => { attribute!(@text preload "preload" "How much of the media to load before it is played."); };
+    (readonly) => { attribute!(@bool readonly "readonly" "Prevent the user from editing the value."); };
+    (rel) => { attribute!(@text rel "rel" "The relationship of the linked resource to this document."); };
+    (required) => { attribute!(@bool required "required" "Require a value when submitting the form."); };
+    (reversed) => { attribute!(@bool reversed "reversed" "Number the list in descending order."); };
+    (rows) => { attribute!(@value rows "rows" u32, "The number of visible lines of text."); };
+    (rowspan) => { attribute!(@value rowspan "rowspan" u32, "The number of rows this cell spans."); };
+    (sandbox) => { attribute!(@text sandbox "sandbox" "Restrictions applied to the content of this frame."); };
+    (scope) => { attribute!(@text scope "scope" "The cells this header cell applies to."); };
+    (selected) => { attribute!(@bool selected "selected" "Whether this option is selected."); };
+    (shape) => { attribute!(@text shape "shape" "The shape of this area."); };
+    (size) => { attribute!(@value size "size" u32, "The visible size of this control."); };
+    (sizes) => { attribute!(@text sizes "sizes" "The sizes of the images for different page layouts."); };
+    (span) => { attribute!(@value span "span" u32, "The number of columns spanned."); };
+    (src) => { attribute!(@text src "src" "The url of the embedded resource."); };
+    (srcdoc) => { attribute!(@text srcdoc "srcdoc" "Inline html to embed in this frame."); };
+    (srclang) => { attribute!(@text srclang "srclang" "The language of this text track."); };
+    (srcset) => { attribute!(@text srcset "srcset" "Alternative images for different screens."); };
+    (start) => { attribute!(@value start "start" i32, "The number of the first item in the list."); };
+    (step) => { attribute!(@text step "step" "The granularity of allowed values."); };
+    (target) => { attribute!(@text target "target" "Where to display the linked resource (e.g. `_blank`)."); };
+    (type_) => { attribute!(@text type_ "type" "The mime type of the resource."); };
+    (type_button) => { attribute!(@value type_ "type" ButtonType, "The behavior of this button."); };
+    (type_input) => { attribute!(@value type_ "type" InputType, "The type of this input."); };
+    (type_list) => { attribute!(@text type_ "type" "The numbering type of this list."); };
+    (usemap) => { attribute!(@text usemap "usemap" "The image map to use with this image."); };
+    (value) => { attribute!(@text value "value" "The value of this element."); };
+    (value_number) => { attribute!(@value value "value" f64, "The current value."); };
+    (value_ordinal) => { attribute!(@value value "value" i32, "The ordinal value of this list item."); };
+    (width) => { attribute!(@value width "width" u32, "The width in pixels."); };
+    (wrap) => { attribute!(@text wrap "wrap" "How the text should be wrapped when submitting the form."); };
+}
+
+// global attributes, these apply to all elements
+impl<E: HtmlElement, Message, Command, Key> Element<E, Message, Command, Key> {
+    attribute!(accesskey);
+    attribute!(contenteditable);
+    attribute!(dir);
+    attribute!(draggable);
+    attribute!(hidden);
+    attribute!(id);
+    attribute!(lang);
+    attribute!(role);
+    attribute!(spellcheck);
+    attribute!(tabindex);
+    attribute!(title);
+    attribute!(translate);
+}
+
+/// Generate the builder function, element type, and attribute methods for each element.
+macro_rules! elements {
+    ( $( $tag:literal $func:ident $ty:ident $kind:ident [ $( $attr:ident ),* ]; )* ) => {
+        /// Types representing each html element.
+        pub mod elements {
+            $(
+                #[doc = concat!("The `<", $tag, ">` element.")]
+                #[derive(Debug)]
+                pub enum $ty {}
+            )*
+        }
+
+        $(
+            impl HtmlElement for elements::$ty {
+                const NAME: &'static str = $tag;
+            }
+
+            elements!(@kind $ty $kind);
+
+            #[doc = concat!("Create a `<", $tag, ">` element.")]
+            pub fn $func<Message, Command, Key>() -> Element<elements::$ty, Message, Command, Key> {
+                Element::new()
+            }
+
+            impl<Message, Command, Key> Element<elements::$ty, Message, Command, Key> {
+                $( attribute!($attr); )*
+            }
+        )*
+    };
+    (@kind $ty:ident void) => {};
+    (@kind $ty:ident container) => {
+        impl Container for elements::$ty {}
+    };
+}
+
+elements! {
+    // document metadata
+    "html" html Html container [];
+    "head" head Head container [];
+    "title" title Title container [];
+    "base" base Base void [href, target];
+    "link" link Link void [href, rel, type_, media, hreflang, sizes, crossorigin, as_];
+    "meta" meta Meta void [name, content, charset, http_equiv];
+    "style" style Style container [media];
+    "script" script Script container [src, type_, async_, defer, crossorigin];
+    "noscript" noscript Noscript container [];
+    "template" template Template container [];
+    "body" body Body container [];
+
+    // sections
+    "article" article Article container [];
+    "section" section Section container [];
+    "nav" nav Nav container [];
+    "aside" aside Aside container [];
+    "h1" h1 H1 container [];
+    "h2" h2 H2 container [];
+    "h3" h3 H3 container [];
+    "h4" h4 H4 container [];
+    "h5" h5 H5 container [];
+    "h6" h6 H6 container [];
+    "hgroup" hgroup Hgroup container [];
+    "header" header Header container [];
+    "footer" footer Footer container [];
+    "address" address Address container [];
+    "main" main Main container [];
+
+    // grouping content
+    "p" p P container [];
+    "hr" hr Hr void [];
+    "pre" pre Pre container [];
+    "blockquote" blockquote Blockquote container [cite];
+    "ol" ol Ol container [reversed, start, type_list];
+    "ul" ul Ul container [];
+    "menu" menu Menu container [];
+    "li" li Li container [value_ordinal];
+    "dl" dl Dl container [];
+    "dt" dt Dt container [];
+    "dd" dd Dd container [];
+    "figure" figure Figure container [];
+    "figcaption" figcaption Figcaption container [];
+    "div" div Div container [];
+
+    // text level semantics
+    "a" a A container [href, target, download, rel, hreflang, type_];
+    "em" em Em container [];
+    "strong" strong Strong container [];
+    "small" small Small container [];
+    "s" s S container [];
+    "cite" cite Cite container [];
+    "q" q Q container [cite];
+    "dfn" dfn Dfn container [];
+    "abbr" abbr Abbr container [];
+    "ruby" ruby Ruby container [];
+    "rt" rt Rt container [];
+    "rp" rp Rp container [];
+    "data" data Data container [value];
+    "time" time Time container [datetime];
+    "code" code Code container [];
+    "var" var Var container [];
+    "samp" samp Samp container [];
+    "kbd" kbd Kbd container [];
+    "sub" sub Sub container [];
+    "sup" sup Sup container [];
+    "i" i I container [];
+    "b" b B container [];
+    "u" u U container [];
+    "mark" mark Mark container [];
+    "bdi" bdi Bdi container [];
+    "bdo" bdo Bdo container [];
+    "span" span Span container [];
+    "br" br Br void [];
+    "wbr" wbr Wbr void [];
+
+    // edits
+    "ins" ins Ins container [cite, datetime];
+    "del" del Del container [cite, datetime];
+
+    // embedded content
+    "picture" picture Picture container [];
+    "source" source Source void [src, type_, srcset, sizes, media];
+    "img" img Img void [src, alt, width, height, srcset, sizes, loading, crossorigin, usemap];
+    "iframe" iframe Iframe container [src, srcdoc, name, width, height, allow, sandbox, loading];
+    "embed" embed Embed void [src, type_, width, height];
+    "object" object Object container [data, type_, name, width, height, form];
+    "param" param Param void [name, value];
+    "video" video Video container [src, controls, autoplay, loop_, muted, preload, poster, width, height, crossorigin];
+    "audio" audio Audio container [src, controls, autoplay, loop_, muted, preload, crossorigin];
+    "track" track Track void [src, kind, srclang, label, default];
+    "map" map Map container [name];
+    "area" area Area void [alt, coords, shape, href, target, download, rel];
+    "canvas" canvas Canvas container [width, height];
+
+    // tabular data
+    "table" table Table container [];
+    "caption" caption Caption container [];
+    "colgroup" colgroup Colgroup container [span];
+    "col" col Col void [span];
+    "tbody" tbody Tbody container [];
+    "thead" thead Thead container [];
+    "tfoot" tfoot Tfoot container [];
+    "tr" tr Tr container [];
+    "td" td Td container [colspan, rowspan, headers];
+    "th" th Th container [colspan, rowspan, headers, scope, abbr];
+
+    // forms
+    "form" form Form container [action, method, enctype, novalidate, target, name, autocomplete];
+    "label" label Label container [for_, form];
+    "input" input Input void [
+        type_input, name, value, checked, disabled, autofocus, placeholder, required, readonly,
+        min, max, step, minlength, maxlength, pattern, size, multiple, accept, autocomplete, list,
+        form
+    ];
+    "button" button Button container [type_button, name, value, disabled, autofocus, form];
+    "select" select Select container [name, value, disabled, multiple, required, autofocus, size, form];
+    "datalist" datalist Datalist container [];
+    "optgroup" optgroup Optgroup container [disabled, label];
+    "option" option Option container [value, selected, disabled, label];
+    "textarea" textarea Textarea container [
+        name, value, placeholder, rows, cols, disabled, readonly, required, autofocus, minlength,
+        maxlength, wrap, form
+    ];
+    "output" output Output container [for_, name, form];
+    "progress" progress Progress container [value_number, max];
+    "meter" meter Meter container [value_number, min, max, low, high, optimum];
+    "fieldset" fieldset Fieldset container [disabled, name, form];
+    "legend" legend Legend container [];
+
+    // interactive elements
+    "details" details Details container [open];
+    "summary" summary Summary container [];
+    "dialog" dialog Dialog container [open];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ssr;
+    use crate::vdom::DomIter;
+
+    fn render(dom: impl Into<Dom>) -> String {
+        ssr::render(dom.into().dom_iter())
+    }
+
+    #[test]
+    fn same_as_dom() {
+        let typed = section()
+            .class("main")
+            .push(input()
+                .id("toggle-all")
+                .type_(InputType::Checkbox)
+                .checked(true)
+                .disabled(false)
+                .tabindex(-1)
+            )
+            .push(label().for_("toggle-all").push("Mark all"));
+
+        let dom: Dom = Dom::elem("section")
+            .class("main")
+            .push(Dom::elem("input")
+                .attr("id", "toggle-all")
+                .attr("type", "checkbox")
+                .attr("checked", "true")
+                .attr("tabindex", "-1")
+            )
+            .push(Dom::elem("label").attr("for", "toggle-all").push("Mark all"));
+
+        assert_eq!(render(typed), render(dom));
+    }
+
+    #[test]
+    fn attribute_values() {
+        assert_eq!(
+            render(form().method(FormMethod::Post).action("/submit")),
+            r#"<form method="post" action="/submit"></form>"#,
+        );
+        assert_eq!(
+            render(meter().value(0.5).min("0").max("1")),
+            r#"<meter value="0.5" min="0" max="1"></meter>"#,
+        );
+        assert_eq!(
+            render(button().type_(ButtonType::Button).push("ok")),
+            r#"<button type="button">ok</button>"#,
+        );
+        assert_eq!(
+            render(div().draggable(false).attr("data-id", "1")),
+            r#"<div draggable="false" data-id="1"></div>"#,
+        );
+    }
+}
@@ -4,17 +4,82 @@
 //! Modular wasm application framework.
 
 pub mod patch;
+pub mod backend;
 pub mod diff;
 pub mod dom;
 pub mod vdom;
 pub mod app;
 pub mod route;
 pub mod component;
+pub mod ssr;
+pub mod html;
 
 pub use diff::diff;
 pub use app::AppBuilder;
 pub use component::ComponentBuilder;
 
+/// Build a [`Dom`] tree from html-like markup.
+///
+/// ```
+/// use euca::html;
+/// use euca::dom::Dom;
+///
+/// #[derive(Clone, PartialEq, Debug)]
+/// enum Message {
+///     Toggle(usize),
+///     Clear,
+/// }
+///
+/// let items = vec!["one", "two"];
+/// let dom: Dom<Message, (), usize> = html! {
+///     <div class="container">
+///         <ul>
+///             {..items.iter().enumerate().map(|(i, item)| html! {
+///                 <li key={i} onclick=Message::Toggle(i)>{*item}</li>
+///             })}
+///         </ul>
+///         <input type="checkbox" checked={items.is_empty().to_string()}>
+///         <button onclick=Message::Clear>"Clear"</button>
+///     </div>
+/// };
+/// ```
+///
+/// The supported syntax is:
+///
+/// - Elements, e.g. `<div>...</div>`. [Void elements] like `<input>` have no closing tag and may
+///   optionally be written as `<input />`. Other elements must be closed with a matching tag.
+/// - SVG and MathML, e.g. `<svg><circle r=5 /></svg>`. Elements inside `<svg>` and `<math>` are
+///   created in those namespaces, have no void elements, and may always be self closing.
+/// - Attributes, e.g. `class="x"`, `tabindex=1`, or `value={expr}` where `expr` is anything that
+///   can be converted into a `String`. Attributes without a value, e.g. `disabled`, are set to
+///   `"true"`. Namespaced attributes like `xlink:href="#id"` are supported.
+/// - Events, e.g. `onclick=Message::Clear` or `onclick={expr}`, dispatch the given message when
+///   triggered. Use `on:input={handler}` to attach a [`Handler`].
+/// - Properties, e.g. `prop:indeterminate={expr}`, set a property on the DOM element instead of
+///   an attribute (see [`Dom::property`]).
+/// - Keys, e.g. `key={expr}`.
+/// - Text, e.g. `"text"`.
+/// - Expressions, e.g. `{expr}`, where `expr` is anything that can be converted into a [`Dom`].
+/// - Iterators, e.g. `{..iter}`, where `iter` produces [`Dom`] nodes.
+/// - Components, e.g. `<Counter msg={Message::Init} />`, where `Counter` is the path to a function
+///   that creates the component (see [`Dom::component`]). Component names must start with an
+///   uppercase letter or contain `::`.
+///
+/// More than one top level node produces a [`DomVec`]. A `DomVec` used as an expression in a
+/// child position is inserted as a [`Dom::fragment`], without a containing element.
+///
+/// [`Dom`]: dom/struct.Dom.html
+/// [`DomVec`]: dom/struct.DomVec.html
+/// [`Dom::fragment`]: dom/struct.Dom.html#method.fragment
+/// [`Handler`]: dom/enum.Handler.html
+/// [`Dom::component`]: dom/struct.Dom.html#method.component
+/// [`Dom::property`]: dom/struct.Dom.html#method.property
+/// [Void elements]: https://html.spec.whatwg.org/multipage/syntax.html#void-elements
+pub use euca_macros::html;
+
+// allow the html! macro to refer to this crate by name from within it
+extern crate self as euca;
+
 pub use app::model;
 
 #[doc(hidden)]
@@ -2,21 +2,30 @@
 //!
 //! This module implements the [`Patch`] and [`PatchSet`] types which provide the tools necessary
 //! to describe a set of changes to a dom tree. Also provided is the [`PatchSet::apply`] method
-//! which will apply a patch set to the browser's dom tree creating elements as the children of the
-//! given parent element and dispatching events using the given dispatcher.
+//! which will apply a patch set to a dom tree via a [`Backend`] (the browser's dom by default)
+//! creating elements as the children of the given parent element and dispatching events using the
+//! given dispatcher.
 //!
 //! [`Patch`]: enum.Patch.html
 //! [`PatchSet`]: struct.PatchSet.html
 //! [`PatchSet::apply`]: struct.PatchSet.html#method.apply
+//! [`Backend`]: ../backend/trait.Backend.html
 
 use std::fmt;
 use std::collections::hash_map::HashMap;
+use std::collections::VecDeque;
 use std::hash::Hash;
-use wasm_bindgen::prelude::*;
-use wasm_bindgen::JsCast;
 use crate::vdom::EventHandler;
+use crate::vdom::PropertyValue;
+use crate::vdom::ListenerOptions;
+use crate::vdom::Namespace;
+use crate::vdom::PortalTarget;
+use crate::vdom::Hook;
+use crate::vdom::Lifecycle;
+use crate::vdom::attribute_namespace;
 use crate::vdom::WebItem;
 use crate::vdom::Storage;
+use crate::backend::{Backend, Web};
 use crate::app::{Dispatcher, SideEffect};
 use crate::component::Component;
 use log::warn;
@@ -32,26 +41,39 @@ use log::warn;
 /// [`web_sys::Element`]: https://rustwasm.github.io/wasm-bindgen/api/web_sys/struct.Element.html
 /// [`web_sys::Text`]: https://rustwasm.github.io/wasm-bindgen/api/web_sys/struct.Text.html
 /// [`Closure`]: https://rustwasm.github.io/wasm-bindgen/api/wasm_bindgen/closure/struct.Closure.html
-pub enum Patch<'a, Message, Command, K> {
+pub enum Patch<'a, Message, Command, K, B: Backend = Web> {
     /// Remove an element.
-    RemoveElement(&'a mut WebItem<Message>),
+    RemoveElement(&'a mut WebItem<Message, B>),
     /// Create an element of the given type.
     CreateElement {
         /// The name/type of element that will be created.
         element: &'a str,
+        /// The namespace of the element, inherited from its parent if `None`.
+        namespace: Option<Namespace>,
     },
     /// Reference a keyed thing.
     ReferenceKey(&'a K),
     /// Copy and element from the old dom tree to the new dom tree.
-    CopyElement(&'a mut WebItem<Message>),
+    CopyElement(&'a mut WebItem<Message, B>),
     /// Move the given element from it's old position in the dom to a new position.
-    MoveElement(&'a mut WebItem<Message>),
+    MoveElement(&'a mut WebItem<Message, B>),
+    /// Start a fragment, the nodes created in it are added to the parent of the fragment.
+    CreateFragment,
+    /// Copy a fragment from the old dom tree to the new dom tree.
+    CopyFragment(&'a mut WebItem<Message, B>),
+    /// Move a fragment, and all of the nodes in it, from it's old position in the dom to a new
+    /// position.
+    MoveFragment(&'a mut WebItem<Message, B>),
+    /// Start a portal, the nodes created in it are added to the given target element.
+    CreatePortal(&'a PortalTarget),
+    /// Copy a portal from the old dom tree to the new dom tree.
+    CopyPortal(&'a mut WebItem<Message, B>),
     /// Remove a text element.
-    RemoveText(&'a mut WebItem<Message>),
+    RemoveText(&'a mut WebItem<Message, B>),
     /// Replace the value of a text element.
     ReplaceText {
         /// Called once to take an existing text node from the old virtual dom.
-        take: &'a mut WebItem<Message>,
+        take: &'a mut WebItem<Message, B>,
         /// The replacement text for the existing text node.
         text: &'a str,
     },
@@ -61,7 +83,7 @@ pub enum Patch<'a, Message, Command, K> {
         text: &'a str,
     },
     /// Copy the reference we have to the text element to the new dom.
-    CopyText(&'a mut WebItem<Message>),
+    CopyText(&'a mut WebItem<Message, B>),
     /// Update this element by setting innerHTML.
     SetInnerHtml(&'a str),
     /// Remove all of the children of the parent of this element.
@@ -74,25 +96,25 @@ pub enum Patch<'a, Message, Command, K> {
         create: fn(Dispatcher<Message, Command>) -> Box<dyn Component<Message>>,
     },
     /// Copy a component from the old dom to the new one.
-    CopyComponent(&'a mut WebItem<Message>),
+    CopyComponent(&'a mut WebItem<Message, B>),
     /// Move a component from the old dom to the new one.
-    MoveComponent(&'a mut WebItem<Message>),
+    MoveComponent(&'a mut WebItem<Message, B>),
     /// Send a message to a component.
     UpdateComponent {
         /// Called once to take an existing component node from the old virtual dom.
-        take: &'a mut WebItem<Message>,
+        take: &'a mut WebItem<Message, B>,
         /// The message to send.
         msg: Message,
     },
     /// Move a component and Send a message to it.
     MupdateComponent {
         /// The storage for this component.
-        take: &'a mut WebItem<Message>,
+        take: &'a mut WebItem<Message, B>,
         /// The message to send.
         msg: Message,
     },
     /// Remove a component.
-    RemoveComponent(&'a mut WebItem<Message>),
+    RemoveComponent(&'a mut WebItem<Message, B>),
     /// Set an attribute.
     SetAttribute {
         /// The name of the attribute to set.
@@ -102,36 +124,92 @@ pub enum Patch<'a, Message, Command, K> {
     },
     /// Remove an attribute.
     RemoveAttribute(&'a str),
+    /// Add a class.
+    AddClass(&'a str),
+    /// Remove a class.
+    RemoveClass(&'a str),
+    /// Set an inline style property.
+    SetStyle {
+        /// The css property to set.
+        property: &'a str,
+        /// The value of the property.
+        value: &'a str,
+    },
+    /// Remove an inline style property.
+    RemoveStyle(&'a str),
+    /// Set a property.
+    SetProperty {
+        /// The name of the property to set.
+        name: &'a str,
+        /// The value of the property.
+        value: &'a PropertyValue,
+    },
+    /// Remove a property.
+    RemoveProperty(&'a str),
+    /// Record the element in the ref table under the given name.
+    SetRef(&'a str),
+    /// Remove the element from the ref table.
+    RemoveRef(&'a str),
+    /// Remove a removed element from the ref table.
+    UnmountRef {
+        /// The name the element was recorded under.
+        name: &'a str,
+        /// The removed element.
+        element: B::Element,
+    },
+    /// Run a mount or update hook with the element once the patch set has been applied.
+    RunHook {
+        /// When the hook runs.
+        lifecycle: Lifecycle,
+        /// The hook to run.
+        hook: Hook<Message, Command>,
+    },
+    /// Run an unmount hook with an element that is about to be removed.
+    RunUnmountHook {
+        /// The hook to run.
+        hook: Hook<Message, Command>,
+        /// The removed element.
+        element: B::Element,
+    },
     /// Add an event listener.
     AddListener {
         /// The trigger for the event to watch.
         trigger: &'a str,
         /// A handler for the event.
         handler: EventHandler<'a, Message>,
+        /// Options for the listener.
+        options: ListenerOptions,
     },
     /// Copy an event listener from the old dom tree to the new dom tree.
-    CopyListener(&'a mut WebItem<Message>),
+    CopyListener(&'a mut WebItem<Message, B>),
     /// Remove an event listener.
     RemoveListener {
         /// The trigger for the event to remove.
         trigger: &'a str,
         /// Called once to take an existing closure from the old virtual dom.
-        take: &'a mut WebItem<Message>,
+        take: &'a mut WebItem<Message, B>,
+        /// The options the listener was added with.
+        options: ListenerOptions,
     },
     /// This marks the end of operations on the last node.
     Up,
 }
 
-impl<'a, Message, Command, K: fmt::Debug> fmt::Debug for Patch<'a, Message, Command, K> where
+impl<'a, Message, Command, K: fmt::Debug, B: Backend> fmt::Debug for Patch<'a, Message, Command, K, B> where
     Message: fmt::Debug
 {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             Patch::RemoveElement(e) => write!(f, "RemoveElement({:?})", e),
-            Patch::CreateElement { element: s } => write!(f, "CreateElement {{ element: {:?} }}", s),
+            Patch::CreateElement { element: s, namespace: n } => write!(f, "CreateElement {{ element: {:?}, namespace: {:?} }}", s, n),
             Patch::ReferenceKey(k) => write!(f, "ReferenceKey({:?})", k),
             Patch::CopyElement(e) => write!(f, "CopyElement({:?})", e),
             Patch::MoveElement(k) => write!(f, "MoveElement({:?})", k),
+            Patch::CreateFragment => write!(f, "CreateFragment"),
+            Patch::CopyFragment(i) => write!(f, "CopyFragment({:?})", i),
+            Patch::MoveFragment(i) => write!(f, "MoveFragment({:?})", i),
+            Patch::CreatePortal(t) => write!(f, "CreatePortal({:?})", t),
+            Patch::CopyPortal(i) => write!(f, "CopyPortal({:?})", i),
             Patch::RemoveText(wt) => write!(f, "RemoveText({:?})", wt),
             Patch::ReplaceText { take: wt, text: t }  => write!(f, "ReplaceText {{ take: {:?}, text: {:?} }}", wt, t),
             Patch::CreateText { text: t } => write!(f, "CreateText {{ text: {:?} }}", t),
@@ -146,127 +224,37 @@ impl<'a, Message, Command, K: fmt::Debug> fmt::Debug for Patch<'a, Message, Comm
             Patch::RemoveComponent(c) => write!(f, "RemoveComponent({:?})", c),
             Patch::SetAttribute { name: n, value: v } => write!(f, "SetAttribute {{ name: {:?}, value: {:?} }}", n, v),
             Patch::RemoveAttribute(s) => write!(f, "RemoveAttribute({:?})", s),
-            Patch::AddListener { trigger: t, handler: h } => write!(f, "AddListener {{ trigger: {:?}, handler: {:?} }}", t, h),
+            Patch::AddClass(c) => write!(f, "AddClass({:?})", c),
+            Patch::RemoveClass(c) => write!(f, "RemoveClass({:?})", c),
+            Patch::SetStyle { property: p, value: v } => write!(f, "SetStyle {{ property: {:?}, value: {:?} }}", p, v),
+            Patch::RemoveStyle(p) => write!(f, "RemoveStyle({:?})", p),
+            Patch::SetProperty { name: n, value: v } => write!(f, "SetProperty {{ name: {:?}, value: {:?} }}", n, v),
+            Patch::RemoveProperty(n) => write!(f, "RemoveProperty({:?})", n),
+            Patch::SetRef(n) => write!(f, "SetRef({:?})", n),
+            Patch::RemoveRef(n) => write!(f, "RemoveRef({:?})", n),
+            Patch::UnmountRef { name: n, element: e } => write!(f, "UnmountRef {{ name: {:?}, element: {:?} }}", n, e),
+            Patch::RunHook { lifecycle: l, hook: _ } => write!(f, "RunHook {{ lifecycle: {:?}, hook: _ }}", l),
+            Patch::RunUnmountHook { hook: _, element: e } => write!(f, "RunUnmountHook {{ hook: _, element: {:?} }}", e),
+            Patch::AddListener { trigger: t, handler: h, options: o } => write!(f, "AddListener {{ trigger: {:?}, handler: {:?}, options: {:?} }}", t, h, o),
             Patch::CopyListener(l) => write!(f, "CopyListener({:?})", l),
-            Patch::RemoveListener { trigger: t, take: l } => write!(f, "RemoveListener {{ trigger: {:?}), take: {:?} }}", t, l),
+            Patch::RemoveListener { trigger: t, take: l, options: o } => write!(f, "RemoveListener {{ trigger: {:?}), take: {:?}, options: {:?} }}", t, l, o),
             Patch::Up => write!(f, "Up"),
         }
     }
 }
 
-macro_rules! attribute_setter_match_arm {
-    ( $node:ident, $setter:ident, $attr:literal, $value:ident, [ $node_type1:path $(, $node_type:path )* ] ) => {
-        {
-            if let Some(elem) = $node.dyn_ref::<$node_type1>() {
-                if let Ok(value) = $value.parse() {
-                    elem.$setter(value);
-                }
-                else if $value == $attr {
-                    elem.$setter(true);
-                }
-                else {
-                    warn!("non boolean value '{}' set for '{}' attribute", $value, $attr);
-                    $node.dyn_ref::<web_sys::Element>()
-                        .expect("attributes can only be added to elements")
-                        .set_attribute($attr, $value)
-                        .expect("failed to set attribute");
-                }
-            }
-            $(else if let Some(elem) = $node.dyn_ref::<$node_type>() {
-                if let Ok(value) = $value.parse() {
-                    elem.$setter(value);
-                }
-                else if $value == $attr {
-                    elem.$setter(true);
-                }
-                else {
-                    warn!("non boolean value '{}' set for '{}' attribute", $value, $attr);
-                    $node.dyn_ref::<web_sys::Element>()
-                        .expect("attributes can only be added to elements")
-                        .set_attribute($attr, $value)
-                        .expect("failed to set attribute");
-                }
-            })*
-            else {
-                let elem = $node.dyn_ref::<web_sys::Element>()
-                    .expect("attributes can only be added to elements");
-                elem.set_attribute($attr, $value)
-                    .expect("failed to set attribute");
-                warn!("attribute '{}' set for '{}' element, expected one of {}",
-                    $attr, elem.node_name(), stringify!($($node_type),*));
-            }
-        }
-    };
-}
-
-macro_rules! attribute_setter {
-    ( $node:ident, $name:ident, $value:ident, [ $( $attr:literal => $setter:ident [ $( $node_type:path ,)* ] ,)* ] ) => {
-        attribute_setter!($node, $name, $value, [ $( $attr => $setter [ $( $node_type ),* ] ),* ] )
-    };
-    ( $node:ident, $name:ident, $value:ident, [ $( $attr:literal => $setter:ident [ $( $node_type:path ),* ] ),* ] ) => {
-        match $name {
-            $( $attr => { attribute_setter_match_arm!($node, $setter, $attr, $value, [ $($node_type),* ]) } )*
-            _ => {
-                attribute_setter!($node, $name, $value);
-            }
-        }
-    };
-    ( $node:ident, $name:ident, $value:ident ) => {
-        $node.dyn_ref::<web_sys::Element>()
-            .expect("attributes can only be added to elements")
-            .set_attribute($name, $value)
-            .expect("failed to set attribute");
-    };
-}
-
-macro_rules! attribute_unsetter_match_arm {
-    ( $node:ident, $setter:ident, $attr:literal, [ $node_type1:path $(, $node_type:path )* ] ) => {
-        if let Some(elem) = $node.dyn_ref::<$node_type1>() {
-            elem.$setter(false);
-        }
-        $(else if let Some(elem) = $node.dyn_ref::<$node_type>() {
-            elem.$setter(false);
-        })*
-        else {
-            let elem = $node.dyn_ref::<web_sys::Element>()
-                .expect("attributes can only be removed from elements");
-            elem.remove_attribute($attr)
-                .expect("failed to set attribute");
-            warn!("attribute '{}' removed for '{}' element, expected one of {}",
-                $attr, elem.node_name(), stringify!($($node_type),*));
-        }
-    };
-}
-
-macro_rules! attribute_unsetter {
-    ( $node:ident, $name:ident, [ $( $attr:literal => $setter:ident [ $( $node_type:path ,)* ] ,)* ] ) => {
-        attribute_unsetter!($node, $name, [ $( $attr => $setter [ $( $node_type ),* ] ),* ] )
-    };
-    ( $node:ident, $name:ident, [ $( $attr:literal => $setter:ident [ $( $node_type:path ),* ] ),* ] ) => {
-        match $name {
-            $( $attr => { attribute_unsetter_match_arm!($node, $setter, $attr, [ $($node_type),* ]) } )*
-            _ => {
-                $node.dyn_ref::<web_sys::Element>()
-                    .expect("attributes can only be removed from elements")
-                    .remove_attribute($name)
-                    .expect("failed to remove attribute");
-            }
-        }
-    };
-}
-
 /// A series of [`Patch`]es to apply to the dom.
 ///
 /// [`Patch`]: enum.Patch.html
 #[derive(Default, Debug)]
-pub struct PatchSet<'a, Message, Command, K: Eq + Hash> {
+pub struct PatchSet<'a, Message, Command, K: Eq + Hash, B: Backend = Web> {
     /// The patches in this patch set.
-    pub patches: Vec<Patch<'a, Message, Command, K>>,
+    pub patches: Vec<Patch<'a, Message, Command, K, B>>,
     /// Mini patch sets for keyed nodes.
-    pub keyed: HashMap<&'a K, Vec<Patch<'a, Message, Command, K>>>,
+    pub keyed: HashMap<&'a K, Vec<Patch<'a, Message, Command, K, B>>>,
 }
 
-impl<'a, Message, Command, K: Eq + Hash> PatchSet<'a, Message, Command, K> {
+impl<'a, Message, Command, K: Eq + Hash, B: Backend> PatchSet<'a, Message, Command, K, B> {
     /// Create an empty PatchSet.
     pub fn new() -> Self {
         PatchSet {
@@ -276,7 +264,7 @@ impl<'a, Message, Command, K: Eq + Hash> PatchSet<'a, Message, Command, K> {
     }
 
     /// Push a patch on to the end of the PatchSet.
-    pub fn push(&mut self, patch: Patch<'a, Message, Command, K>) {
+    pub fn push(&mut self, patch: Patch<'a, Message, Command, K, B>) {
         self.patches.push(patch)
     }
 
@@ -296,7 +284,12 @@ impl<'a, Message, Command, K: Eq + Hash> PatchSet<'a, Message, Command, K> {
 
     /// Return the length of the PatchSet.
     pub fn len(&self) -> usize {
-        return self.patches.len()
+        self.patches.len()
+    }
+
+    /// Return true if the PatchSet has no patches.
+    pub fn is_empty(&self) -> bool {
+        self.patches.is_empty()
     }
 
     /// Return true if applying this PatchSet won't actually alter the browser's dom representation
@@ -310,57 +303,116 @@ impl<'a, Message, Command, K: Eq + Hash> PatchSet<'a, Message, Command, K> {
             // these patches just copy stuff into the new virtual dom tree, thus if we just keep
             // the old dom tree, the end result is the same
             CopyElement(_) | CopyListener(_) | ReferenceKey(_)
-            | CopyText(_) | CopyComponent(_) | Up
+            | CopyText(_) | CopyComponent(_) | CopyFragment(_) | CopyPortal(_) | Up
             => true,
             // these patches change the dom
             RemoveElement(_) | CreateElement { .. }
             | MoveElement(_)
+            | CreateFragment | MoveFragment(_) | CreatePortal(_)
             | CreateComponent { .. } | UpdateComponent { .. }
             | MoveComponent { .. } | MupdateComponent { .. }
             | RemoveComponent(_)
             | SetInnerHtml(_) | UnsetInnerHtml
             | RemoveListener { .. } | AddListener { .. }
             | RemoveAttribute(_) | SetAttribute { .. }
+            | AddClass(_) | RemoveClass(_)
+            | SetStyle { .. } | RemoveStyle(_)
+            | SetProperty { .. } | RemoveProperty(_)
+            | SetRef(_) | RemoveRef(_) | UnmountRef { .. }
+            | RunHook { .. } | RunUnmountHook { .. }
             | RemoveText(_) | CreateText { .. } | ReplaceText { .. }
             => false,
         })
     }
 
+    /// Return true if this PatchSet only creates new nodes, as generated by diffing against an
+    /// empty virtual dom.
+    fn creates_dom(&self) -> bool {
+        use Patch::*;
+
+        self.patches.iter()
+            .chain(self.keyed.values().flatten())
+            .all(|p| match p {
+            ReferenceKey(_) | CreateElement { .. } | CreateFragment | CreatePortal(_)
+            | CreateText { .. } | CreateComponent { .. }
+            | SetInnerHtml(_) | AddListener { .. } | SetAttribute { .. } | AddClass(_)
+            | SetStyle { .. } | SetProperty { .. } | SetRef(_) | RunHook { .. } | Up
+            => true,
+            RemoveElement(_) | CopyElement(_) | MoveElement(_)
+            | CopyFragment(_) | MoveFragment(_) | CopyPortal(_)
+            | RemoveText(_) | ReplaceText { .. } | CopyText(_)
+            | UnsetInnerHtml
+            | CopyComponent(_) | MoveComponent(_)
+            | UpdateComponent { .. } | MupdateComponent { .. } | RemoveComponent(_)
+            | RemoveAttribute(_) | RemoveClass(_) | RemoveStyle(_)
+            | RemoveProperty(_) | RemoveRef(_) | UnmountRef { .. } | RunUnmountHook { .. }
+            | CopyListener(_) | RemoveListener { .. }
+            => false,
+        })
+    }
+
     fn process_patch_list(
-        patches: Vec<Patch<'a, Message, Command, K>>,
-        keyed: &mut HashMap<&'a K, Vec<Patch<'a, Message, Command, K>>>,
+        backend: &B,
+        patches: Vec<Patch<'a, Message, Command, K, B>>,
+        keyed: &mut HashMap<&'a K, Vec<Patch<'a, Message, Command, K, B>>>,
         app: &Dispatcher<Message, Command>,
-        storage: &mut Storage<Message>,
+        storage: &mut Storage<Message, B>,
     )
-    -> Vec<web_sys::Node>
+    -> Vec<B::Node>
     where
         Message: Clone + PartialEq + fmt::Debug + 'static,
         Command: SideEffect<Message> + 'static,
         EventHandler<'a, Message>: Clone,
     {
-        let mut node_stack = NodeStack::new();
-        let mut special_attributes: Vec<(web_sys::Node, &str, &str)> = vec![];
+        let mut node_stack = NodeStack::new(backend);
+        let mut special_attributes = vec![];
 
-        let document = web_sys::window().expect("expected window")
-            .document().expect("expected document");
+        Self::process_patches(backend, patches, keyed, app, storage, &mut node_stack, &mut special_attributes);
+
+        // set special attributes. These must be done last or strange things can happen when
+        // rendering in the browser. I have observed range inputs not properly updating (appears to
+        // be caused by `value` getting set before `max`) and option inputs not getting set.
+        for (element, name, value) in special_attributes.into_iter() {
+            set_attribute(backend, &element, name, value);
+        }
+
+        assert_eq!(node_stack.depth(), 0, "the stack should be empty");
+        node_stack.pop_pending()
+    }
 
+    #[allow(clippy::too_many_arguments)]
+    fn process_patches(
+        backend: &B,
+        patches: Vec<Patch<'a, Message, Command, K, B>>,
+        keyed: &mut HashMap<&'a K, Vec<Patch<'a, Message, Command, K, B>>>,
+        app: &Dispatcher<Message, Command>,
+        storage: &mut Storage<Message, B>,
+        node_stack: &mut NodeStack<'_, B>,
+        special_attributes: &mut Vec<(B::Element, &'a str, &'a str)>,
+    )
+    where
+        Message: Clone + PartialEq + fmt::Debug + 'static,
+        Command: SideEffect<Message> + 'static,
+        EventHandler<'a, Message>: Clone,
+    {
         for p in patches.into_iter() {
             match p {
                 Patch::ReferenceKey(key) => {
+                    // keyed patches are processed in place, keyed nodes that stay in place need
+                    // pending siblings inserted before them just like any other node
                     let patches = keyed.remove(&key)
                         .expect("patches for given key not found");
-                    let nodes = Self::process_patch_list(patches, keyed, app, storage);
-                    for node in nodes {
-                        node_stack.push_child(node);
-                    }
+                    Self::process_patches(backend, patches, keyed, app, storage, node_stack, special_attributes);
                 }
                 Patch::RemoveElement(item) => {
-                    item.take().as_element()
+                    let node = item.take().as_element()
                         .expect("unexpected WebItem, expected element")
-                        .remove();
+                        .clone();
+                    backend.remove(&node.into());
                 }
-                Patch::CreateElement { element } => {
-                    let node = document.create_element(&element).expect("failed to create element");
+                Patch::CreateElement { element, namespace } => {
+                    let namespace = element_namespace(backend, node_stack.last(), element, namespace);
+                    let node = backend.create_element(element, namespace);
                     storage.push(WebItem::Element(node.clone()));
                     node_stack.push_child(node.clone());
                     node_stack.push_parent(node);
@@ -369,10 +421,11 @@ impl<'a, Message, Command, K: Eq + Hash> PatchSet<'a, Message, Command, K> {
                     let item = item.take();
                     let node = item.as_element()
                         .expect("unexpected WebItem, expected element")
-                        .clone();
+                        .clone()
+                        .into();
 
                     storage.push(item);
-                    node_stack.insert_before(Some(&node));
+                    node_stack.keep(&node);
                     node_stack.push_parent(node);
                 }
                 Patch::MoveElement(item) => {
@@ -385,15 +438,39 @@ impl<'a, Message, Command, K: Eq + Hash> PatchSet<'a, Message, Command, K> {
                     node_stack.push_child(node.clone());
                     node_stack.push_parent(node);
                 }
+                Patch::CreateFragment => {
+                    storage.push(WebItem::Fragment);
+                    node_stack.push_fragment(false);
+                }
+                Patch::CopyFragment(item) => {
+                    storage.push(item.take());
+                    node_stack.push_fragment(false);
+                }
+                Patch::MoveFragment(item) => {
+                    storage.push(item.take());
+                    node_stack.push_fragment(true);
+                }
+                Patch::CreatePortal(target) => {
+                    let node = portal_target(backend, target);
+                    storage.push(WebItem::Portal(node.clone()));
+                    node_stack.push_parent(node);
+                }
+                Patch::CopyPortal(item) => {
+                    let item = item.take();
+                    let node = item.as_portal()
+                        .expect("unexpected WebItem, expected portal")
+                        .clone();
+
+                    storage.push(item);
+                    node_stack.push_parent(node);
+                }
                 Patch::RemoveText(item) => {
                     let item = item.take();
                     let node = item.as_text()
-                        .expect("unexpected WebItem, expected text");
+                        .expect("unexpected WebItem, expected text")
+                        .clone();
 
-                    node_stack.last()
-                        .expect("no previous node")
-                        .remove_child(&node)
-                        .expect("failed to remove child node");
+                    backend.remove(&node.into());
                 }
                 Patch::ReplaceText { take: item, text } => {
                     let item = item.take();
@@ -401,14 +478,15 @@ impl<'a, Message, Command, K: Eq + Hash> PatchSet<'a, Message, Command, K> {
                         .expect("unexpected WebItem, expected text")
                         .clone();
 
-                    node.set_data(&text);
+                    backend.set_text(&node, text);
 
+                    let node = node.into();
                     storage.push(item);
-                    node_stack.insert_before(Some(&node));
+                    node_stack.keep(&node);
                     node_stack.push_parent(node);
                 }
                 Patch::CreateText { text } => {
-                    let node = document.create_text_node(&text);
+                    let node = backend.create_text(text);
 
                     storage.push(WebItem::Text(node.clone()));
                     node_stack.push_child(node.clone());
@@ -418,18 +496,19 @@ impl<'a, Message, Command, K: Eq + Hash> PatchSet<'a, Message, Command, K> {
                     let item = item.take();
                     let node = item.as_text()
                         .expect("unexpected WebItem, expected text")
-                        .clone();
+                        .clone()
+                        .into();
 
                     storage.push(item);
-                    node_stack.insert_before(Some(&node));
+                    node_stack.keep(&node);
                     node_stack.push_parent(node);
                 }
                 Patch::SetInnerHtml(html) => {
-                    node_stack.last()
-                        .expect("no previous node")
-                        .dyn_ref::<web_sys::Element>()
-                        .expect("innerHtml requested on non Element node")
-                        .set_inner_html(html);
+                    let node = node_stack.last()
+                        .expect("no previous node");
+                    let element = backend.as_element(node)
+                        .expect("innerHtml requested on non Element node");
+                    backend.set_inner_html(element, html);
                 }
                 Patch::UnsetInnerHtml => {
                     let node = node_stack.last()
@@ -437,158 +516,128 @@ impl<'a, Message, Command, K: Eq + Hash> PatchSet<'a, Message, Command, K> {
 
                     // remove all of the children of this node. These are the nodes created by the
                     // innerHtml value.
-                    while let Some(child) = node.first_child() {
-                        node.remove_child(&child)
-                            .expect("failed to remove innerHtml child node");
-                    }
+                    backend.remove_children(node);
                 }
                 Patch::SetAttribute { name, value } => {
                     let node = node_stack.last().expect("no previous node");
+                    let element = backend.as_element(node)
+                        .expect("attributes can only be added to elements");
                     match name {
                         "autofocus" | "checked" | "disabled" | "draggable" |  "hidden"
                         | "selected" | "spellcheck" | "value"
                         => {
                             // delay setting special attributes until after everything else is done
-                            special_attributes.push((node.clone(), name, value));
+                            special_attributes.push((element.clone(), name, value));
                         }
-                        _ => attribute_setter!(node, name, value),
+                        _ => set_attribute(backend, element, name, value),
                     }
                 }
                 Patch::RemoveAttribute(name) => {
                     let node = node_stack.last().expect("no previous node");
-
-                    // properly handle boolean attributes using special setters
-                    attribute_unsetter!(node, name, [
-                        "autofocus" => set_autofocus [
-                            web_sys::HtmlButtonElement,
-                            web_sys::HtmlInputElement,
-                            web_sys::HtmlSelectElement,
-                            web_sys::HtmlTextAreaElement,
-                        ],
-                        "checked" => set_checked [
-                            web_sys::HtmlInputElement,
-                            web_sys::HtmlMenuItemElement,
-                        ],
-                        "disabled" => set_disabled [
-                            web_sys::HtmlButtonElement,
-                            web_sys::HtmlFieldSetElement,
-                            web_sys::HtmlInputElement,
-                            web_sys::HtmlLinkElement,
-                            web_sys::HtmlMenuItemElement,
-                            web_sys::HtmlOptGroupElement,
-                            web_sys::HtmlOptionElement,
-                            web_sys::HtmlSelectElement,
-                            web_sys::HtmlStyleElement,
-                            web_sys::HtmlTextAreaElement,
-                        ],
-                        "draggable" => set_draggable [
-                            web_sys::HtmlElement,
-                        ],
-                        "hidden" => set_hidden [
-                            web_sys::HtmlElement,
-                        ],
-                        "selected" => set_selected [
-                            web_sys::HtmlOptionElement,
-                        ],
-                        "spellcheck" => set_spellcheck [
-                            web_sys::HtmlElement,
-                        ],
-                    ]);
-                }
-                Patch::AddListener { trigger, handler } => {
-                    let app = app.clone();
-                    let closure = match handler {
-                        EventHandler::Msg(msg) => {
-                            let msg = msg.clone();
-                            Closure::wrap(
-                                Box::new(move |_| {
-                                    app.dispatch(msg.clone());
-                                }) as Box<dyn FnMut(web_sys::Event)>
-                            )
-                        }
-                        EventHandler::Fn(fun) => {
-                            Closure::wrap(
-                                Box::new(move |event| {
-                                    if let Some(msg) = fun(event) {
-                                        app.dispatch(msg);
-                                    }
-                                }) as Box<dyn FnMut(web_sys::Event)>
-                            )
-                        }
-                        EventHandler::FnMsg(msg, fun) => {
-                            let msg = msg.clone();
-                            Closure::wrap(
-                                Box::new(move |event| {
-                                    if let Some(msg) = fun(msg.clone(), event) {
-                                        app.dispatch(msg);
-                                    }
-                                }) as Box<dyn FnMut(web_sys::Event)>
-                            )
-                        }
-                        EventHandler::InputValue(fun) => {
-                            Closure::wrap(
-                                Box::new(move |event: web_sys::Event| {
-                                    let value = match event.target() {
-                                        None => String::new(),
-                                        Some(target) => {
-                                            if let Some(input) = target.dyn_ref::<web_sys::HtmlInputElement>() {
-                                                input.value()
-                                            }
-                                            else if let Some(input) = target.dyn_ref::<web_sys::HtmlTextAreaElement>() {
-                                                input.value()
-                                            }
-                                            else if let Some(input) = target.dyn_ref::<web_sys::HtmlSelectElement>() {
-                                                input.value()
-                                            }
-                                            else {
-                                                String::new()
-                                            }
-                                        }
-                                    };
-                                    if let Some(msg) = fun(value) {
-                                        app.dispatch(msg);
-                                    }
-                                }) as Box<dyn FnMut(web_sys::Event)>
-                            )
-                        }
-                        EventHandler::InputEvent(fun) => {
-                            Closure::wrap(
-                                Box::new(move |event: web_sys::Event| {
-                                    let event = event.dyn_into::<web_sys::InputEvent>().expect_throw("expected web_sys::InputEvent");
-                                    if let Some(msg) = fun(event) {
-                                        app.dispatch(msg);
-                                    }
-                                }) as Box<dyn FnMut(web_sys::Event)>
-                            )
-                        }
-                    };
+                    let element = backend.as_element(node)
+                        .expect("attributes can only be removed from elements");
+                    backend.remove_attribute(element, name);
+                }
+                Patch::AddClass(name) => {
+                    let node = node_stack.last().expect("no previous node");
+                    let element = backend.as_element(node)
+                        .expect("classes can only be added to elements");
+                    backend.add_class(element, name);
+                }
+                Patch::RemoveClass(name) => {
+                    let node = node_stack.last().expect("no previous node");
+                    let element = backend.as_element(node)
+                        .expect("classes can only be removed from elements");
+                    backend.remove_class(element, name);
+                }
+                Patch::SetStyle { property, value } => {
+                    let node = node_stack.last().expect("no previous node");
+                    let element = backend.as_element(node)
+                        .expect("styles can only be set on elements");
+                    backend.set_style(element, property, value);
+                }
+                Patch::RemoveStyle(property) => {
+                    let node = node_stack.last().expect("no previous node");
+                    let element = backend.as_element(node)
+                        .expect("styles can only be removed from elements");
+                    backend.remove_style(element, property);
+                }
+                Patch::SetProperty { name, value } => {
+                    let node = node_stack.last().expect("no previous node");
+                    let element = backend.as_element(node)
+                        .expect("properties can only be set on elements");
+                    backend.set_property(element, name, value);
+                }
+                Patch::RemoveProperty(name) => {
+                    let node = node_stack.last().expect("no previous node");
+                    let element = backend.as_element(node)
+                        .expect("properties can only be removed from elements");
+                    backend.remove_property(element, name);
+                }
+                Patch::SetRef(name) => {
+                    let node = node_stack.last().expect("no previous node");
+                    let element = backend.as_element(node)
+                        .expect("refs can only be set on elements");
+                    app.set_ref(name, element.clone());
+                }
+                Patch::RemoveRef(name) => {
+                    let node = node_stack.last().expect("no previous node");
+                    let element = backend.as_element(node)
+                        .expect("refs can only be removed from elements");
+                    app.remove_ref(name, element);
+                }
+                Patch::UnmountRef { name, element } => {
+                    app.remove_ref(name, &element);
+                }
+                Patch::RunHook { lifecycle, hook } => {
+                    let node = node_stack.last().expect("no previous node");
+                    let element = backend.as_element(node)
+                        .expect("hooks can only be run on elements");
+                    app.queue_hook(backend.bind_hook(lifecycle, hook, element));
+                }
+                Patch::RunUnmountHook { hook, element } => {
+                    // run the hook while the element is still attached, its output is handled
+                    // along with the other hooks
+                    let output = backend.bind_hook(Lifecycle::Unmount, hook, &element)();
+                    app.queue_hook(Box::new(move || output));
+                }
+                Patch::AddListener { trigger, handler, options } => {
                     let node = node_stack.last().expect("no previous node");
-                    (node.as_ref() as &web_sys::EventTarget)
-                        .add_event_listener_with_callback(&trigger, closure.as_ref().unchecked_ref())
-                        .expect("failed to add event listener");
-                    storage.push(WebItem::Closure(closure));
+                    let element = backend.as_element(node)
+                        .expect("listeners can only be added to elements");
+                    let listener = backend.add_listener(element, trigger, handler, options, app);
+                    storage.push(WebItem::Closure(listener));
                 }
                 Patch::CopyListener(item) => {
                     storage.push(item.take());
                 }
-                Patch::RemoveListener { trigger, take: item } => {
+                Patch::RemoveListener { trigger, take: item, options } => {
                     let item = item.take();
-                    let closure = item.as_closure()
-                        .expect("unexpected WebItem, expected closure")
-                        .as_ref().unchecked_ref();
+                    let listener = item.as_closure()
+                        .expect("unexpected WebItem, expected closure");
 
                     let node = node_stack.last().expect("no previous node");
-                    (node.as_ref() as &web_sys::EventTarget)
-                        .remove_event_listener_with_callback(&trigger, closure)
-                        .expect("failed to remove event listener");
+                    let element = backend.as_element(node)
+                        .expect("listeners can only be removed from elements");
+                    backend.remove_listener(element, trigger, listener, options);
                 }
                 Patch::CreateComponent { msg, create } => {
                     let mut component = create(app.clone());
-                    for n in component.pending().into_iter() {
-                        node_stack.push_child(n);
-                    }
                     let node = component.node().expect("empty component?");
-                    node_stack.push_parent(node);
+                    match backend.component_node(node) {
+                        Some(node) => {
+                            for n in component.pending().into_iter().filter_map(|n| backend.component_node(n)) {
+                                node_stack.push_child(n);
+                            }
+                            node_stack.push_parent(node);
+                        }
+                        None => node_stack.push_unhosted(),
+                    }
+
+                    // the component's mount hooks run with ours, once its nodes are attached
+                    if let Some(hooks) = component.pending_hooks() {
+                        app.queue_hook(Box::new(move || { hooks(); (None, None) }));
+                    }
 
                     component.dispatch(msg);
                     storage.push(WebItem::Component(component));
@@ -600,10 +649,8 @@ impl<'a, Message, Command, K: Eq + Hash> PatchSet<'a, Message, Command, K> {
 
                     component.dispatch(msg);
 
-                    let node = component.node().expect("empty component?");
+                    node_stack.keep_component(component);
                     storage.push(item);
-                    node_stack.insert_before(Some(&node));
-                    node_stack.push_parent(node);
                 }
                 Patch::MupdateComponent { take: item, msg } => {
                     let item = item.take();
@@ -612,11 +659,7 @@ impl<'a, Message, Command, K: Eq + Hash> PatchSet<'a, Message, Command, K> {
 
                     component.dispatch(msg);
 
-                    for n in component.nodes().into_iter() {
-                        node_stack.push_child(n);
-                    }
-                    let node = component.node().expect("empty component?");
-                    node_stack.push_parent(node);
+                    node_stack.move_component(component);
                     storage.push(item);
                 }
                 Patch::CopyComponent(item) => {
@@ -624,22 +667,15 @@ impl<'a, Message, Command, K: Eq + Hash> PatchSet<'a, Message, Command, K> {
                     let component = item.as_component()
                         .expect("unexpected WebItem, expected component");
 
-                    let node = component.node().expect("empty component?");
-
+                    node_stack.keep_component(component);
                     storage.push(item);
-                    node_stack.insert_before(Some(&node));
-                    node_stack.push_parent(node);
                 }
                 Patch::MoveComponent(item) => {
                     let item = item.take();
                     let component = item.as_component()
                         .expect("unexpected WebItem, expected component");
 
-                    for n in component.nodes().into_iter() {
-                        node_stack.push_child(n);
-                    }
-                    let node = component.node().expect("empty component?");
-                    node_stack.push_parent(node);
+                    node_stack.move_component(component);
                     storage.push(item);
                 }
                 Patch::RemoveComponent(item) => {
@@ -655,73 +691,6 @@ impl<'a, Message, Command, K: Eq + Hash> PatchSet<'a, Message, Command, K> {
                 }
             }
         }
-
-        // set special attributes. These must be done last or strange things can happen when
-        // rendering in the browser. I have observed range inputs not properly updating (appears to
-        // be caused by `value` getting set before `max`) and option inputs not getting set.
-        for (node, name, value) in special_attributes.into_iter() {
-            let mut set_value = false;
-
-            // handle the "value" attribute for non boolean values
-            if name == "value" {
-                set_value = true;
-                if let Some(input) = node.dyn_ref::<web_sys::HtmlInputElement>() {
-                    input.set_value(value);
-                }
-                else if let Some(input) = node.dyn_ref::<web_sys::HtmlTextAreaElement>() {
-                    input.set_value(value);
-                }
-                else if let Some(input) = node.dyn_ref::<web_sys::HtmlSelectElement>() {
-                    input.set_value(value);
-                }
-                else {
-                    set_value = false;
-                }
-            }
-
-            if !set_value {
-                // properly handle boolean attributes using special setters
-                attribute_setter!(node, name, value, [
-                    "autofocus" => set_autofocus [
-                        web_sys::HtmlButtonElement,
-                        web_sys::HtmlInputElement,
-                        web_sys::HtmlSelectElement,
-                        web_sys::HtmlTextAreaElement,
-                    ],
-                    "checked" => set_checked [
-                        web_sys::HtmlInputElement,
-                        web_sys::HtmlMenuItemElement,
-                    ],
-                    "disabled" => set_disabled [
-                        web_sys::HtmlButtonElement,
-                        web_sys::HtmlFieldSetElement,
-                        web_sys::HtmlInputElement,
-                        web_sys::HtmlLinkElement,
-                        web_sys::HtmlMenuItemElement,
-                        web_sys::HtmlOptGroupElement,
-                        web_sys::HtmlOptionElement,
-                        web_sys::HtmlSelectElement,
-                        web_sys::HtmlStyleElement,
-                        web_sys::HtmlTextAreaElement,
-                    ],
-                    "draggable" => set_draggable [
-                        web_sys::HtmlElement,
-                    ],
-                    "hidden" => set_hidden [
-                        web_sys::HtmlElement,
-                    ],
-                    "selected" => set_selected [
-                        web_sys::HtmlOptionElement,
-                    ],
-                    "spellcheck" => set_spellcheck [
-                        web_sys::HtmlElement,
-                    ],
-                ]);
-            }
-        }
-
-        assert_eq!(node_stack.depth(), 0, "the stack should be empty");
-        node_stack.pop_pending()
     }
 
     /// Prep the given PatchSet by creating any elements in the set and placing them in Storage.
@@ -729,7 +698,19 @@ impl<'a, Message, Command, K: Eq + Hash> PatchSet<'a, Message, Command, K> {
     /// will be dispatched via the given [`Dispatch`]er.
     ///
     /// [`Dispatch`]: ../app/trait.Dispatch.html
-    pub fn prepare(self, app: &Dispatcher<Message, Command>) -> (Storage<Message>, Vec<web_sys::Node>) where
+    pub fn prepare(self, app: &Dispatcher<Message, Command>) -> (Storage<Message, B>, Vec<B::Node>) where
+        Message: Clone + PartialEq + fmt::Debug + 'static,
+        Command: SideEffect<Message> + fmt::Debug + 'static,
+        EventHandler<'a, Message>: Clone,
+        B: Default,
+    {
+        self.prepare_with(&B::default(), app)
+    }
+
+    /// Prep the given PatchSet like [`prepare`], creating elements with the given backend.
+    ///
+    /// [`prepare`]: #method.prepare
+    pub fn prepare_with(self, backend: &B, app: &Dispatcher<Message, Command>) -> (Storage<Message, B>, Vec<B::Node>) where
         Message: Clone + PartialEq + fmt::Debug + 'static,
         Command: SideEffect<Message> + fmt::Debug + 'static,
         EventHandler<'a, Message>: Clone,
@@ -737,99 +718,459 @@ impl<'a, Message, Command, K: Eq + Hash> PatchSet<'a, Message, Command, K> {
         let mut storage = vec![];
         let PatchSet { patches, mut keyed } = self;
 
-        let nodes = Self::process_patch_list(patches, &mut keyed, app, &mut storage);
+        let nodes = Self::process_patch_list(backend, patches, &mut keyed, app, &mut storage);
         (storage, nodes)
     }
 
     /// Apply the given PatchSet creating any elements under the given parent node. Events are
-    /// dispatched via the given [`Dispatch`]er.
+    /// dispatched via the given [`Dispatch`]er. Lifecycle hooks are queued on the dispatcher, for
+    /// the app to run once it is done patching.
     ///
     /// [`Dispatch`]: ../app/trait.Dispatch.html
-    pub fn apply(self, parent: &web_sys::Element, app: &Dispatcher<Message, Command>) -> Storage<Message> where
+    pub fn apply(self, parent: &B::Element, app: &Dispatcher<Message, Command>) -> Storage<Message, B> where
+        Message: Clone + PartialEq + fmt::Debug + 'static,
+        Command: SideEffect<Message> + fmt::Debug + 'static,
+        EventHandler<'a, Message>: Clone,
+        B: Default,
+    {
+        self.apply_with(&B::default(), parent, app)
+    }
+
+    /// Apply the given PatchSet like [`apply`], operating on the dom via the given backend.
+    ///
+    /// [`apply`]: #method.apply
+    pub fn apply_with(self, backend: &B, parent: &B::Element, app: &Dispatcher<Message, Command>) -> Storage<Message, B> where
         Message: Clone + PartialEq + fmt::Debug + 'static,
         Command: SideEffect<Message> + fmt::Debug + 'static,
         EventHandler<'a, Message>: Clone,
     {
-        let (storage, pending) = self.prepare(app);
+        let (storage, pending) = self.prepare_with(backend, app);
 
         // add top level nodes
+        let parent = parent.clone().into();
         for node in pending.iter() {
-            parent
-                .insert_before(node, None)
-                .expect("failed to insert child node");
+            backend.insert_before(&parent, node, None);
         }
 
         // return storage so it can be stored by the caller
         storage
     }
-}
 
-struct NodeStack {
-    /// Parent nodes in the tree [(parent, [pending children])].
-    stack: Vec<(web_sys::Node, Vec<web_sys::Node>)>,
-    pending: Vec<web_sys::Node>,
-}
+    fn process_hydrate_list(
+        backend: &B,
+        patches: Vec<Patch<'a, Message, Command, K, B>>,
+        keyed: &mut HashMap<&'a K, Vec<Patch<'a, Message, Command, K, B>>>,
+        app: &Dispatcher<Message, Command>,
+        storage: &mut Storage<Message, B>,
+        hydrator: &mut Hydrator<'a, '_, B>,
+    )
+    where
+        Message: Clone + PartialEq + fmt::Debug + 'static,
+        Command: SideEffect<Message> + 'static,
+        EventHandler<'a, Message>: Clone,
+    {
+        let mut special_attributes: Vec<(B::Element, &str, &str)> = vec![];
 
-impl NodeStack {
-    fn new() -> Self {
-        Self {
-            stack: vec![],
-            pending: vec![],
-        }
-    }
+        for p in patches.into_iter() {
+            match p {
+                Patch::ReferenceKey(key) => {
+                    let patches = keyed.remove(&key)
+                        .expect("patches for given key not found");
+                    Self::process_hydrate_list(backend, patches, keyed, app, storage, hydrator);
+                }
+                Patch::CreateElement { element, namespace } => {
+                    let namespace = element_namespace(backend, Some(hydrator.last()), element, namespace);
+                    let node = hydrator.claim_element(element, namespace);
+                    storage.push(WebItem::Element(node));
+                }
+                Patch::CreateText { text } => {
+                    let node = hydrator.claim_text(text);
+                    storage.push(WebItem::Text(node));
+                }
+                Patch::CreateFragment => {
+                    hydrator.push_fragment();
+                    storage.push(WebItem::Fragment);
+                }
+                Patch::CreatePortal(target) => {
+                    // portal content isn't pre-rendered, it is created in the target
+                    let node = portal_target(backend, target);
+                    hydrator.push(node.clone().into());
+                    storage.push(WebItem::Portal(node));
+                }
+                Patch::SetInnerHtml(html) => {
+                    let node = hydrator.last();
+                    let element = backend.as_element(node)
+                        .expect("innerHtml requested on non Element node");
+                    backend.set_inner_html(element, html);
+                    hydrator.claim_children();
+                }
+                Patch::SetAttribute { name, value } => {
+                    let node = hydrator.last();
+                    let element = backend.as_element(node)
+                        .expect("attributes can only be added to elements")
+                        .clone();
+                    match name {
+                        "autofocus" | "checked" | "disabled" | "draggable" |  "hidden"
+                        | "selected" | "spellcheck" | "value"
+                        => {
+                            // delay setting special attributes until after everything else is done
+                            special_attributes.push((element, name, value));
+                        }
+                        _ if !hydrator.adopted() => set_attribute(backend, &element, name, value),
+                        _ => {
+                            let existing = backend.attribute(&element, name);
+                            if existing.as_deref() != Some(value) {
+                                warn!("hydration mismatch, expected attribute {}={:?}, found {:?}", name, value, existing);
+                                set_attribute(backend, &element, name, value);
+                            }
+                        }
+                    }
+                    hydrator.claim_attribute(name);
+                }
+                Patch::AddClass(name) => {
+                    let node = hydrator.last();
+                    let element = backend.as_element(node)
+                        .expect("classes can only be added to elements");
+                    backend.add_class(element, name);
+                    hydrator.claim_attribute("class");
+                }
+                Patch::SetStyle { property, value } => {
+                    let node = hydrator.last();
+                    let element = backend.as_element(node)
+                        .expect("styles can only be set on elements");
+                    backend.set_style(element, property, value);
+                    hydrator.claim_attribute("style");
+                }
+                Patch::SetProperty { name, value } => {
+                    // properties aren't present in pre-rendered markup, always set them
+                    let node = hydrator.last();
+                    let element = backend.as_element(node)
+                        .expect("properties can only be set on elements");
+                    backend.set_property(element, name, value);
+                }
+                Patch::SetRef(name) => {
+                    let node = hydrator.last();
+                    let element = backend.as_element(node)
+                        .expect("refs can only be set on elements");
+                    app.set_ref(name, element.clone());
+                }
+                Patch::RunHook { lifecycle, hook } => {
+                    let node = hydrator.last();
+                    let element = backend.as_element(node)
+                        .expect("hooks can only be run on elements");
+                    app.queue_hook(backend.bind_hook(lifecycle, hook, element));
+                }
+                Patch::AddListener { trigger, handler, options } => {
+                    let node = hydrator.last();
+                    let element = backend.as_element(node)
+                        .expect("listeners can only be added to elements");
+                    let listener = backend.add_listener(element, trigger, handler, options, app);
+                    storage.push(WebItem::Closure(listener));
+                }
+                Patch::CreateComponent { msg, create } => {
+                    let mut component = create(app.clone());
+                    let node = component.node().expect("empty component?");
+                    match backend.component_node(node) {
+                        Some(node) => {
+                            for n in component.pending().into_iter().filter_map(|n| backend.component_node(n)) {
+                                hydrator.claim_component(&n);
+                            }
+                            hydrator.push(node);
+                        }
+                        None => {
+                            warn!("components can't be rendered by this backend, leaving it out of the dom");
+                            hydrator.push_fragment();
+                        }
+                    }
 
-    /// Get the current depth of the tree.
-    fn depth(&self) -> usize {
-        self.stack.len()
-    }
+                    // the component's mount hooks run with ours, once its nodes are attached
+                    if let Some(hooks) = component.pending_hooks() {
+                        app.queue_hook(Box::new(move || { hooks(); (None, None) }));
+                    }
 
-    /// Get the current parent node off the stack, if any.
-    fn last(&self) -> Option<&web_sys::Node> {
-        self.stack.last().map(|(node, _)| node)
-    }
+                    component.dispatch(msg);
+                    storage.push(WebItem::Component(component));
+                }
+                Patch::Up => {
+                    hydrator.pop();
+                    storage.push(WebItem::Up);
+                }
+                Patch::RemoveElement(_) | Patch::CopyElement(_) | Patch::MoveElement(_)
+                | Patch::CopyFragment(_) | Patch::MoveFragment(_) | Patch::CopyPortal(_)
+                | Patch::RemoveText(_) | Patch::ReplaceText { .. } | Patch::CopyText(_)
+                | Patch::UnsetInnerHtml
+                | Patch::CopyComponent(_) | Patch::MoveComponent(_)
+                | Patch::UpdateComponent { .. } | Patch::MupdateComponent { .. }
+                | Patch::RemoveComponent(_)
+                | Patch::RemoveAttribute(_) | Patch::RemoveClass(_) | Patch::RemoveStyle(_)
+                | Patch::RemoveProperty(_) | Patch::RemoveRef(_) | Patch::UnmountRef { .. }
+                | Patch::RunUnmountHook { .. }
+                | Patch::CopyListener(_) | Patch::RemoveListener { .. }
+                => {
+                    unreachable!("only patch sets that create a new dom can be hydrated");
+                }
+            }
+        }
 
-    /// Add a new parent node to the stack.
-    fn push_parent(&mut self, parent: impl Into<web_sys::Node>) {
-        self.stack.push((parent.into(), vec![]));
+        for (element, name, value) in special_attributes.into_iter() {
+            set_attribute(backend, &element, name, value);
+        }
     }
 
-    /// Append a pending child node to the current parent.
-    fn push_child(&mut self, child: impl Into<web_sys::Node>) {
-        self.stack.last_mut()
-            .map_or(&mut self.pending, |(_parent, pending)| pending)
-            .push(child.into());
+    /// Hydrate the existing children of the given parent node using this PatchSet. Events are
+    /// dispatched via the given [`Dispatch`]er.
+    ///
+    /// Instead of creating new nodes, the nodes this patch set would create are matched against
+    /// the nodes already present in the parent, for example markup pre-rendered on a server with
+    /// [`ssr::render`]. Matching nodes are adopted into storage and event listeners are attached
+    /// to them. Mismatched nodes and attributes are repaired and unexpected nodes are removed, a
+    /// warning is logged for each mismatch. Components always create their own nodes, pre-rendered
+    /// markup matching those nodes is replaced by them.
+    ///
+    /// Only patch sets that create a new dom, as generated by diffing against an empty virtual dom,
+    /// can be hydrated. Other patch sets are applied as usual, with a warning.
+    ///
+    /// [`Dispatch`]: ../app/trait.Dispatch.html
+    /// [`ssr::render`]: ../ssr/fn.render.html
+    pub fn hydrate(self, parent: &B::Element, app: &Dispatcher<Message, Command>) -> Storage<Message, B> where
+        Message: Clone + PartialEq + fmt::Debug + 'static,
+        Command: SideEffect<Message> + fmt::Debug + 'static,
+        EventHandler<'a, Message>: Clone,
+        B: Default,
+    {
+        self.hydrate_with(&B::default(), parent, app)
     }
 
-    /// We are finished processing this parent node, remove it from the stack and append any
-    /// remaining child nodes.
-    fn pop(&mut self) {
+    /// Hydrate the children of the given parent like [`hydrate`], operating on the dom via the
+    /// given backend.
+    ///
+    /// [`hydrate`]: #method.hydrate
+    pub fn hydrate_with(self, backend: &B, parent: &B::Element, app: &Dispatcher<Message, Command>) -> Storage<Message, B> where
+        Message: Clone + PartialEq + fmt::Debug + 'static,
+        Command: SideEffect<Message> + fmt::Debug + 'static,
+        EventHandler<'a, Message>: Clone,
+    {
+        if !self.creates_dom() {
+            warn!("only patch sets that create a new dom can be hydrated, applying patches instead");
+            return self.apply_with(backend, parent, app);
+        }
+
+        let mut storage = vec![];
+        let mut hydrator = Hydrator::new(backend, parent);
+        let PatchSet { patches, mut keyed } = self;
+
+        Self::process_hydrate_list(backend, patches, &mut keyed, app, &mut storage, &mut hydrator);
+        hydrator.finish();
+
+        storage
+    }
+}
+
+/// The namespace of a new element with the given parent, inherited from the parent unless
+/// specified explicitly.
+fn element_namespace<B: Backend>(backend: &B, parent: Option<&B::Node>, name: &str, namespace: Option<Namespace>) -> Namespace {
+    if let Some(namespace) = namespace {
+        return namespace;
+    }
+
+    match parent.and_then(|node| backend.as_element(node)) {
+        Some(parent) => backend.element_namespace(parent).child(&backend.element_name(parent), name),
+        None => Namespace::default().child("", name),
+    }
+}
+
+/// Find the target element of a portal. If there is no such element, the content of the portal
+/// is created in a detached element.
+fn portal_target<B: Backend>(backend: &B, target: &PortalTarget) -> B::Element {
+    backend.portal_target(target)
+        .unwrap_or_else(|| {
+            warn!("portal target {:?} not found", target);
+            backend.create_element("div", Namespace::Html)
+        })
+}
+
+/// Set an attribute, in its namespace if it has a namespace prefix (e.g. `xlink:href`).
+fn set_attribute<B: Backend>(backend: &B, element: &B::Element, name: &str, value: &str) {
+    match attribute_namespace(name) {
+        Some(namespace) => backend.set_attribute_ns(element, namespace, name, value),
+        None => backend.set_attribute(element, name, value),
+    }
+}
+
+/// Returns true if the given nodes have the same markup, meaning the same element names,
+/// attributes, text, and children.
+fn same_markup<B: Backend>(backend: &B, a: &B::Node, b: &B::Node) -> bool {
+    if let (Some(a), Some(b)) = (backend.as_text(a), backend.as_text(b)) {
+        return backend.text(a) == backend.text(b);
+    }
+
+    let (a_element, b_element) = match (backend.as_element(a), backend.as_element(b)) {
+        (Some(a), Some(b)) => (a, b),
+        _ => return false,
+    };
+
+    let mut names = backend.attribute_names(a_element);
+    let mut other_names = backend.attribute_names(b_element);
+    names.sort();
+    other_names.sort();
+
+    let (children, other_children) = (backend.children(a), backend.children(b));
+
+    backend.element_name(a_element).eq_ignore_ascii_case(&backend.element_name(b_element))
+        && backend.element_namespace(a_element) == backend.element_namespace(b_element)
+        && names == other_names
+        && names.iter().all(|name| backend.attribute(a_element, name) == backend.attribute(b_element, name))
+        && children.len() == other_children.len()
+        && children.iter().zip(other_children.iter()).all(|(a, b)| same_markup(backend, a, b))
+}
+
+struct NodeStack<'b, B: Backend> {
+    backend: &'b B,
+    /// Parent nodes in the tree [(parent, [pending children])].
+    stack: Vec<(B::Node, Vec<B::Node>)>,
+    /// Fragments we are in [(depth of the parent stack, moved)].
+    fragments: Vec<(usize, bool)>,
+    pending: Vec<B::Node>,
+}
+
+impl<'b, B: Backend> NodeStack<'b, B> {
+    fn new(backend: &'b B) -> Self {
+        Self {
+            backend,
+            stack: vec![],
+            fragments: vec![],
+            pending: vec![],
+        }
+    }
+
+    /// Get the current depth of the tree.
+    fn depth(&self) -> usize {
+        self.stack.len() + self.fragments.len()
+    }
+
+    /// Returns the innermost fragment if its nodes are direct children of the current parent.
+    fn fragment(&self) -> Option<(usize, bool)> {
+        self.fragments.last()
+            .cloned()
+            .filter(|(depth, _)| *depth == self.stack.len())
+    }
+
+    /// Returns true if we are in a fragment that is being moved. Existing nodes in the fragment
+    /// need to be moved along with it.
+    fn moving(&self) -> bool {
+        matches!(self.fragment(), Some((_, true)))
+    }
+
+    /// Start a fragment, its nodes are added to the current parent. Fragments nested in a moved
+    /// fragment are also moved.
+    fn push_fragment(&mut self, moved: bool) {
+        let moved = moved || self.moving();
+        self.fragments.push((self.stack.len(), moved));
+    }
+
+    /// An existing node is kept in the tree, insert any pending children of the current parent
+    /// before it, or move it if it is part of a fragment that is being moved.
+    fn keep(&mut self, node: &B::Node) {
+        if self.moving() {
+            self.push_child(node.clone());
+        }
+        else {
+            self.insert_before(Some(node));
+        }
+    }
+
+    /// Get the current parent node off the stack, if any.
+    fn last(&self) -> Option<&B::Node> {
+        self.stack.last().map(|(node, _)| node)
+    }
+
+    /// An existing component is kept in the tree, its first node is the parent for its patches.
+    fn keep_component<Message>(&mut self, component: &dyn Component<Message>) {
+        let backend = self.backend;
+        let node = match backend.component_node(component.node().expect("empty component?")) {
+            Some(node) => node,
+            None => return self.push_unhosted(),
+        };
+
+        if self.moving() {
+            for n in component.nodes().into_iter().filter_map(|n| backend.component_node(n)) {
+                self.push_child(n);
+            }
+        }
+        else {
+            self.insert_before(Some(&node));
+        }
+        self.push_parent(node);
+    }
+
+    /// An existing component is moved, its first node is the parent for its patches.
+    fn move_component<Message>(&mut self, component: &dyn Component<Message>) {
+        let backend = self.backend;
+        let node = match backend.component_node(component.node().expect("empty component?")) {
+            Some(node) => node,
+            None => return self.push_unhosted(),
+        };
+
+        for n in component.nodes().into_iter().filter_map(|n| backend.component_node(n)) {
+            self.push_child(n);
+        }
+        self.push_parent(node);
+    }
+
+    /// A component the backend can't host, its patches apply to an empty fragment instead.
+    fn push_unhosted(&mut self) {
+        warn!("components can't be rendered by this backend, leaving it out of the dom");
+        self.push_fragment(false);
+    }
+
+    /// Add a new parent node to the stack.
+    fn push_parent(&mut self, parent: impl Into<B::Node>) {
+        self.stack.push((parent.into(), vec![]));
+    }
+
+    /// Append a pending child node to the current parent.
+    fn push_child(&mut self, child: impl Into<B::Node>) {
+        self.stack.last_mut()
+            .map_or(&mut self.pending, |(_parent, pending)| pending)
+            .push(child.into());
+    }
+
+    /// We are finished processing this parent node, remove it from the stack and append any
+    /// remaining child nodes. The end of a fragment just removes the fragment, its nodes are
+    /// children of the parent node.
+    fn pop(&mut self) {
+        if self.fragment().is_some() {
+            self.fragments.pop();
+            return;
+        }
+
         self.insert_before(None);
         self.stack.pop();
     }
 
     /// Pop and return pending items.
-    fn pop_pending(&mut self) -> Vec<web_sys::Node> {
+    fn pop_pending(&mut self) -> Vec<B::Node> {
         let mut pending = vec![];
         std::mem::swap(&mut self.pending, &mut pending);
         pending
     }
 
     /// Insert any pending children into the parent before the given child node.
-    fn insert_before(&mut self, child: Option<&web_sys::Node>) {
+    fn insert_before(&mut self, child: Option<&B::Node>) {
+        let backend = self.backend;
         if let Some((parent, pending)) = &mut self.stack.last_mut() {
             for node in pending.drain(..) {
-                parent
-                    .insert_before(&node, child)
-                    .expect("failed to insert child node");
+                backend.insert_before(parent, &node, child);
             }
         }
         else if let Some(sibling) = child {
-            let parent = sibling.parent_node();
+            let parent = backend.parent(sibling);
             for node in self.pending.drain(..) {
-                parent.as_ref()
-                    .expect("no parent node")
-                    .insert_before(&node, Some(sibling))
-                    .expect("failed to insert child node");
+                backend.insert_before(
+                    parent.as_ref().expect("no parent node"),
+                    &node,
+                    Some(sibling),
+                );
             }
         }
         else {
@@ -838,8 +1179,267 @@ impl NodeStack {
     }
 }
 
-impl<'a, Message, Command, K: Eq + Hash> From<Vec<Patch<'a, Message, Command, K>>> for PatchSet<'a, Message, Command, K> {
-    fn from(v: Vec<Patch<'a, Message, Command, K>>) -> Self {
+/// A node being hydrated.
+struct Level<'a, B: Backend> {
+    node: B::Node,
+    /// Existing children of this node that have not been claimed yet.
+    unclaimed: VecDeque<B::Node>,
+    /// Attributes set on this node.
+    attributes: Vec<&'a str>,
+    /// Whether this node existed before hydration.
+    adopted: bool,
+}
+
+/// Tracks our position in the existing dom tree while hydrating it.
+struct Hydrator<'a, 'b, B: Backend> {
+    backend: &'b B,
+    stack: Vec<Level<'a, B>>,
+    /// The depth of the stack at the start of each fragment we are in.
+    fragments: Vec<usize>,
+}
+
+impl<'a, 'b, B: Backend> Hydrator<'a, 'b, B> {
+    fn new(backend: &'b B, parent: &B::Element) -> Self {
+        let node = parent.clone().into();
+        Self {
+            backend,
+            stack: vec![Level {
+                unclaimed: backend.children(&node).into(),
+                node,
+                attributes: vec![],
+                adopted: true,
+            }],
+            fragments: vec![],
+        }
+    }
+
+    /// Get the current parent node.
+    fn last(&self) -> &B::Node {
+        &self.stack.last().expect("no previous node").node
+    }
+
+    /// Returns true if the current parent node existed before hydration.
+    fn adopted(&self) -> bool {
+        self.stack.last().expect("no previous node").adopted
+    }
+
+    /// Describe a node for mismatch warnings.
+    fn describe(&self, node: Option<&B::Node>) -> String {
+        let backend = self.backend;
+        match node {
+            None => "nothing".to_owned(),
+            Some(node) => {
+                if let Some(element) = backend.as_element(node) {
+                    format!("<{}> element", backend.element_name(element))
+                }
+                else if let Some(text) = backend.as_text(node) {
+                    format!("text {:?}", backend.text(text))
+                }
+                else {
+                    "unknown node".to_owned()
+                }
+            }
+        }
+    }
+
+    /// Insert a node created by a component into the current parent. Components create their own
+    /// dom, so if the next unclaimed child was pre-rendered with the same markup it is claimed and
+    /// replaced by the new node instead of being removed as unexpected.
+    fn claim_component(&mut self, node: &B::Node) {
+        let backend = self.backend;
+        let level = self.stack.last_mut().expect("no parent node");
+
+        let existing = level.unclaimed.front()
+            .filter(|existing| same_markup(backend, existing, node))
+            .cloned();
+
+        backend.insert_before(&level.node, node, level.unclaimed.front());
+        if let Some(existing) = existing {
+            level.unclaimed.pop_front();
+            backend.remove(&existing);
+        }
+    }
+
+    /// Add a newly created node to the stack.
+    fn push(&mut self, node: B::Node) {
+        self.stack.push(Level {
+            node,
+            unclaimed: VecDeque::new(),
+            attributes: vec![],
+            adopted: false,
+        });
+    }
+
+    /// Start a fragment, its nodes are claimed from the current parent.
+    fn push_fragment(&mut self) {
+        self.fragments.push(self.stack.len());
+    }
+
+    /// Claim the next child of the current parent as an element with the given name. The existing
+    /// child is replaced if it does not match.
+    fn claim_element(&mut self, name: &str, namespace: Namespace) -> B::Element {
+        let backend = self.backend;
+        let level = self.stack.last_mut().expect("no parent node");
+
+        // skip whitespace between elements, html templates commonly introduce it
+        while let Some(node) = level.unclaimed.front() {
+            match backend.as_text(node) {
+                Some(text) if backend.text(text).trim().is_empty() => {
+                    backend.remove(node);
+                    level.unclaimed.pop_front();
+                }
+                _ => break,
+            }
+        }
+
+        let existing = level.unclaimed.pop_front();
+        let matched = existing.as_ref()
+            .and_then(|node| backend.as_element(node))
+            .filter(|element| backend.element_name(element).eq_ignore_ascii_case(name))
+            .filter(|element| backend.element_namespace(element) == namespace)
+            .cloned();
+
+        match matched {
+            Some(element) => {
+                let node = element.clone().into();
+                self.stack.push(Level {
+                    unclaimed: backend.children(&node).into(),
+                    node,
+                    attributes: vec![],
+                    adopted: true,
+                });
+                element
+            }
+            None => {
+                if level.adopted {
+                    warn!("hydration mismatch, expected <{}> element, found {}", name, self.describe(existing.as_ref()));
+                }
+                let element = backend.create_element(name, namespace);
+                self.replace(existing, element.clone().into());
+                element
+            }
+        }
+    }
+
+    /// Claim the next child of the current parent as a text node with the given value. The
+    /// existing child is replaced or updated if it does not match.
+    fn claim_text(&mut self, text: &str) -> B::Text {
+        let backend = self.backend;
+        let level = self.stack.last_mut().expect("no parent node");
+
+        let existing = level.unclaimed.pop_front();
+        let found = existing.as_ref()
+            .and_then(|node| backend.as_text(node))
+            .map(|node| (node.clone(), backend.text(node)));
+
+        let node = match found {
+            Some((node, value)) if !text.is_empty() && value.starts_with(text) => {
+                if value.len() > text.len() {
+                    // adjacent text nodes are merged when html is parsed, split them back up
+                    backend.set_text(&node, text);
+                    let rest = backend.create_text(&value[text.len()..]).into();
+                    backend.insert_before(&level.node, &rest, level.unclaimed.front());
+                    level.unclaimed.push_front(rest);
+                }
+                node
+            }
+            Some((node, value)) if !text.is_empty() => {
+                warn!("hydration mismatch, expected text {:?}, found text {:?}", text, value);
+                backend.set_text(&node, text);
+                node
+            }
+            _ if text.is_empty() => {
+                // empty text nodes do not survive html serialization, create one without
+                // claiming the existing child
+                let node = backend.create_text(text);
+                backend.insert_before(&level.node, &node.clone().into(), existing.as_ref());
+                if let Some(existing) = existing {
+                    level.unclaimed.push_front(existing);
+                }
+                self.push(node.clone().into());
+                return node;
+            }
+            _ => {
+                if level.adopted {
+                    warn!("hydration mismatch, expected text {:?}, found {}", text, self.describe(existing.as_ref()));
+                }
+                let node = backend.create_text(text);
+                self.replace(existing, node.clone().into());
+                return node;
+            }
+        };
+
+        self.push(node.clone().into());
+        node
+    }
+
+    /// Replace the given existing child of the current parent with a newly created node and add
+    /// the new node to the stack.
+    fn replace(&mut self, existing: Option<B::Node>, node: B::Node) {
+        let backend = self.backend;
+        let level = self.stack.last().expect("no parent node");
+        backend.insert_before(&level.node, &node, existing.as_ref());
+        if let Some(existing) = existing {
+            backend.remove(&existing);
+        }
+        self.push(node);
+    }
+
+    /// Mark the existing children of the current parent as claimed.
+    fn claim_children(&mut self) {
+        self.stack.last_mut().expect("no parent node").unclaimed.clear();
+    }
+
+    /// Mark the given attribute of the current parent as claimed.
+    fn claim_attribute(&mut self, name: &'a str) {
+        self.stack.last_mut().expect("no parent node").attributes.push(name);
+    }
+
+    /// Remove any unclaimed children of the given node.
+    fn remove_unclaimed(&self, level: &Level<'a, B>) {
+        for node in level.unclaimed.iter() {
+            warn!("hydration mismatch, removing unexpected {}", self.describe(Some(node)));
+            self.backend.remove(node);
+        }
+    }
+
+    /// We are finished processing this parent node, remove it from the stack along with any
+    /// unclaimed children and attributes. The end of a fragment just removes the fragment.
+    fn pop(&mut self) {
+        if self.fragments.last() == Some(&self.stack.len()) {
+            self.fragments.pop();
+            return;
+        }
+
+        let backend = self.backend;
+        let level = self.stack.pop().expect("no parent node");
+        assert!(!self.stack.is_empty(), "unexpected Up");
+
+        if !level.adopted {
+            return;
+        }
+
+        self.remove_unclaimed(&level);
+        if let Some(element) = backend.as_element(&level.node) {
+            for name in backend.attribute_names(element) {
+                if !level.attributes.contains(&name.as_str()) {
+                    warn!("hydration mismatch, removing unexpected attribute {}", name);
+                    backend.remove_attribute(element, &name);
+                }
+            }
+        }
+    }
+
+    /// We are finished hydrating, remove any unclaimed children of the parent.
+    fn finish(mut self) {
+        let level = self.stack.pop().expect("no parent node");
+        assert!(self.stack.is_empty(), "the stack should be empty");
+        self.remove_unclaimed(&level);
+    }
+}
+
+impl<'a, Message, Command, K: Eq + Hash, B: Backend> From<Vec<Patch<'a, Message, Command, K, B>>> for PatchSet<'a, Message, Command, K, B> {
+    fn from(v: Vec<Patch<'a, Message, Command, K, B>>) -> Self {
         PatchSet {
             patches: v,
             keyed: HashMap::new(),
@@ -847,9 +1447,9 @@ impl<'a, Message, Command, K: Eq + Hash> From<Vec<Patch<'a, Message, Command, K>
     }
 }
 
-impl<'a, Message, Command, K: Eq + Hash> IntoIterator for PatchSet<'a, Message, Command, K> {
-    type Item = Patch<'a, Message, Command, K>;
-    type IntoIter = ::std::vec::IntoIter<Patch<'a, Message, Command, K>>;
+impl<'a, Message, Command, K: Eq + Hash, B: Backend> IntoIterator for PatchSet<'a, Message, Command, K, B> {
+    type Item = Patch<'a, Message, Command, K, B>;
+    type IntoIter = ::std::vec::IntoIter<Patch<'a, Message, Command, K, B>>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.patches.into_iter()
@@ -860,6 +1460,7 @@ impl<'a, Message, Command, K: Eq + Hash> IntoIterator for PatchSet<'a, Message,
 mod tests {
     use super::*;
 
+    use wasm_bindgen::JsCast;
     use wasm_bindgen_test::*;
     use wasm_bindgen_test::wasm_bindgen_test_configure;
     wasm_bindgen_test_configure!(run_in_browser);
@@ -914,6 +1515,7 @@ mod tests {
         let patch_set: PatchSet<Msg, Cmd, Key> = vec![
             Patch::CreateElement {
                 element: "",
+                namespace: None,
             },
         ].into();
 
@@ -924,7 +1526,7 @@ mod tests {
     fn keyed_not_noop() {
         let mut keyed: HashMap<_, Vec<Patch<Msg, Cmd, _>>> = HashMap::new();
         keyed.insert(&1, vec![
-            Patch::CreateElement { element: "" },
+            Patch::CreateElement { element: "", namespace: None },
             Patch::Up,
         ]);
 
@@ -989,6 +1591,7 @@ mod tests {
         let patch_set: PatchSet<Msg, Cmd, Key> = vec![
             CreateElement {
                 element: "input",
+                namespace: None,
             },
             SetAttribute { name: "checked", value: "true" },
             Up,
@@ -1014,6 +1617,7 @@ mod tests {
         let patch_set: PatchSet<Msg, Cmd, Key> = vec![
             CreateElement {
                 element: "input",
+                namespace: None,
             },
             SetAttribute { name: "disabled", value: "true" },
             Up,
@@ -1124,6 +1728,7 @@ mod tests {
         let patch_set: PatchSet<Msg, Cmd, Key> = vec![
             CreateElement {
                 element: "input",
+                namespace: None,
             },
             SetAttribute { name: "checked", value: "false" },
             Up,
@@ -1149,6 +1754,7 @@ mod tests {
         let patch_set: PatchSet<Msg, Cmd, Key> = vec![
             CreateElement {
                 element: "input",
+                namespace: None,
             },
             SetAttribute { name: "disabled", value: "false" },
             Up,
@@ -1174,6 +1780,7 @@ mod tests {
         let patch_set: PatchSet<Msg, Cmd, Key> = vec![
             CreateElement {
                 element: "input",
+                namespace: None,
             },
             SetAttribute { name: "autofocus", value: "false" },
             Up,
@@ -1199,6 +1806,7 @@ mod tests {
         let patch_set: PatchSet<Msg, Cmd, Key> = vec![
             CreateElement {
                 element: "option",
+                namespace: None,
             },
             SetAttribute { name: "selected", value: "false" },
             Up,
@@ -1238,7 +1846,7 @@ mod tests {
 
         let parent = elem("div");
         let app = App::dispatcher();
-        let mut storage = vec![];
+        let mut storage: Storage<_> = vec![];
 
         let n = gen1.dom_iter();
         let patch_set = diff::diff(iter::empty(), n, &mut storage);
@@ -1283,7 +1891,7 @@ mod tests {
 
         let parent = elem("div");
         let app = App::dispatcher();
-        let mut storage = vec![];
+        let mut storage: Storage<_> = vec![];
 
         let n = gen1.dom_iter();
         let patch_set = diff::diff(iter::empty(), n, &mut storage);
@@ -1311,4 +1919,1035 @@ mod tests {
             "wrong node in DOM"
         );
     }
+
+    #[test]
+    fn mock_create() {
+        use crate::dom::Dom;
+        use crate::test::mock::Fixture;
+
+        let gen1 = Dom::<Msg, Cmd, Key>::elem("div")
+            .attr("class", "container")
+            .event("click", ())
+            .push(Dom::elem("input").attr("value", "<&>"))
+            .push("text");
+
+        let mut fixture = Fixture::new("main");
+        fixture.create(&gen1);
+
+        let parent = fixture.parent();
+        assert_eq!(fixture.storage().len(), 7);
+        assert_eq!(
+            parent.children_to_html(),
+            r#"<div class="container"><input value="&lt;&amp;&gt;">text</div>"#,
+        );
+        assert_eq!(parent.children()[0].listeners(), vec!["click"]);
+    }
+
+    #[test]
+    fn mock_update() {
+        use crate::dom::Dom;
+        use crate::test::mock::Fixture;
+
+        let gen1 = Dom::<Msg, Cmd, Key>::elem("div")
+            .attr("id", "one")
+            .event("click", ())
+            .push("text");
+
+        let gen2 = Dom::<Msg, Cmd, Key>::elem("div")
+            .attr("class", "two")
+            .push("new text");
+
+        let mut fixture = Fixture::new("main");
+        fixture.create(&gen1);
+
+        let div = fixture.parent().children()[0].clone();
+        fixture.update(&gen1, &gen2);
+
+        assert_eq!(fixture.parent().children(), vec![div.clone()], "element should have been reused");
+        assert_eq!(div.to_html(), r#"<div class="two">new text</div>"#);
+        assert!(div.listeners().is_empty());
+    }
+
+    #[test]
+    fn mock_inner_html() {
+        use crate::dom::Dom;
+        use crate::test::mock::Fixture;
+
+        let gen1 = unsafe { Dom::<Msg, Cmd, Key>::elem("div").inner_html("<b>bold</b>") };
+        let gen2 = Dom::<Msg, Cmd, Key>::elem("div");
+
+        let mut fixture = Fixture::new("main");
+        fixture.create(&gen1);
+
+        assert_eq!(fixture.parent().children_to_html(), "<div><b>bold</b></div>");
+
+        fixture.update(&gen1, &gen2);
+
+        assert_eq!(fixture.parent().children_to_html(), "<div></div>");
+    }
+
+    #[test]
+    fn mock_class_and_style() {
+        use crate::backend::Backend;
+        use crate::dom::Dom;
+        use crate::test::mock::Fixture;
+        use crate::test::MockDom;
+
+        let gen1 = Dom::<Msg, Cmd, Key>::elem("div")
+            .class("a b c")
+            .style("color", "red")
+            .style("width", "1px");
+
+        let gen2 = Dom::<Msg, Cmd, Key>::elem("div")
+            .class("a")
+            .class_if("c", true)
+            .class_if("b", false)
+            .class("d")
+            .style("color", "blue");
+
+        let mut fixture = Fixture::new("main");
+        fixture.create(&gen1);
+
+        let div = fixture.parent().children()[0].clone();
+        assert_eq!(div.to_html(), r#"<div class="a b c" style="color: red; width: 1px;"></div>"#);
+
+        // classes and styles added outside of the vdom should be left alone
+        MockDom.add_class(&div, "external");
+        MockDom.set_style(&div, "height", "2px");
+
+        fixture.update(&gen1, &gen2);
+
+        assert_eq!(fixture.parent().children(), vec![div.clone()], "element should have been reused");
+        assert_eq!(div.to_html(), r#"<div class="a c external d" style="color: blue; height: 2px;"></div>"#);
+
+        // removing the last class should remove the attribute
+        let gen3 = Dom::<Msg, Cmd, Key>::elem("div")
+            .style("color", "blue");
+
+        fixture.update(&gen2, &gen3);
+        assert_eq!(div.to_html(), r#"<div class="external" style="color: blue; height: 2px;"></div>"#);
+
+        MockDom.remove_class(&div, "external");
+        assert_eq!(div.to_html(), r#"<div style="color: blue; height: 2px;"></div>"#);
+    }
+
+    #[test]
+    fn mock_properties() {
+        use crate::dom::Dom;
+        use crate::test::mock::Fixture;
+
+        let gen1 = Dom::<Msg, Cmd, Key>::elem("input")
+            .attr("type", "checkbox")
+            .property("indeterminate", true)
+            .property("scrollTop", 10);
+
+        let gen2 = Dom::<Msg, Cmd, Key>::elem("input")
+            .attr("type", "checkbox")
+            .property("indeterminate", false);
+
+        let mut fixture = Fixture::new("main");
+        fixture.create(&gen1);
+
+        let input = fixture.parent().children()[0].clone();
+        assert_eq!(input.property("indeterminate"), Some(PropertyValue::Bool(true)));
+        assert_eq!(input.property("scrollTop"), Some(PropertyValue::Number(10.0)));
+        assert_eq!(input.to_html(), r#"<input type="checkbox">"#, "properties should not be attributes");
+
+        fixture.update(&gen1, &gen2);
+
+        assert_eq!(fixture.parent().children(), vec![input.clone()], "element should have been reused");
+        assert_eq!(input.property("indeterminate"), Some(PropertyValue::Bool(false)));
+        assert_eq!(input.property("scrollTop"), None);
+    }
+
+    #[test]
+    fn mock_refs() {
+        use crate::dom::Dom;
+        use crate::test::mock::{Fixture, Node};
+        use crate::test::MockDom;
+
+        let input = |name: Option<&'static str>| {
+            let input = Dom::<Msg, Cmd, Key>::elem("input").attr("type", "text");
+            match name {
+                Some(name) => input.node_ref(name),
+                None => input,
+            }
+        };
+
+        let gen1 = input(Some("pending"));
+        let gen2 = input(Some("pending"));
+        let gen3 = input(Some("edit"));
+        let gen4 = input(None);
+
+        fn refs<'a>(patch_set: &PatchSet<'a, Msg, Cmd, Key, MockDom>) -> Vec<(bool, &'a str)> {
+            patch_set.patches.iter()
+                .filter_map(|p| match *p {
+                    Patch::SetRef(name) => Some((true, name)),
+                    Patch::RemoveRef(name) => Some((false, name)),
+                    _ => None,
+                })
+                .collect()
+        }
+
+        let mut fixture = Fixture::new("main");
+        let changes = fixture.patch(None, Some(&gen1), |p| refs(p).len());
+        assert_eq!(changes, 1);
+
+        assert_eq!(fixture.parent().children_to_html(), r#"<input type="text">"#, "refs should not be attributes");
+
+        let input = fixture.parent().children()[0].clone();
+        let node_ref = |fixture: &Fixture, name| fixture.app().get_ref::<Node>(name);
+        assert_eq!(node_ref(&fixture, "pending"), Some(input.clone()));
+
+        // unchanged refs are already recorded
+        let changes = fixture.patch(Some(&gen1), Some(&gen2), |p| refs(p).len());
+        assert_eq!(changes, 0);
+        assert_eq!(node_ref(&fixture, "pending"), Some(input.clone()));
+
+        fixture.patch(Some(&gen2), Some(&gen3), |p| {
+            assert_eq!(refs(p), vec![(false, "pending"), (true, "edit")]);
+        });
+        assert_eq!(node_ref(&fixture, "pending"), None);
+        assert_eq!(node_ref(&fixture, "edit"), Some(input.clone()));
+
+        fixture.patch(Some(&gen3), Some(&gen4), |p| {
+            assert_eq!(refs(p), vec![(false, "edit")]);
+        });
+        assert_eq!(node_ref(&fixture, "edit"), None);
+
+        assert_eq!(fixture.parent().children_to_html(), r#"<input type="text">"#);
+    }
+
+    #[test]
+    fn mock_refs_removed_elements() {
+        use crate::dom::Dom;
+        use crate::test::mock::{Fixture, Node};
+
+        let form = |field: Option<&'static str>| Dom::<Msg, Cmd, Key>::elem("form")
+            .extend(field.map(|name| Dom::elem("div")
+                .push(Dom::elem(name).node_ref("field"))
+            ));
+
+        let gen1 = form(Some("input"));
+        let gen2 = form(Some("textarea"));
+        let gen3 = form(None);
+
+        let mut fixture = Fixture::new("main");
+        let field = |fixture: &Fixture| fixture.parent().children()[0].children()[0].children()[0].clone();
+        let node_ref = |fixture: &Fixture| fixture.app().get_ref::<Node>("field");
+
+        fixture.create(&gen1);
+        let input = field(&fixture);
+        assert_eq!(node_ref(&fixture), Some(input.clone()));
+
+        // a replaced element is replaced in the ref table
+        fixture.update(&gen1, &gen2);
+        let textarea = field(&fixture);
+        assert_ne!(textarea, input);
+        assert_eq!(node_ref(&fixture), Some(textarea));
+
+        // removing an ancestor removes the element from the ref table
+        fixture.update(&gen2, &gen3);
+        assert_eq!(fixture.parent().children_to_html(), "<form></form>");
+        assert_eq!(node_ref(&fixture), None);
+
+        fixture.update(&gen3, &gen1);
+        assert_eq!(node_ref(&fixture), Some(field(&fixture)));
+
+        fixture.remove(&gen1);
+        assert_eq!(node_ref(&fixture), None);
+    }
+
+    #[test]
+    fn mock_hooks() {
+        use crate::dom::Dom;
+        use crate::vdom::{Hook, Lifecycle};
+        use crate::test::mock::Fixture;
+        use crate::test::MockDom;
+
+        let hook = Hook::Msg(|_| None);
+
+        let gen1 = Dom::<Msg, Cmd, Key>::elem("div")
+            .hook(Lifecycle::Mount, hook)
+            .hook(Lifecycle::Update, hook)
+            .hook(Lifecycle::Unmount, hook)
+            .push(Dom::elem("span").hook(Lifecycle::Unmount, hook));
+        let gen2 = Dom::<Msg, Cmd, Key>::elem("div")
+            .hook(Lifecycle::Mount, hook)
+            .hook(Lifecycle::Update, hook)
+            .hook(Lifecycle::Unmount, hook)
+            .push(Dom::elem("span").hook(Lifecycle::Unmount, hook));
+
+        let hooks = |patch_set: &PatchSet<Msg, Cmd, Key, MockDom>| -> Vec<String> {
+            patch_set.patches.iter()
+                .filter_map(|p| match p {
+                    Patch::RunHook { lifecycle, .. } => Some(format!("run {:?}", lifecycle)),
+                    Patch::RunUnmountHook { element, .. } => Some(format!("unmount {}", element.name().unwrap())),
+                    Patch::RemoveElement(_) => Some("remove".to_owned()),
+                    _ => None,
+                })
+                .collect()
+        };
+
+        let mut fixture = Fixture::new("main");
+
+        // only mount hooks run on creation
+        assert_eq!(fixture.patch(None, Some(&gen1), hooks), vec!["run Mount"]);
+        assert_eq!(fixture.parent().children_to_html(), "<div><span></span></div>", "hooks should not be attributes");
+
+        // only update hooks run on a kept element
+        assert_eq!(fixture.patch(Some(&gen1), Some(&gen2), hooks), vec!["run Update"]);
+
+        // unmount hooks run for the removed element and its children, before it is removed
+        assert_eq!(fixture.patch(Some(&gen2), None, hooks), vec!["unmount div", "unmount span", "remove"]);
+        assert_eq!(fixture.parent().children_to_html(), "");
+    }
+
+    #[test]
+    fn mock_hook_calls() {
+        use crate::dom::Dom;
+        use crate::vdom::{Hook, Lifecycle};
+        use crate::test::mock::{Fixture, HookCall, take_hook_calls};
+
+        let hook = Hook::Msg(|_| None);
+
+        let render = || Dom::<Msg, Cmd, u32>::elem("div")
+            .hook(Lifecycle::Mount, hook)
+            .hook(Lifecycle::Update, hook)
+            .hook(Lifecycle::Unmount, hook)
+            .push(Dom::elem("span").hook(Lifecycle::Unmount, hook))
+            .push(Dom::elem("p").key(1u32).hook(Lifecycle::Unmount, hook));
+        let gen1 = render();
+        let gen2 = render();
+
+        let mut fixture = Fixture::new("main");
+        take_hook_calls();
+
+        // mount hooks run once the element is attached
+        fixture.create(&gen1);
+        let div = fixture.parent().children()[0].clone();
+        let span = div.children()[0].clone();
+        let p = div.children()[1].clone();
+        assert_eq!(take_hook_calls(), vec![
+            HookCall { lifecycle: Lifecycle::Mount, element: div.clone(), root: fixture.parent().clone() },
+        ]);
+
+        // update hooks run with the kept element
+        fixture.update(&gen1, &gen2);
+        assert_eq!(take_hook_calls(), vec![
+            HookCall { lifecycle: Lifecycle::Update, element: div.clone(), root: fixture.parent().clone() },
+        ]);
+
+        // unmount hooks run while the elements are still attached, keyed ones first
+        fixture.remove(&gen2);
+        assert_eq!(take_hook_calls(), vec![
+            HookCall { lifecycle: Lifecycle::Unmount, element: p, root: fixture.parent().clone() },
+            HookCall { lifecycle: Lifecycle::Unmount, element: div.clone(), root: fixture.parent().clone() },
+            HookCall { lifecycle: Lifecycle::Unmount, element: span, root: fixture.parent().clone() },
+        ]);
+        assert_eq!(div.parent(), None);
+    }
+
+    #[test]
+    fn mock_namespaces() {
+        use crate::dom::Dom;
+        use crate::test::mock::Fixture;
+
+        let gen1 = Dom::<Msg, Cmd, Key>::elem("svg")
+            .push(Dom::elem("use").attr("xlink:href", "#dot"))
+            .push(Dom::elem("foreignObject")
+                .push(Dom::elem("div"))
+            );
+
+        let gen2 = Dom::<Msg, Cmd, Key>::elem("svg")
+            .push(Dom::elem("use").attr("xlink:href", "#dot"))
+            .push(Dom::elem("foreignObject")
+                .push(Dom::elem("div"))
+            )
+            .push(Dom::elem("circle"));
+
+        let mut fixture = Fixture::new("main");
+        fixture.create(&gen1);
+
+        let svg = fixture.parent().children()[0].clone();
+        let children = svg.children();
+        assert_eq!(svg.namespace(), Some(Namespace::Svg));
+        assert_eq!(children[0].namespace(), Some(Namespace::Svg));
+        assert_eq!(children[0].attribute("xlink:href"), Some("#dot".to_owned()));
+        assert_eq!(children[1].namespace(), Some(Namespace::Svg));
+        assert_eq!(children[1].children()[0].namespace(), Some(Namespace::Html));
+
+        // new children of existing elements inherit their namespace too
+        fixture.update(&gen1, &gen2);
+
+        assert_eq!(svg.children()[2].name(), Some("circle".to_owned()));
+        assert_eq!(svg.children()[2].namespace(), Some(Namespace::Svg));
+
+        // explicit namespaces aren't inherited
+        let gen = Dom::<Msg, Cmd, Key>::elem_ns("g", Namespace::Svg)
+            .push(Dom::elem("math")
+                .push(Dom::elem_ns("div", Namespace::Html))
+            );
+
+        let mut fixture = Fixture::new("main");
+        fixture.create(&gen);
+
+        let g = fixture.parent().children()[0].clone();
+        assert_eq!(g.namespace(), Some(Namespace::Svg));
+        assert_eq!(g.children()[0].namespace(), Some(Namespace::MathMl));
+        assert_eq!(g.children()[0].children()[0].namespace(), Some(Namespace::Html));
+    }
+
+    #[test]
+    fn mock_callback_listener() {
+        use crate::dom::Dom;
+        use crate::test::mock::Fixture;
+
+        fn button(id: u32) -> Dom<Msg, Cmd, Key> {
+            Dom::elem("button").callback("click", id, move |_| if id > 0 { Some(()) } else { None })
+        }
+
+        let gen1 = button(1);
+        let gen2 = button(1);
+        let gen3 = button(2);
+
+        let mut fixture = Fixture::new("main");
+        fixture.create(&gen1);
+
+        // same key, the listener is kept
+        fixture.patch(Some(&gen1), Some(&gen2), |patch_set| {
+            assert!(patch_set.patches.iter().any(|p| matches!(p, Patch::CopyListener(_))));
+            assert!(!patch_set.patches.iter().any(|p| matches!(p, Patch::AddListener { .. })));
+        });
+
+        // different key, the listener is replaced
+        fixture.patch(Some(&gen2), Some(&gen3), |patch_set| {
+            assert!(patch_set.patches.iter().any(|p| matches!(p, Patch::RemoveListener { .. })));
+            assert!(patch_set.patches.iter().any(|p| matches!(p, Patch::AddListener { .. })));
+        });
+
+        assert_eq!(fixture.parent().children()[0].listeners(), vec!["click"]);
+    }
+
+    #[test]
+    fn callback_identity() {
+        use crate::vdom::Callback;
+
+        fn callback<K: PartialEq + 'static>(key: K) -> Callback<Msg> {
+            Callback::new(key, |_| None)
+        }
+
+        assert_eq!(callback("item"), callback("item"));
+        assert_ne!(callback("item"), callback("other"));
+
+        // keys of different types never match, even if they look the same
+        assert_ne!(callback(1u32), callback(1u64));
+
+        // neither do different closures with the same key
+        assert_ne!(callback(1u32), Callback::new(1u32, |_| None));
+    }
+
+    #[test]
+    fn mock_listener_options() {
+        use crate::dom::{Dom, Handler};
+        use crate::vdom::ListenerOptions;
+        use crate::test::mock::Fixture;
+
+        fn list(options: ListenerOptions) -> Dom<Msg, Cmd, Key> {
+            Dom::elem("ul").on_with_options("scroll", Handler::Msg(()), options)
+        }
+
+        let gen1 = list(ListenerOptions::new().passive());
+        let gen2 = list(ListenerOptions::new().passive());
+        let gen3 = list(ListenerOptions::new().passive().capture());
+
+        let mut fixture = Fixture::new("main");
+        fixture.create(&gen1);
+
+        // same options, the listener is kept
+        fixture.patch(Some(&gen1), Some(&gen2), |patch_set| {
+            assert!(patch_set.patches.iter().any(|p| matches!(p, Patch::CopyListener(_))));
+        });
+
+        // different options, the listener is replaced
+        fixture.patch(Some(&gen2), Some(&gen3), |patch_set| {
+            assert!(patch_set.patches.iter().any(|p| matches!(p,
+                Patch::RemoveListener { options, .. } if !options.capture
+            )));
+            assert!(patch_set.patches.iter().any(|p| matches!(p,
+                Patch::AddListener { options, .. } if options.capture && options.passive
+            )));
+        });
+
+        assert_eq!(fixture.parent().children()[0].listeners(), vec!["scroll"]);
+    }
+
+    #[test]
+    fn mock_insert_element_nested() {
+        use crate::dom::Dom;
+        use crate::test::mock::Fixture;
+
+        let gen1 = Dom::<Msg, Cmd, Key>::elem("div")
+            .push(Dom::elem("a"))
+            .push(Dom::elem("b"))
+            .push(Dom::elem("i"));
+
+        let gen2 = Dom::elem("div")
+            .push(Dom::elem("a"))
+            .push(Dom::elem("p"))
+            .push(Dom::elem("i"));
+
+        let mut fixture = Fixture::new("div");
+        fixture.create(&gen1);
+        fixture.update(&gen1, &gen2);
+
+        match fixture.storage()[3] {
+            WebItem::Element(ref node) => assert_eq!(node.name().unwrap(), "p", "wrong node in storage"),
+            ref e => panic!("expected node to be created instead of: {:?}", e),
+        }
+
+        assert_eq!(fixture.parent().children_to_html(), "<div><a></a><p></p><i></i></div>", "wrong node in DOM");
+    }
+
+    #[test]
+    fn mock_hydrate() {
+        use crate::dom::Dom;
+        use crate::test::mock::{Fixture, Node};
+        use crate::test::MockDom;
+
+        let gen1 = Dom::<Msg, Cmd, Key>::elem("div")
+            .attr("class", "container")
+            .event("click", ())
+            .push(Dom::elem("span").push("text"))
+            .push("more");
+
+        let gen2 = Dom::<Msg, Cmd, Key>::elem("div")
+            .attr("class", "container")
+            .push(Dom::elem("span").push("new text"))
+            .push("more");
+
+        // <div class="container"><span>text</span>more</div>
+        let dom = MockDom;
+        let mut fixture = Fixture::new("main");
+        let parent = fixture.parent().clone();
+        let div = Node::element("div");
+        let span = Node::element("span");
+        dom.set_attribute(&div, "class", "container");
+        dom.insert_before(&span, &Node::text("text"), None);
+        dom.insert_before(&div, &span, None);
+        dom.insert_before(&div, &Node::text("more"), None);
+        dom.insert_before(&parent, &div, None);
+
+        fixture.hydrate(&gen1);
+
+        assert_eq!(fixture.storage().len(), 9);
+        assert_eq!(parent.children(), vec![div.clone()], "element should have been adopted");
+        assert_eq!(div.children()[0], span, "element should have been adopted");
+        assert_eq!(div.listeners(), vec!["click"]);
+        assert_eq!(parent.children_to_html(), r#"<div class="container"><span>text</span>more</div>"#);
+
+        fixture.update(&gen1, &gen2);
+
+        assert_eq!(parent.children(), vec![div.clone()], "element should have been reused");
+        assert_eq!(parent.children_to_html(), r#"<div class="container"><span>new text</span>more</div>"#);
+        assert!(div.listeners().is_empty());
+    }
+
+    #[test]
+    fn mock_hydrate_repair() {
+        use crate::dom::Dom;
+        use crate::test::mock::{Fixture, Node};
+        use crate::test::MockDom;
+
+        let gen1 = Dom::<Msg, Cmd, Key>::elem("div")
+            .attr("title", "new")
+            .push("a")
+            .push("b")
+            .push(Dom::elem("p"));
+
+        // \n<div id="old" title="old">ab<span></span></div><b></b>
+        let dom = MockDom;
+        let mut fixture = Fixture::new("main");
+        let parent = fixture.parent().clone();
+        let div = Node::element("div");
+        dom.set_attribute(&div, "id", "old");
+        dom.set_attribute(&div, "title", "old");
+        dom.insert_before(&div, &Node::text("ab"), None);
+        dom.insert_before(&div, &Node::element("span"), None);
+        dom.insert_before(&parent, &Node::text("\n"), None);
+        dom.insert_before(&parent, &div, None);
+        dom.insert_before(&parent, &Node::element("b"), None);
+
+        fixture.hydrate(&gen1);
+
+        assert_eq!(fixture.storage().len(), 8);
+        assert_eq!(parent.children(), vec![div.clone()], "element should have been adopted");
+        assert_eq!(div.children().len(), 3, "merged text should have been split");
+        assert_eq!(parent.children_to_html(), r#"<div title="new">ab<p></p></div>"#);
+    }
+
+    #[test]
+    fn mock_hydrate_component() {
+        use crate::test::mock::Node;
+        use crate::test::MockDom;
+
+        let element = |name: &str, text: &str| {
+            let node = Node::element(name);
+            MockDom.set_attribute(&node, "class", "counter");
+            MockDom.insert_before(&node, &Node::text(text), None);
+            node
+        };
+
+        // <p class="counter">0</p><p class="counter">1</p>
+        let parent = Node::element("main");
+        let pre_rendered = element("p", "0");
+        let sibling = element("p", "1");
+        MockDom.insert_before(&parent, &pre_rendered, None);
+        MockDom.insert_before(&parent, &sibling, None);
+
+        // pre-rendered component output is replaced by the nodes the component creates
+        let mut hydrator = Hydrator::new(&MockDom, &parent);
+        let created = element("p", "0");
+        hydrator.claim_component(&created);
+        assert_eq!(parent.children(), vec![created.clone(), sibling.clone()]);
+
+        // other nodes are left for the rest of the dom to claim
+        let created_text = Node::text("2");
+        hydrator.claim_component(&created_text);
+        assert_eq!(parent.children(), vec![created, created_text, sibling]);
+    }
+
+    /// Render a keyed list using the given keys, then rerender it using the given new keys.
+    /// Returns the number of moved elements.
+    fn mock_keyed_reorder(old_keys: &[u32], new_keys: &[u32]) -> usize {
+        use crate::dom::Dom;
+        use crate::test::mock::Fixture;
+
+        let list = |keys: &[u32]| Dom::<Msg, Cmd, u32>::elem("ul")
+            .push(Dom::elem("li").push("header"))
+            .extend(keys.iter().map(|k| Dom::elem("li").key(*k).push(k.to_string())))
+            .push(Dom::elem("li").push("footer"));
+
+        let gen1 = list(old_keys);
+        let gen2 = list(new_keys);
+
+        let mut fixture = Fixture::new("div");
+        fixture.create(&gen1);
+
+        let ul = fixture.parent().children()[0].clone();
+        let old_nodes = ul.children();
+
+        let moves = fixture.patch(Some(&gen1), Some(&gen2), |patch_set| {
+            patch_set.keyed.values()
+                .flatten()
+                .filter(|p| matches!(p, Patch::MoveElement(_)))
+                .count()
+        });
+
+        let mut html = "<ul><li>header</li>".to_owned();
+        for k in new_keys {
+            html.push_str(&format!("<li>{}</li>", k));
+        }
+        html.push_str("<li>footer</li></ul>");
+        assert_eq!(fixture.parent().children_to_html(), html);
+
+        // elements for keys that were not removed should have been reused
+        let new_nodes = ul.children();
+        for (i, k) in new_keys.iter().enumerate() {
+            if let Some(j) = old_keys.iter().position(|o| o == k) {
+                assert_eq!(new_nodes[i + 1], old_nodes[j + 1], "element for key {} not reused", k);
+            }
+        }
+
+        moves
+    }
+
+    #[test]
+    fn mock_keyed_unchanged() {
+        assert_eq!(mock_keyed_reorder(&[1, 2, 3, 4, 5], &[1, 2, 3, 4, 5]), 0);
+    }
+
+    #[test]
+    fn mock_keyed_move_last_to_front() {
+        assert_eq!(mock_keyed_reorder(&[1, 2, 3, 4, 5], &[5, 1, 2, 3, 4]), 1);
+    }
+
+    #[test]
+    fn mock_keyed_move_first_to_back() {
+        assert_eq!(mock_keyed_reorder(&[1, 2, 3, 4, 5], &[2, 3, 4, 5, 1]), 1);
+    }
+
+    #[test]
+    fn mock_keyed_swap() {
+        assert_eq!(mock_keyed_reorder(&[1, 2, 3, 4, 5], &[5, 2, 3, 4, 1]), 2);
+    }
+
+    #[test]
+    fn mock_keyed_reverse() {
+        assert_eq!(mock_keyed_reorder(&[1, 2, 3, 4], &[4, 3, 2, 1]), 3);
+    }
+
+    #[test]
+    fn mock_keyed_insert_and_remove() {
+        assert_eq!(mock_keyed_reorder(&[1, 2, 3, 4, 5], &[6, 1, 3, 7, 5, 4]), 1);
+        assert_eq!(mock_keyed_reorder(&[1, 2, 3], &[]), 0);
+        assert_eq!(mock_keyed_reorder(&[], &[1, 2, 3]), 0);
+    }
+
+    #[test]
+    fn mock_keyed_nested() {
+        use crate::dom::Dom;
+        use crate::test::mock::Fixture;
+
+        // rows keyed by row number, containing cells keyed by row and cell number
+        let table = |rows: &[u32]| Dom::<Msg, Cmd, u32>::elem("table")
+            .extend(rows.iter().map(|r| Dom::elem("tr")
+                .key(*r)
+                .extend((1..=3).map(|c| Dom::elem("td")
+                    .key(r * 10 + c)
+                    .push((r * 10 + c).to_string())
+                ))
+            ));
+
+        let gen1 = table(&[1, 2, 3]);
+        let gen2 = table(&[3, 1, 2]);
+
+        let mut fixture = Fixture::new("div");
+        fixture.create(&gen1);
+
+        let old_cells: Vec<_> = fixture.parent().children()[0].children().iter()
+            .flat_map(|row| row.children())
+            .collect();
+
+        let (row_moves, cell_moves) = fixture.patch(Some(&gen1), Some(&gen2), |patch_set| {
+            let moves = |row: bool| patch_set.keyed.iter()
+                .filter(|(&&k, _)| (k < 10) == row)
+                .flat_map(|(_, patches)| patches)
+                .filter(|p| matches!(p, Patch::MoveElement(_)))
+                .count();
+            (moves(true), moves(false))
+        });
+
+        assert_eq!(row_moves, 1);
+        assert_eq!(cell_moves, 0, "cells nested in moved rows should stay in place");
+
+        let mut html = "<table>".to_owned();
+        for r in &[3, 1, 2] {
+            html.push_str("<tr>");
+            for c in 1..=3 {
+                html.push_str(&format!("<td>{}</td>", r * 10 + c));
+            }
+            html.push_str("</tr>");
+        }
+        html.push_str("</table>");
+        assert_eq!(fixture.parent().children_to_html(), html);
+
+        // cells should have been reused
+        let new_cells: Vec<_> = fixture.parent().children()[0].children().iter()
+            .flat_map(|row| row.children())
+            .collect();
+        for cell in &old_cells {
+            assert!(new_cells.contains(cell), "cell {:?} not reused", cell);
+        }
+    }
+
+    #[test]
+    fn mock_fragment() {
+        use crate::dom::Dom;
+        use crate::test::mock::Fixture;
+
+        let row = |cells: Option<&[&str]>| Dom::<Msg, Cmd, Key>::elem("tr")
+            .push(Dom::elem("td").push("first"))
+            .extend(cells.map(|cells| Dom::fragment(
+                cells.iter().map(|c| Dom::elem("td").push(*c))
+            )))
+            .push(Dom::elem("td").push("last"));
+
+        let gen1 = row(Some(&["a", "b"]));
+        let gen2 = row(Some(&["a", "x", "b"]));
+        let gen3 = row(None);
+
+        let mut fixture = Fixture::new("tbody");
+        fixture.create(&gen1);
+
+        assert_eq!(fixture.parent().children_to_html(), "<tr><td>first</td><td>a</td><td>b</td><td>last</td></tr>");
+        let tr = fixture.parent().children()[0].clone();
+        let old_cells = tr.children();
+
+        // add a node in the middle of the fragment
+        fixture.update(&gen1, &gen2);
+
+        assert_eq!(fixture.parent().children_to_html(), "<tr><td>first</td><td>a</td><td>x</td><td>b</td><td>last</td></tr>");
+        assert_eq!(tr.children()[1], old_cells[1], "fragment children should have been reused");
+
+        // remove the fragment
+        fixture.update(&gen2, &gen3);
+
+        assert_eq!(fixture.parent().children_to_html(), "<tr><td>first</td><td>last</td></tr>");
+    }
+
+    #[test]
+    fn mock_portal() {
+        use crate::dom::Dom;
+        use crate::test::mock::{self, Fixture, Node};
+        use crate::test::MockDom;
+
+        let modals = Node::element("div");
+        MockDom.set_attribute(&modals, "id", "modals");
+        MockDom.insert_before(&mock::document(), &modals, None);
+
+        let page = |modal: Option<&str>| Dom::<Msg, Cmd, Key>::elem("div")
+            .push("page")
+            .extend(modal.map(|text| Dom::portal("#modals", vec![
+                Dom::elem("p").push(text),
+            ])));
+
+        let gen1 = page(Some("one"));
+        let gen2 = page(Some("two"));
+        let gen3 = page(None);
+
+        let mut fixture = Fixture::new("main");
+        fixture.create(&gen1);
+
+        assert_eq!(fixture.parent().children_to_html(), "<div>page</div>");
+        assert_eq!(modals.children_to_html(), "<p>one</p>");
+        let p = modals.children()[0].clone();
+
+        // update the portal content
+        fixture.update(&gen1, &gen2);
+
+        assert_eq!(fixture.parent().children_to_html(), "<div>page</div>");
+        assert_eq!(modals.children(), vec![p], "portal content should have been reused");
+        assert_eq!(modals.children_to_html(), "<p>two</p>");
+
+        // remove the portal
+        fixture.update(&gen2, &gen3);
+
+        assert_eq!(modals.children_to_html(), "");
+
+        // add the portal back
+        fixture.update(&gen3, &gen1);
+
+        assert_eq!(modals.children_to_html(), "<p>one</p>");
+
+        // removing the element containing a portal also removes the portal content
+        fixture.remove(&gen1);
+
+        assert_eq!(fixture.parent().children_to_html(), "");
+        assert_eq!(modals.children_to_html(), "");
+    }
+
+    /// Reorder a list of keyed fragments. Returns the number of fragments that were moved.
+    fn mock_keyed_fragments(old_keys: &[u32], new_keys: &[u32]) -> usize {
+        use crate::dom::Dom;
+        use crate::test::mock::Fixture;
+
+        let list = |keys: &[u32]| Dom::<Msg, Cmd, u32>::elem("dl")
+            .push(Dom::elem("dt").push("header"))
+            .extend(keys.iter().map(|k| Dom::fragment(vec![
+                Dom::elem("dt").push(k.to_string()),
+                Dom::elem("dd").push(k.to_string()),
+            ]).key(*k)))
+            .push(Dom::elem("dt").push("footer"));
+
+        let gen1 = list(old_keys);
+        let gen2 = list(new_keys);
+
+        let mut fixture = Fixture::new("div");
+        fixture.create(&gen1);
+
+        let dl = fixture.parent().children()[0].clone();
+        let old_nodes = dl.children();
+
+        let moves = fixture.patch(Some(&gen1), Some(&gen2), |patch_set| {
+            patch_set.keyed.values()
+                .flatten()
+                .filter(|p| matches!(p, Patch::MoveFragment(_)))
+                .count()
+        });
+
+        let mut html = "<dl><dt>header</dt>".to_owned();
+        for k in new_keys {
+            html.push_str(&format!("<dt>{0}</dt><dd>{0}</dd>", k));
+        }
+        html.push_str("<dt>footer</dt></dl>");
+        assert_eq!(fixture.parent().children_to_html(), html);
+
+        // nodes for keys that were not removed should have been reused
+        let new_nodes = dl.children();
+        for (i, k) in new_keys.iter().enumerate() {
+            if let Some(j) = old_keys.iter().position(|o| o == k) {
+                assert_eq!(new_nodes[i * 2 + 1], old_nodes[j * 2 + 1], "dt for key {} not reused", k);
+                assert_eq!(new_nodes[i * 2 + 2], old_nodes[j * 2 + 2], "dd for key {} not reused", k);
+            }
+        }
+
+        moves
+    }
+
+    #[test]
+    fn mock_keyed_fragments_unchanged() {
+        assert_eq!(mock_keyed_fragments(&[1, 2, 3], &[1, 2, 3]), 0);
+    }
+
+    #[test]
+    fn mock_keyed_fragments_reorder() {
+        assert_eq!(mock_keyed_fragments(&[1, 2, 3, 4], &[4, 1, 2, 3]), 1);
+        assert_eq!(mock_keyed_fragments(&[1, 2, 3, 4], &[4, 3, 2, 1]), 3);
+    }
+
+    #[test]
+    fn mock_keyed_fragments_insert_and_remove() {
+        assert_eq!(mock_keyed_fragments(&[1, 2, 3, 4, 5], &[6, 1, 3, 7, 5, 4]), 1);
+        assert_eq!(mock_keyed_fragments(&[1, 2, 3], &[]), 0);
+        assert_eq!(mock_keyed_fragments(&[], &[1, 2, 3]), 0);
+    }
+
+    thread_local! {
+        static RENDERED: std::cell::Cell<usize> = std::cell::Cell::new(0);
+    }
+
+    fn render_lazy_list(items: &Vec<String>) -> crate::dom::Dom<Msg, Cmd, Key> {
+        use crate::dom::Dom;
+
+        RENDERED.with(|r| r.set(r.get() + 1));
+        Dom::elem("ul")
+            .extend(items.iter().map(|i| Dom::elem("li").push(i.as_str())))
+    }
+
+    /// Apply a lazy sub tree, then diff and apply a second one. Returns the number of times a lazy
+    /// sub tree was rendered during the second diff, the resulting html, and whether the root of
+    /// the sub tree was reused.
+    fn mock_lazy(old: &[&str], new: &[&str]) -> (usize, String, bool) {
+        use crate::dom::Dom;
+        use crate::test::mock::Fixture;
+
+        let list = |items: &[&str]| Dom::<Msg, Cmd, Key>::elem("div")
+            .push(Dom::elem("h1").push("title"))
+            .push(Dom::lazy(items.iter().map(|i| i.to_string()).collect(), render_lazy_list));
+
+        let gen1 = list(old);
+        let gen2 = list(new);
+
+        let mut fixture = Fixture::new("div");
+        fixture.create(&gen1);
+
+        let ul = fixture.parent().children()[0].children()[1].clone();
+
+        RENDERED.with(|r| r.set(0));
+        let rendered = fixture.patch(Some(&gen1), Some(&gen2), |_| RENDERED.with(|r| r.get()));
+
+        let reused = fixture.parent().children()[0].children()[1] == ul;
+
+        // the new storage must describe the new dom, diff against it to verify that
+        fixture.update(&gen2, &gen2);
+
+        (rendered, fixture.parent().children_to_html(), reused)
+    }
+
+    #[test]
+    fn mock_lazy_unchanged() {
+        let (rendered, html, reused) = mock_lazy(&["a", "b"], &["a", "b"]);
+        assert_eq!(rendered, 0);
+        assert_eq!(html, "<div><h1>title</h1><ul><li>a</li><li>b</li></ul></div>");
+        assert!(reused);
+    }
+
+    #[test]
+    fn mock_lazy_changed() {
+        // the old sub tree was already rendered when it was created, only the new one is rendered
+        let (rendered, html, reused) = mock_lazy(&["a", "b"], &["a", "c", "d"]);
+        assert_eq!(rendered, 1);
+        assert_eq!(html, "<div><h1>title</h1><ul><li>a</li><li>c</li><li>d</li></ul></div>");
+        assert!(reused);
+    }
+
+    fn render_lazy_ordered_list(items: &Vec<String>) -> crate::dom::Dom<Msg, Cmd, Key> {
+        use crate::dom::Dom;
+
+        RENDERED.with(|r| r.set(r.get() + 1));
+        Dom::elem("ol")
+            .extend(items.iter().map(|i| Dom::elem("li").push(i.as_str())))
+    }
+
+    #[test]
+    fn mock_lazy_render_function_changed() {
+        use crate::dom::Dom;
+        use crate::test::mock::Fixture;
+
+        let items = || vec!["a".to_owned(), "b".to_owned()];
+        let gen1 = Dom::<Msg, Cmd, Key>::lazy(items(), render_lazy_list);
+        let gen2 = Dom::<Msg, Cmd, Key>::lazy(items(), render_lazy_ordered_list);
+
+        let mut fixture = Fixture::new("div");
+        fixture.create(&gen1);
+
+        // equal inputs rendered by different functions are different sub trees
+        RENDERED.with(|r| r.set(0));
+        let rendered = fixture.patch(Some(&gen1), Some(&gen2), |_| RENDERED.with(|r| r.get()));
+        assert_eq!(rendered, 1);
+        assert_eq!(fixture.parent().children_to_html(), "<ol><li>a</li><li>b</li></ol>");
+    }
+
+    fn render_lazy_item(item: &u32) -> crate::dom::Dom<Msg, Cmd, u32> {
+        use crate::dom::Dom;
+
+        RENDERED.with(|r| r.set(r.get() + 1));
+        Dom::elem("li").push(item.to_string())
+    }
+
+    #[test]
+    fn mock_lazy_keyed() {
+        use crate::dom::Dom;
+        use crate::test::mock::Fixture;
+        use crate::test::MockDom;
+
+        let list = |items: &[u32]| Dom::<Msg, Cmd, u32>::elem("ul")
+            .extend(items.iter().map(|i| Dom::lazy(*i, render_lazy_item)
+                .key(*i)
+                .node_ref(if *i == 1 { "first" } else { "other" })
+            ));
+
+        let gen1 = list(&[1, 2, 3]);
+        let gen2 = list(&[1, 2, 3]);
+        let gen3 = list(&[3, 1, 2]);
+
+        fn refs(patch_set: &PatchSet<Msg, Cmd, u32, MockDom>) -> Vec<String> {
+            patch_set.keyed.values()
+                .flatten()
+                .chain(patch_set.patches.iter())
+                .filter_map(|p| match *p {
+                    Patch::SetRef(name) => Some(name.to_owned()),
+                    _ => None,
+                })
+                .collect()
+        }
+
+        let mut fixture = Fixture::new("div");
+
+        // the ref of a lazy node is applied to the root of its sub tree
+        let mut set = fixture.patch(None, Some(&gen1), refs);
+        set.sort();
+        assert_eq!(set, vec!["first", "other", "other"]);
+
+        let ul = fixture.parent().children()[0].clone();
+        let old_items = ul.children();
+
+        // unchanged keyed lazy sub trees are not rendered
+        RENDERED.with(|r| r.set(0));
+        let rendered = fixture.patch(Some(&gen1), Some(&gen2), |_| RENDERED.with(|r| r.get()));
+        assert_eq!(rendered, 0);
+
+        // the root of a moved lazy sub tree carries its key
+        let moves = fixture.patch(Some(&gen2), Some(&gen3), |patch_set| {
+            patch_set.keyed.values()
+                .flatten()
+                .filter(|p| matches!(p, Patch::MoveElement(_)))
+                .count()
+        });
+        assert_eq!(moves, 1);
+
+        assert_eq!(fixture.parent().children_to_html(), "<ul><li>3</li><li>1</li><li>2</li></ul>");
+        assert_eq!(ul.children(), vec![old_items[2].clone(), old_items[0].clone(), old_items[1].clone()]);
+    }
 }
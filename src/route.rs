@@ -1,5 +1,11 @@
 //! Router trait for generating a message when the page url changes.
 
+pub mod router;
+pub mod navigate;
+
+pub use crate::route::router::{Router, Params, Pattern};
+pub use crate::route::navigate::Navigate;
+
 /// Implement this trait on your router to allow for routing when the URL changes.
 pub trait Route<Message> {
     /// Convert a new url to a message for the app.
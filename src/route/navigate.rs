@@ -0,0 +1,126 @@
+//! Side effects for navigating between pages of an app.
+
+use std::fmt;
+use wasm_bindgen::prelude::*;
+use crate::app::{Dispatcher, SideEffect};
+
+/// A navigation side effect.
+///
+/// Navigation updates the browser's history and then routes the new url with the [`Route`]
+/// registered with the app (see [`AppBuilder::router`]), dispatching the resulting message. Apps
+/// can include this in their own command type and process it from their [`SideEffect`]
+/// implementation.
+///
+/// ```no_run
+/// use euca::app::{Dispatcher, SideEffect};
+/// use euca::route::Navigate;
+///
+/// enum Command {
+///     Navigate(Navigate),
+/// }
+///
+/// impl SideEffect<Message> for Command {
+///     fn process(self, dispatcher: &Dispatcher<Message, Command>) {
+///         match self {
+///             Command::Navigate(nav) => nav.process(dispatcher),
+///         }
+///     }
+/// }
+/// # #[derive(Clone, PartialEq, Debug)]
+/// # struct Message;
+/// ```
+///
+/// [`Route`]: ../trait.Route.html
+/// [`AppBuilder::router`]: ../../app/struct.AppBuilder.html#method.router
+/// [`SideEffect`]: ../../app/side_effect/trait.SideEffect.html
+#[derive(Clone, PartialEq, Debug)]
+pub enum Navigate {
+    /// Add the given url to the history and navigate to it.
+    Push(String),
+    /// Replace the current history entry with the given url and navigate to it.
+    Replace(String),
+    /// Go back one page in the history.
+    Back,
+    /// Go forward one page in the history.
+    Forward,
+}
+
+impl Navigate {
+    /// Navigate, dispatching the routed message to the given dispatcher.
+    ///
+    /// The router is run after pushing or replacing the current url. Going back or forward
+    /// triggers a `popstate` event which the app's router already handles.
+    pub fn process<Message, Command>(self, dispatcher: &Dispatcher<Message, Command>)
+    where
+        Message: fmt::Debug + Clone + PartialEq + 'static,
+        Command: SideEffect<Message> + 'static,
+    {
+        let history = web_sys::window()
+            .expect_throw("couldn't get window handle")
+            .history()
+            .expect_throw("couldn't get history handle");
+
+        match self {
+            Navigate::Push(url) => {
+                history.push_state_with_url(&JsValue::NULL, "", Some(&url))
+                    .expect_throw("failed to push url to history");
+                dispatcher.route();
+            }
+            Navigate::Replace(url) => {
+                history.replace_state_with_url(&JsValue::NULL, "", Some(&url))
+                    .expect_throw("failed to replace url in history");
+                dispatcher.route();
+            }
+            Navigate::Back => {
+                history.back()
+                    .expect_throw("failed to go back in history");
+            }
+            Navigate::Forward => {
+                history.forward()
+                    .expect_throw("failed to go forward in history");
+            }
+        }
+    }
+}
+
+impl<Message> SideEffect<Message> for Navigate
+where
+    Message: fmt::Debug + Clone + PartialEq + 'static,
+{
+    fn process(self, dispatcher: &Dispatcher<Message, Self>) {
+        Navigate::process(self, dispatcher)
+    }
+}
+
+/// Returns true if the given link points to a page within this app, meaning it can be navigated
+/// to without loading a new page.
+pub(crate) fn is_internal(href: &str) -> bool {
+    if href.starts_with("//") {
+        return false;
+    }
+
+    // links with a scheme (e.g. `https:` or `mailto:`) are external
+    match href.find([':', '/', '?', '#']) {
+        Some(i) => !href[i..].starts_with(':'),
+        None => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn internal_links() {
+        assert!(is_internal("/users/1"));
+        assert!(is_internal("users/1"));
+        assert!(is_internal("#/active"));
+        assert!(is_internal("?page=2"));
+        assert!(is_internal("/a:b"));
+        assert!(is_internal(""));
+
+        assert!(!is_internal("https://example.com/"));
+        assert!(!is_internal("//example.com/"));
+        assert!(!is_internal("mailto:someone@example.com"));
+    }
+}
@@ -0,0 +1,515 @@
+//! A router that matches urls against path patterns.
+//!
+//! Patterns are made of `/` separated segments. A segment can be a literal, a named parameter
+//! like `:id` which matches any single segment, or a named rest parameter like `*rest` which
+//! matches all remaining segments and must be the last segment in the pattern.
+//!
+//! ```
+//! use euca::route::{Route, Router, Params};
+//!
+//! #[derive(Debug, PartialEq)]
+//! enum Message {
+//!     Home,
+//!     Post(u32, u32),
+//!     File(String),
+//! }
+//!
+//! let router = Router::new()
+//!     .add("/", |_| Some(Message::Home))
+//!     .add("/users/:id/posts/:post", |p| Some(Message::Post(p.get("id")?, p.get("post")?)))
+//!     .reverse(|msg| match msg {
+//!         Message::Post(id, post) => Some(Params::new().with("id", id).with("post", post)),
+//!         _ => None,
+//!     })
+//!     .add("/files/*path", |p| Some(Message::File(p.get("path")?)));
+//!
+//! assert_eq!(router.route("http://localhost/users/1/posts/2"), Some(Message::Post(1, 2)));
+//! assert_eq!(router.route("http://localhost/files/a/b.txt"), Some(Message::File("a/b.txt".into())));
+//! assert_eq!(router.url(&Message::Post(3, 4)), Some("/users/3/posts/4".into()));
+//! ```
+
+use std::fmt;
+use std::str::FromStr;
+use crate::route::Route;
+
+/// A segment of a path pattern.
+#[derive(Debug, PartialEq)]
+enum Segment {
+    /// A literal segment, it must match exactly.
+    Literal(String),
+    /// A named parameter matching a single segment.
+    Param(String),
+    /// A named parameter matching all of the remaining segments.
+    Rest(String),
+}
+
+/// A path pattern like `/users/:id/posts/*rest`.
+#[derive(Debug, PartialEq)]
+pub struct Pattern(Vec<Segment>);
+
+impl Pattern {
+    /// Parse the given pattern.
+    ///
+    /// # Panics
+    ///
+    /// Panics if a rest parameter is not the last segment of the pattern.
+    pub fn new(pattern: &str) -> Self {
+        let segments: Vec<_> = pattern.split('/')
+            .filter(|s| !s.is_empty())
+            .map(|s| {
+                if let Some(name) = s.strip_prefix(':') {
+                    Segment::Param(name.to_owned())
+                }
+                else if let Some(name) = s.strip_prefix('*') {
+                    Segment::Rest(name.to_owned())
+                }
+                else {
+                    Segment::Literal(decode(s, false))
+                }
+            })
+            .collect();
+
+        if let Some(i) = segments.iter().position(|s| matches!(s, Segment::Rest(_))) {
+            assert!(i == segments.len() - 1, "rest parameters must be the last segment of a pattern: {}", pattern);
+        }
+
+        Pattern(segments)
+    }
+
+    /// Match the given path against this pattern, returning the values of the parameters.
+    fn matches(&self, path: &str) -> Option<Vec<(String, String)>> {
+        let mut segments = path.split('/').filter(|s| !s.is_empty());
+        let mut params = vec![];
+
+        for segment in self.0.iter() {
+            match segment {
+                Segment::Literal(literal) => {
+                    if decode(segments.next()?, false) != *literal {
+                        return None;
+                    }
+                }
+                Segment::Param(name) => {
+                    params.push((name.clone(), decode(segments.next()?, false)));
+                }
+                Segment::Rest(name) => {
+                    let rest: Vec<_> = segments.by_ref()
+                        .map(|s| decode(s, false))
+                        .collect();
+                    params.push((name.clone(), rest.join("/")));
+                }
+            }
+        }
+
+        match segments.next() {
+            Some(_) => None,
+            None => Some(params),
+        }
+    }
+
+    /// Generate a path from this pattern using the given parameters.
+    ///
+    /// Returns `None` if a parameter is missing.
+    pub fn path(&self, params: &Params) -> Option<String> {
+        let mut path = String::new();
+
+        for segment in self.0.iter() {
+            path.push('/');
+            match segment {
+                Segment::Literal(literal) => encode_to(literal, "", &mut path),
+                Segment::Param(name) => encode_to(params.param(name)?, "", &mut path),
+                Segment::Rest(name) => encode_to(params.param(name)?, "/", &mut path),
+            }
+        }
+
+        if path.is_empty() {
+            path.push('/');
+        }
+
+        Some(path)
+    }
+}
+
+/// The parameters, query string, and fragment of a url.
+#[derive(Debug, Default, PartialEq)]
+pub struct Params {
+    params: Vec<(String, String)>,
+    query: Vec<(String, String)>,
+    fragment: Option<String>,
+}
+
+impl Params {
+    /// Create an empty set of parameters, used to generate a url.
+    pub fn new() -> Self {
+        Params::default()
+    }
+
+    /// Add a path parameter.
+    pub fn with(mut self, name: &str, value: impl fmt::Display) -> Self {
+        self.params.push((name.to_owned(), value.to_string()));
+        self
+    }
+
+    /// Add a query string parameter.
+    pub fn with_query(mut self, name: &str, value: impl fmt::Display) -> Self {
+        self.query.push((name.to_owned(), value.to_string()));
+        self
+    }
+
+    /// Set the fragment.
+    pub fn with_fragment(mut self, fragment: impl fmt::Display) -> Self {
+        self.fragment = Some(fragment.to_string());
+        self
+    }
+
+    /// Get the value of the given path parameter, converted to the requested type.
+    ///
+    /// Returns `None` if there is no such parameter or if it could not be converted.
+    pub fn get<T: FromStr>(&self, name: &str) -> Option<T> {
+        self.param(name)?.parse().ok()
+    }
+
+    /// Get the value of the given query string parameter, converted to the requested type.
+    ///
+    /// If the parameter is present multiple times, the first value is used.
+    pub fn query<T: FromStr>(&self, name: &str) -> Option<T> {
+        self.query.iter()
+            .find(|(n, _)| n == name)
+            .and_then(|(_, v)| v.parse().ok())
+    }
+
+    /// The fragment of the url (without the leading `#`), if any.
+    pub fn fragment(&self) -> Option<&str> {
+        self.fragment.as_deref()
+    }
+
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+/// Convert the parameters of a matching url to a message.
+type ToMessage<Message> = Box<dyn Fn(&Params) -> Option<Message>>;
+
+/// Convert a message to the parameters of a url.
+type ToParams<Message> = Box<dyn Fn(&Message) -> Option<Params>>;
+
+/// A route, a pattern and the functions to convert it to and from a message.
+struct Entry<Message> {
+    pattern: Pattern,
+    to_message: ToMessage<Message>,
+    to_params: Option<ToParams<Message>>,
+}
+
+/// A [`Route`] implementation that matches urls against a list of patterns.
+///
+/// Routes are tried in the order they were added. The first route with a pattern that matches the
+/// url and a handler that returns a message is used.
+///
+/// [`Route`]: ../trait.Route.html
+pub struct Router<Message> {
+    routes: Vec<Entry<Message>>,
+    fragment: bool,
+}
+
+impl<Message> Default for Router<Message> {
+    fn default() -> Self {
+        Router {
+            routes: vec![],
+            fragment: false,
+        }
+    }
+}
+
+impl<Message> Router<Message> {
+    /// Create an empty router.
+    pub fn new() -> Self {
+        Router::default()
+    }
+
+    /// Match patterns against the fragment of the url (e.g. `/#/users/1`) instead of the path.
+    ///
+    /// This is useful for apps that use hash based routing. Any query string in the fragment is
+    /// parsed as the query string.
+    #[must_use]
+    pub fn fragment(mut self) -> Self {
+        self.fragment = true;
+        self
+    }
+
+    /// Add a route with the given pattern. The given function is called with the parameters of
+    /// matching urls to generate a message.
+    #[must_use]
+    pub fn add(mut self, pattern: &str, to_message: impl Fn(&Params) -> Option<Message> + 'static) -> Self {
+        self.routes.push(Entry {
+            pattern: Pattern::new(pattern),
+            to_message: Box::new(to_message),
+            to_params: None,
+        });
+        self
+    }
+
+    /// Allow urls to be generated for the most recently added route. The given function should
+    /// return the parameters for messages that belong to this route.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no routes have been added.
+    #[must_use]
+    pub fn reverse(mut self, to_params: impl Fn(&Message) -> Option<Params> + 'static) -> Self {
+        self.routes.last_mut()
+            .expect("reverse must be called after adding a route")
+            .to_params = Some(Box::new(to_params));
+        self
+    }
+
+    /// Generate a url for the given message.
+    ///
+    /// Returns `None` if no route can generate a url for this message.
+    pub fn url(&self, msg: &Message) -> Option<String> {
+        self.routes.iter()
+            .find_map(|entry| {
+                let params = entry.to_params.as_ref()?(msg)?;
+                let path = entry.pattern.path(&params)?;
+                Some(self.format(path, &params))
+            })
+    }
+
+    /// Format the given path, query, and fragment as a url.
+    fn format(&self, path: String, params: &Params) -> String {
+        let mut url = String::new();
+
+        if self.fragment {
+            url.push('#');
+        }
+        url.push_str(&path);
+
+        for (i, (name, value)) in params.query.iter().enumerate() {
+            url.push(if i == 0 { '?' } else { '&' });
+            encode_to(name, "", &mut url);
+            url.push('=');
+            encode_to(value, "", &mut url);
+        }
+
+        if let Some(fragment) = &params.fragment {
+            if !self.fragment {
+                url.push('#');
+                encode_to(fragment, "/?", &mut url);
+            }
+        }
+
+        url
+    }
+
+    /// Split the given url into a path and its parameters.
+    fn parse<'u>(&self, url: &'u str) -> (&'u str, Params) {
+        // strip the scheme and host, a "://" after the start of the path, query, or fragment is
+        // part of those instead
+        let start = url.find(['/', '?', '#']).unwrap_or(url.len());
+        let url = match url.find("://").filter(|&i| i < start) {
+            Some(i) => {
+                let rest = &url[i + 3..];
+                &rest[rest.find(['/', '?', '#']).unwrap_or(rest.len())..]
+            }
+            None => url,
+        };
+
+        let (url, fragment) = match url.find('#') {
+            Some(i) => (&url[..i], Some(&url[i + 1..])),
+            None => (url, None),
+        };
+
+        let (path, query) = match (self.fragment, fragment) {
+            (true, fragment) => split_query(fragment.unwrap_or("")),
+            (false, _) => split_query(url),
+        };
+
+        let params = Params {
+            params: vec![],
+            query: query.map(parse_query).unwrap_or_default(),
+            fragment: if self.fragment { None } else { fragment.map(|f| decode(f, false)) },
+        };
+
+        (path, params)
+    }
+}
+
+impl<Message> Route<Message> for Router<Message> {
+    fn route(&self, url: &str) -> Option<Message> {
+        let (path, mut params) = self.parse(url);
+
+        self.routes.iter()
+            .find_map(|entry| {
+                params.params = entry.pattern.matches(path)?;
+                (entry.to_message)(&params)
+            })
+    }
+}
+
+/// Split a path from its query string.
+fn split_query(url: &str) -> (&str, Option<&str>) {
+    match url.find('?') {
+        Some(i) => (&url[..i], Some(&url[i + 1..])),
+        None => (url, None),
+    }
+}
+
+/// Parse the key value pairs of a query string.
+fn parse_query(query: &str) -> Vec<(String, String)> {
+    query.split('&')
+        .filter(|s| !s.is_empty())
+        .map(|pair| {
+            let (name, value) = match pair.find('=') {
+                Some(i) => (&pair[..i], &pair[i + 1..]),
+                None => (pair, ""),
+            };
+            (decode(name, true), decode(value, true))
+        })
+        .collect()
+}
+
+/// Decode a percent encoded string. Optionally decode `+` as a space, as is done in query strings.
+fn decode(value: &str, plus: bool) -> String {
+    let bytes = value.as_bytes();
+    let mut decoded = Vec::with_capacity(bytes.len());
+
+    let hex = |i: usize| bytes.get(i).and_then(|b| (*b as char).to_digit(16));
+
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], hex(i + 1), hex(i + 2)) {
+            (b'%', Some(high), Some(low)) => {
+                decoded.push((high * 16 + low) as u8);
+                i += 3;
+                continue;
+            }
+            (b'+', _, _) if plus => decoded.push(b' '),
+            (b, _, _) => decoded.push(b),
+        }
+        i += 1;
+    }
+
+    String::from_utf8_lossy(&decoded).into_owned()
+}
+
+/// Percent encode the given value, appending it to the given url. Unreserved characters and the
+/// given additional characters are not encoded.
+fn encode_to(value: &str, allowed: &str, url: &mut String) {
+    for b in value.bytes() {
+        match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => url.push(b as char),
+            b if allowed.as_bytes().contains(&b) => url.push(b as char),
+            b => url.push_str(&format!("%{:02X}", b)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Debug, PartialEq)]
+    enum Msg {
+        Home,
+        User(u32),
+        Post(u32, String),
+        Search(String, Option<u32>),
+        File(String),
+    }
+
+    fn router() -> Router<Msg> {
+        Router::new()
+            .add("/", |_| Some(Msg::Home))
+            .add("/users/:id", |p| Some(Msg::User(p.get("id")?)))
+            .reverse(|m| match m {
+                Msg::User(id) => Some(Params::new().with("id", id)),
+                _ => None,
+            })
+            .add("/users/:id/posts/:title", |p| Some(Msg::Post(p.get("id")?, p.get("title")?)))
+            .reverse(|m| match m {
+                Msg::Post(id, title) => Some(Params::new().with("id", id).with("title", title)),
+                _ => None,
+            })
+            .add("/search", |p| Some(Msg::Search(p.query("q")?, p.query("page"))))
+            .reverse(|m| match m {
+                Msg::Search(q, None) => Some(Params::new().with_query("q", q)),
+                Msg::Search(q, Some(page)) => Some(Params::new().with_query("q", q).with_query("page", page)),
+                _ => None,
+            })
+            .add("/files/*path", |p| Some(Msg::File(p.get("path")?)))
+            .reverse(|m| match m {
+                Msg::File(path) => Some(Params::new().with("path", path)),
+                _ => None,
+            })
+    }
+
+    #[test]
+    fn route_params() {
+        let router = router();
+
+        assert_eq!(router.route("http://localhost:8080"), Some(Msg::Home));
+        assert_eq!(router.route("http://localhost:8080/"), Some(Msg::Home));
+        assert_eq!(router.route("http://localhost:8080/users/42"), Some(Msg::User(42)));
+        assert_eq!(router.route("/users/42/"), Some(Msg::User(42)));
+        assert_eq!(router.route("/users/42/posts/hello%20world"), Some(Msg::Post(42, "hello world".into())));
+        assert_eq!(router.route("/users/nope"), None);
+        assert_eq!(router.route("/users/42/extra"), None);
+    }
+
+    #[test]
+    fn route_rest() {
+        let router = router();
+
+        assert_eq!(router.route("/files/a/b/c.txt"), Some(Msg::File("a/b/c.txt".into())));
+        assert_eq!(router.route("/files"), Some(Msg::File("".into())));
+    }
+
+    #[test]
+    fn route_query_and_fragment() {
+        let router = router();
+
+        assert_eq!(router.route("http://localhost/search?q=a+b&page=2#top"), Some(Msg::Search("a b".into(), Some(2))));
+        assert_eq!(router.route("/search?page=x&q=%26"), Some(Msg::Search("&".into(), None)));
+        assert_eq!(router.route("/search"), None);
+        assert_eq!(router.route("/search?q=http://x"), Some(Msg::Search("http://x".into(), None)));
+        assert_eq!(router.route("http://localhost/search?q=http://x#a://b"), Some(Msg::Search("http://x".into(), None)));
+
+        let (path, params) = router.parse("http://localhost/users/1?a=b#frag%20ment");
+        assert_eq!(path, "/users/1");
+        assert_eq!(params.fragment(), Some("frag ment"));
+        assert_eq!(params.query::<String>("a"), Some("b".into()));
+    }
+
+    #[test]
+    fn route_fragment_mode() {
+        let router = router().fragment();
+
+        assert_eq!(router.route("http://localhost/"), Some(Msg::Home));
+        assert_eq!(router.route("http://localhost/#/users/7"), Some(Msg::User(7)));
+        assert_eq!(router.route("http://localhost/users/7"), Some(Msg::Home));
+        assert_eq!(router.url(&Msg::User(7)), Some("#/users/7".into()));
+    }
+
+    #[test]
+    fn generate_urls() {
+        let router = router();
+
+        assert_eq!(router.url(&Msg::Home), None);
+        assert_eq!(router.url(&Msg::User(5)), Some("/users/5".into()));
+        assert_eq!(router.url(&Msg::Post(5, "a b/c".into())), Some("/users/5/posts/a%20b%2Fc".into()));
+        assert_eq!(router.url(&Msg::Search("a&b".into(), Some(3))), Some("/search?q=a%26b&page=3".into()));
+        assert_eq!(router.url(&Msg::File("a/b c".into())), Some("/files/a/b%20c".into()));
+
+        // generated urls route back to the same message
+        for msg in vec![Msg::User(5), Msg::Post(5, "a b/c".into()), Msg::Search("a&b".into(), None), Msg::File("x/y".into())] {
+            assert_eq!(router.route(&router.url(&msg).unwrap()), Some(msg));
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn rest_not_last() {
+        Pattern::new("/files/*path/more");
+    }
+}
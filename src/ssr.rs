@@ -0,0 +1,431 @@
+//! Render a virtual dom to an html string.
+//!
+//! This allows a virtual dom to be pre-rendered outside of the browser, for example on a server
+//! to provide the initial contents of a page. Any virtual dom representation that implements
+//! [`DomIter`] can be rendered.
+//!
+//! Event handlers and properties are not represented in html and are ignored, they are set up
+//! when the page is hydrated. Components create their own dom in the browser and are rendered as
+//! empty. Portals render into another element in the browser and are also rendered as empty.
+//!
+//! [`DomIter`]: ../vdom/trait.DomIter.html
+
+use crate::vdom::{DomItem, Namespace};
+
+/// Render the given sequence of [`DomItem`]s to an html string.
+///
+/// Text and attribute values are escaped, except for the text of html `script` and `style`
+/// elements, raw html from [`DomItem::UnsafeInnerHtml`] is emitted as is. Boolean attributes like `disabled` are emitted without a value when `"true"` and left out
+/// when `"false"`. Explicit `class` and `style` attributes are merged with the element's classes and
+/// styles.
+///
+/// [`DomItem`]: ../vdom/enum.DomItem.html
+/// [`DomItem::UnsafeInnerHtml`]: ../vdom/enum.DomItem.html#variant.UnsafeInnerHtml
+pub fn render<'a, Message, Command, K, I>(items: I) -> String
+where
+    Message: 'a,
+    K: 'a,
+    I: IntoIterator<Item = DomItem<'a, Message, Command, K>>,
+{
+    let mut html = String::new();
+    render_to(items, &mut html);
+    html
+}
+
+/// Render the given sequence of [`DomItem`]s, appending the html to the given string.
+///
+/// [`DomItem`]: ../vdom/enum.DomItem.html
+pub fn render_to<'a, Message, Command, K, I>(items: I, html: &mut String)
+where
+    Message: 'a,
+    K: 'a,
+    I: IntoIterator<Item = DomItem<'a, Message, Command, K>>,
+{
+    render_in(items, Parent::default(), html);
+}
+
+/// Render the given sequence of [`DomItem`]s into the given parent element.
+///
+/// [`DomItem`]: ../vdom/enum.DomItem.html
+fn render_in<'a, Message, Command, K, I>(items: I, root: Parent<'a>, html: &mut String)
+where
+    Message: 'a,
+    K: 'a,
+    I: IntoIterator<Item = DomItem<'a, Message, Command, K>>,
+{
+    // the nodes we are in, along with the element they are in, the name is `None` for nodes that
+    // don't need a closing tag
+    let mut stack: Vec<(Option<&str>, Parent)> = vec![];
+    // the start tag of the last element, if it has not been closed yet
+    let mut tag = StartTag::default();
+    // the depth of the nodes we are in inside of a portal, these are not rendered
+    let mut portal = 0;
+
+    for item in items {
+        if portal > 0 {
+            match item {
+                DomItem::Element { .. } | DomItem::Text(_) | DomItem::Component { .. }
+                | DomItem::Fragment { .. } | DomItem::Portal { .. } => portal += 1,
+                DomItem::Up => portal -= 1,
+                _ => {}
+            }
+            continue;
+        }
+
+        match item {
+            DomItem::Attr { name, value } => {
+                if !tag.open {
+                    continue;
+                }
+
+                match name {
+                    // merged with the classes and styles of the element
+                    "class" => tag.classes.push(value),
+                    "style" => {
+                        let value = value.trim().trim_end_matches(';');
+                        if !value.is_empty() {
+                            tag.styles.push(format!("{};", value));
+                        }
+                    }
+                    // boolean attributes are either present or missing
+                    _ if is_boolean(name) && value == "false" => {}
+                    _ if is_boolean(name) && (value == "true" || value == name) => {
+                        html.push(' ');
+                        html.push_str(name);
+                    }
+                    _ => {
+                        html.push(' ');
+                        html.push_str(name);
+                        html.push_str("=\"");
+                        escape_to(value, true, html);
+                        html.push('"');
+                    }
+                }
+            }
+            DomItem::Class(class) => {
+                if tag.open {
+                    tag.classes.push(class);
+                }
+            }
+            DomItem::Style { property, value } => {
+                if tag.open {
+                    tag.styles.push(format!("{}: {};", property, value));
+                }
+            }
+            DomItem::Property { .. } | DomItem::Ref(_) | DomItem::Hook { .. } | DomItem::Event { .. }
+            | DomItem::Key(_) => {}
+            DomItem::Element { name, namespace, .. } => {
+                tag.close(html);
+                html.push('<');
+                html.push_str(name);
+                tag.open = true;
+
+                let parent = stack.last().map_or(root, |(_, parent)| *parent);
+                let namespace = namespace.unwrap_or_else(|| parent.namespace.child(parent.name, name));
+                let close = if is_void(name, namespace) { None } else { Some(name) };
+                stack.push((close, Parent { name, namespace }));
+            }
+            DomItem::Text(text) => {
+                tag.close(html);
+                let parent = stack.last().map_or(root, |(_, parent)| *parent);
+                if parent.is_raw_text() {
+                    html.push_str(text);
+                }
+                else {
+                    escape_to(text, false, html);
+                }
+                stack.push((None, parent));
+            }
+            DomItem::UnsafeInnerHtml(raw) => {
+                tag.close(html);
+                html.push_str(raw);
+            }
+            DomItem::Component { .. } | DomItem::Fragment { .. } => {
+                tag.close(html);
+                let parent = stack.last().map_or(root, |(_, parent)| *parent);
+                stack.push((None, parent));
+            }
+            DomItem::Lazy { dom, .. } => {
+                tag.close(html);
+                let parent = stack.last().map_or(root, |(_, parent)| *parent);
+                render_in(dom.dom_iter(), parent, html);
+            }
+            DomItem::Portal { .. } => {
+                tag.close(html);
+                portal = 1;
+            }
+            DomItem::Up => {
+                tag.close(html);
+                if let (Some(name), _) = stack.pop().expect("unexpected Up") {
+                    html.push_str("</");
+                    html.push_str(name);
+                    html.push('>');
+                }
+            }
+        }
+    }
+
+    tag.close(html);
+}
+
+/// The element nodes are rendered in, the name is empty at the root.
+#[derive(Default, Clone, Copy)]
+struct Parent<'a> {
+    name: &'a str,
+    namespace: Namespace,
+}
+
+impl Parent<'_> {
+    /// Returns true if the text of this element is not escaped.
+    fn is_raw_text(&self) -> bool {
+        self.namespace == Namespace::Html && matches!(self.name, "script" | "style")
+    }
+}
+
+/// The start tag of an element being rendered.
+///
+/// Classes and styles, including those from explicit `class` and `style` attributes, are collected
+/// until the tag is closed and rendered as a single `class` and `style` attribute.
+#[derive(Default)]
+struct StartTag<'a> {
+    open: bool,
+    classes: Vec<&'a str>,
+    styles: Vec<String>,
+}
+
+impl<'a> StartTag<'a> {
+    fn close(&mut self, html: &mut String) {
+        if !self.open {
+            return;
+        }
+
+        if !self.classes.is_empty() {
+            html.push_str(" class=\"");
+            escape_to(&self.classes.join(" "), true, html);
+            html.push('"');
+            self.classes.clear();
+        }
+
+        if !self.styles.is_empty() {
+            html.push_str(" style=\"");
+            escape_to(&self.styles.join(" "), true, html);
+            html.push('"');
+            self.styles.clear();
+        }
+
+        html.push('>');
+        self.open = false;
+    }
+}
+
+/// Returns true if the given element is a void element, meaning it has no closing tag and cannot
+/// contain children. Only html elements can be void, svg and MathML elements are always closed.
+pub(crate) fn is_void(name: &str, namespace: Namespace) -> bool {
+    namespace == Namespace::Html && matches!(name,
+        "area" | "base" | "br" | "col" | "embed" | "hr" | "img" | "input"
+        | "link" | "meta" | "param" | "source" | "track" | "wbr"
+    )
+}
+
+/// Returns true if the given attribute is a boolean attribute, meaning its presence alone sets it.
+/// The vdom sets these to `"true"` or `"false"`.
+fn is_boolean(name: &str) -> bool {
+    matches!(name,
+        "allowfullscreen" | "async" | "autofocus" | "autoplay" | "checked" | "controls"
+        | "default" | "defer" | "disabled" | "formnovalidate" | "hidden" | "inert" | "ismap"
+        | "itemscope" | "loop" | "multiple" | "muted" | "nomodule" | "novalidate" | "open"
+        | "playsinline" | "readonly" | "required" | "reversed" | "selected"
+    )
+}
+
+/// Escape the given text for use in html, appending the result to the given string. Attribute
+/// values additionally have their quotes escaped.
+pub(crate) fn escape_to(value: &str, attribute: bool, html: &mut String) {
+    for c in value.chars() {
+        match c {
+            '&' => html.push_str("&amp;"),
+            '<' => html.push_str("&lt;"),
+            '>' => html.push_str("&gt;"),
+            '"' if attribute => html.push_str("&quot;"),
+            c => html.push(c),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::dom::{Dom, DomVec};
+    use crate::vdom::DomIter;
+
+    #[test]
+    fn render_nested() {
+        let dom: Dom<(), (), ()> = Dom::elem("div")
+            .attr("class", "container")
+            .event("click", ())
+            .push(Dom::elem("span").push("text"))
+            .push(Dom::elem("p"));
+
+        assert_eq!(
+            render(dom.dom_iter()),
+            r#"<div class="container"><span>text</span><p></p></div>"#,
+        );
+    }
+
+    #[test]
+    fn render_fragment() {
+        let dom: Dom<(), (), ()> = Dom::elem("tr")
+            .push(Dom::fragment(vec![
+                Dom::elem("td").push("a"),
+                Dom::elem("td").push("b"),
+            ]))
+            .push(Dom::elem("td").push("c"));
+
+        assert_eq!(
+            render(dom.dom_iter()),
+            r#"<tr><td>a</td><td>b</td><td>c</td></tr>"#,
+        );
+    }
+
+    #[test]
+    fn render_portal() {
+        let dom: Dom<(), (), ()> = Dom::elem("div")
+            .push(Dom::portal("body", vec![
+                Dom::elem("div").attr("class", "modal").push("modal"),
+            ]))
+            .push("text");
+
+        assert_eq!(render(dom.dom_iter()), "<div>text</div>");
+    }
+
+    #[test]
+    fn render_escaped() {
+        let dom: Dom<(), (), ()> = Dom::elem("div")
+            .attr("title", r#"a "quoted" <value> & more"#)
+            .push("<script>alert(\"&\")</script>");
+
+        assert_eq!(
+            render(dom.dom_iter()),
+            r#"<div title="a &quot;quoted&quot; &lt;value&gt; &amp; more">&lt;script&gt;alert("&amp;")&lt;/script&gt;</div>"#,
+        );
+    }
+
+    #[test]
+    fn render_void() {
+        let dom: DomVec<(), (), ()> = vec![
+            Dom::elem("input").attr("value", "v"),
+            Dom::elem("br"),
+            Dom::elem("img").attr("src", "a.png"),
+        ].into();
+
+        assert_eq!(
+            render(dom.dom_iter()),
+            r#"<input value="v"><br><img src="a.png">"#,
+        );
+    }
+
+    #[test]
+    fn render_void_namespace() {
+        let dom: Dom<(), (), ()> = Dom::elem("svg")
+            .push(Dom::elem("source"))
+            .push(Dom::elem("foreignObject").push(Dom::elem("br")));
+
+        assert_eq!(
+            render(dom.dom_iter()),
+            r#"<svg><source></source><foreignObject><br></foreignObject></svg>"#,
+        );
+    }
+
+    #[test]
+    fn render_raw_text() {
+        let dom: DomVec<(), (), ()> = vec![
+            Dom::elem("script").push("if (a < b && c > d) {}"),
+            Dom::elem("style").push("a > b { content: \"&\"; }"),
+            Dom::elem("svg").push(Dom::elem("style").push("a > b {}")),
+        ].into();
+
+        assert_eq!(
+            render(dom.dom_iter()),
+            r#"<script>if (a < b && c > d) {}</script><style>a > b { content: "&"; }</style><svg><style>a &gt; b {}</style></svg>"#,
+        );
+    }
+
+    #[test]
+    fn render_inner_html() {
+        let dom: Dom<(), (), ()> = unsafe {
+            Dom::elem("div")
+                .attr("id", "raw")
+                .inner_html("<b>bold</b>")
+        };
+
+        assert_eq!(
+            render(dom.dom_iter()),
+            r#"<div id="raw"><b>bold</b></div>"#,
+        );
+    }
+
+    #[test]
+    fn render_html_macro() {
+        let items = vec!["a", "b"];
+        let dom: DomVec<(), (), ()> = crate::html! {
+            <ul class="list">
+                {..items.iter().map(|item| crate::html! { <li onclick={()}>{*item}</li> })}
+            </ul>
+            <input type="text" value={String::from("v")} disabled>
+            "text"
+        };
+
+        assert_eq!(
+            render(dom.dom_iter()),
+            r#"<ul class="list"><li>a</li><li>b</li></ul><input type="text" value="v" disabled>text"#,
+        );
+    }
+
+    #[test]
+    fn render_class_and_style() {
+        let dom: Dom<(), (), ()> = Dom::elem("div")
+            .attr("id", "main")
+            .class("a b")
+            .class_if("c", false)
+            .style("color", "red")
+            .style("content", r#""quoted""#)
+            .push(Dom::elem("span").class("inner"));
+
+        assert_eq!(
+            render(dom.dom_iter()),
+            r#"<div id="main" class="a b" style="color: red; content: &quot;quoted&quot;;"><span class="inner"></span></div>"#,
+        );
+    }
+
+    #[test]
+    fn render_boolean_attributes() {
+        let dom: DomVec<(), (), ()> = vec![
+            Dom::elem("input").attr("checked", "true").attr("disabled", "false"),
+            Dom::elem("option").attr("selected", "selected").attr("hidden", "false"),
+            Dom::elem("div").attr("draggable", "false"),
+            Dom::elem("select").attr("multiple", "true").attr("required", "true").attr("autofocus", "false"),
+            Dom::elem("textarea").attr("readonly", "readonly"),
+            Dom::elem("details").attr("open", "true"),
+            Dom::elem("script").attr("async", "true").attr("defer", "false"),
+        ].into();
+
+        assert_eq!(
+            render(dom.dom_iter()),
+            r#"<input checked><option selected></option><div draggable="false"></div><select multiple required></select><textarea readonly></textarea><details open></details><script async></script>"#,
+        );
+    }
+
+    #[test]
+    fn render_class_and_style_attributes() {
+        let dom: Dom<(), (), ()> = Dom::elem("div")
+            .attr("class", "a b")
+            .attr("style", "color: red;")
+            .class("c")
+            .style("width", "1px");
+
+        assert_eq!(
+            render(dom.dom_iter()),
+            r#"<div class="a b c" style="color: red; width: 1px;"></div>"#,
+        );
+    }
+}
@@ -1,5 +1,9 @@
 //! Test utilties.
 
+pub mod mock;
+
+pub use crate::test::mock::MockDom;
+
 use crate::app::Application;
 use crate::app::ScheduledRender;
 use crate::app::Dispatcher;
@@ -50,6 +54,7 @@ impl Application<Msg, Cmd> for App {
         self.messages.borrow_mut().push(msg);
         Commands::default()
     }
+    fn subscribe(&mut self, _app: &Dispatcher<Msg, Cmd>) { }
     fn render(&mut self, _app: &Dispatcher<Msg, Cmd>) -> Vec<Cmd> { vec![] }
     fn process(&self, _cmd: Cmd, _app: &Dispatcher<Msg, Cmd>) { }
     fn get_scheduled_render(&mut self) -> &mut Option<ScheduledRender<Cmd>> {
@@ -62,6 +67,7 @@ impl Application<Msg, Cmd> for App {
     fn node(&self) -> Option<web_sys::Node> { None }
     fn nodes(&self) -> Vec<web_sys::Node> { vec![] }
     fn create(&mut self, _app: &Dispatcher<Msg, Cmd>) -> Vec<web_sys::Node> { vec![] }
+    fn hydrate(&mut self, _parent: &web_sys::Element, _app: &Dispatcher<Msg, Cmd>) { }
     fn detach(&mut self, _app: &Dispatcher<Msg, Cmd>) { }
 }
 
@@ -74,6 +80,14 @@ pub trait Model<Message, Command> {
     /// function automatically does that and returns the resulting `Commands` structure. It's only
     /// useful for unit testing.
     fn test_update(&mut self, msg: Message) -> Commands<Command>;
+
+    /// Update a model with each of the given messages in order.
+    ///
+    /// This is useful for replaying a [`MessageLog`] captured from a running app as a regression
+    /// test. The commands generated by every update are collected and returned.
+    ///
+    /// [`MessageLog`]: ../app/history/struct.MessageLog.html
+    fn test_replay<I: IntoIterator<Item = Message>>(&mut self, msgs: I) -> Commands<Command>;
 }
 
 impl<Message, Command, M: Update<Message, Command>> Model<Message, Command> for M {
@@ -82,4 +96,12 @@ impl<Message, Command, M: Update<Message, Command>> Model<Message, Command> for
         Update::update(self, msg, &mut cmds);
         cmds
     }
+
+    fn test_replay<I: IntoIterator<Item = Message>>(&mut self, msgs: I) -> Commands<Command> {
+        let mut cmds = Commands::default();
+        for msg in msgs {
+            Update::update(self, msg, &mut cmds);
+        }
+        cmds
+    }
 }
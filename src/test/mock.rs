@@ -0,0 +1,648 @@
+//! An in-memory dom for applying patches without a browser.
+
+use std::fmt;
+use std::hash::Hash;
+use std::rc::{Rc, Weak};
+use std::cell::RefCell;
+use crate::backend::Backend;
+use crate::vdom::{BoundHook, DomIter, EventHandler, Hook, Lifecycle, ListenerOptions, Namespace, PortalTarget, PropertyValue, Storage};
+use crate::app::{Dispatcher, SideEffect};
+use crate::patch::PatchSet;
+use crate::ssr::{escape_to, is_void};
+use crate::test::{App, Msg, Cmd};
+use crate::diff;
+
+/// A [`Backend`] that applies patches to an in-memory dom tree made of [`Node`]s.
+///
+/// Event listeners are recorded on the elements they were added to, but never fire. Lifecycle hooks
+/// are never called either, instead each call is recorded, see [`take_hook_calls`]. Portal target
+/// selectors are resolved in the [`document`].
+///
+/// [`Backend`]: ../../backend/trait.Backend.html
+/// [`Node`]: struct.Node.html
+/// [`take_hook_calls`]: fn.take_hook_calls.html
+/// [`document`]: fn.document.html
+#[derive(Default, Debug, Clone, Copy)]
+pub struct MockDom;
+
+thread_local! {
+    static DOCUMENT: Node = Node::element("body");
+    static HOOK_CALLS: RefCell<Vec<HookCall>> = const { RefCell::new(Vec::new()) };
+}
+
+/// The root of the in-memory document of the current thread.
+pub fn document() -> Node {
+    DOCUMENT.with(Node::clone)
+}
+
+/// A lifecycle hook call recorded by the [`MockDom`].
+///
+/// [`MockDom`]: struct.MockDom.html
+#[derive(Debug, PartialEq, Clone)]
+pub struct HookCall {
+    /// When the hook was declared to run.
+    pub lifecycle: Lifecycle,
+    /// The element the hook was called with.
+    pub element: Node,
+    /// The top most ancestor of the element at the time of the call, the element itself if it was
+    /// detached.
+    pub root: Node,
+}
+
+/// Take the lifecycle hook calls recorded on the current thread, in the order they were made.
+pub fn take_hook_calls() -> Vec<HookCall> {
+    HOOK_CALLS.with(|calls| calls.take())
+}
+
+/// A registered event listener.
+#[derive(Debug)]
+pub struct Listener(Rc<String>);
+
+#[derive(Debug)]
+enum Kind {
+    Element {
+        name: String,
+        namespace: Namespace,
+        attributes: Vec<(String, String)>,
+        properties: Vec<(String, PropertyValue)>,
+        listeners: Vec<Rc<String>>,
+        inner_html: Option<String>,
+    },
+    Text(String),
+}
+
+#[derive(Debug)]
+struct NodeData {
+    kind: Kind,
+    parent: Weak<RefCell<NodeData>>,
+    children: Vec<Node>,
+}
+
+/// A node in the in-memory dom.
+///
+/// Nodes are reference counted handles, cloning a node produces another handle to the same node.
+/// Two nodes are equal if they are handles to the same node.
+#[derive(Clone)]
+pub struct Node(Rc<RefCell<NodeData>>);
+
+impl Node {
+    fn new(kind: Kind) -> Self {
+        Node(Rc::new(RefCell::new(NodeData {
+            kind,
+            parent: Weak::new(),
+            children: vec![],
+        })))
+    }
+
+    /// Create a detached element node, useful as the parent when applying a patch set.
+    pub fn element(name: impl Into<String>) -> Self {
+        Node::element_ns(name, Namespace::Html)
+    }
+
+    /// Create a detached element node in the given namespace.
+    pub fn element_ns(name: impl Into<String>, namespace: Namespace) -> Self {
+        Node::new(Kind::Element {
+            name: name.into(),
+            namespace,
+            attributes: vec![],
+            properties: vec![],
+            listeners: vec![],
+            inner_html: None,
+        })
+    }
+
+    /// Create a detached text node.
+    pub fn text(text: impl Into<String>) -> Self {
+        Node::new(Kind::Text(text.into()))
+    }
+
+    /// Returns true if this node is an element.
+    pub fn is_element(&self) -> bool {
+        matches!(self.0.borrow().kind, Kind::Element { .. })
+    }
+
+    /// The name of this element, or `None` for a text node.
+    pub fn name(&self) -> Option<String> {
+        match self.0.borrow().kind {
+            Kind::Element { ref name, .. } => Some(name.clone()),
+            Kind::Text(_) => None,
+        }
+    }
+
+    /// The namespace of this element, or `None` for a text node.
+    pub fn namespace(&self) -> Option<Namespace> {
+        match self.0.borrow().kind {
+            Kind::Element { namespace, .. } => Some(namespace),
+            Kind::Text(_) => None,
+        }
+    }
+
+    /// The value of this text node, or `None` for an element.
+    pub fn text_value(&self) -> Option<String> {
+        match self.0.borrow().kind {
+            Kind::Element { .. } => None,
+            Kind::Text(ref text) => Some(text.clone()),
+        }
+    }
+
+    /// Get the value of the given attribute, if it is set.
+    pub fn attribute(&self, name: &str) -> Option<String> {
+        match self.0.borrow().kind {
+            Kind::Element { ref attributes, .. } => {
+                attributes.iter()
+                    .find(|(n, _)| n == name)
+                    .map(|(_, v)| v.clone())
+            }
+            Kind::Text(_) => None,
+        }
+    }
+
+    /// All of the attributes set on this element, in the order they were first set.
+    pub fn attributes(&self) -> Vec<(String, String)> {
+        match self.0.borrow().kind {
+            Kind::Element { ref attributes, .. } => attributes.clone(),
+            Kind::Text(_) => vec![],
+        }
+    }
+
+    /// The value of the given property, if it is set.
+    pub fn property(&self, name: &str) -> Option<PropertyValue> {
+        match self.0.borrow().kind {
+            Kind::Element { ref properties, .. } => {
+                properties.iter()
+                    .find(|(n, _)| n == name)
+                    .map(|(_, v)| v.clone())
+            }
+            Kind::Text(_) => None,
+        }
+    }
+
+    /// The inline style properties set on this element, parsed from its `style` attribute.
+    pub fn styles(&self) -> Vec<(String, String)> {
+        self.attribute("style")
+            .unwrap_or_default()
+            .split(';')
+            .filter_map(|decl| {
+                let (property, value) = decl.split_once(':')?;
+                Some((property.trim().to_owned(), value.trim().to_owned()))
+            })
+            .collect()
+    }
+
+    /// Serialize style properties the way browsers do.
+    fn style_attribute(styles: &[(String, String)]) -> String {
+        styles.iter()
+            .map(|(property, value)| format!("{}: {};", property, value))
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// The triggers of the event listeners registered on this element.
+    pub fn listeners(&self) -> Vec<String> {
+        match self.0.borrow().kind {
+            Kind::Element { ref listeners, .. } => {
+                listeners.iter().map(|l| l.to_string()).collect()
+            }
+            Kind::Text(_) => vec![],
+        }
+    }
+
+    /// The raw html set via innerHTML on this element, if any.
+    pub fn inner_html(&self) -> Option<String> {
+        match self.0.borrow().kind {
+            Kind::Element { ref inner_html, .. } => inner_html.clone(),
+            Kind::Text(_) => None,
+        }
+    }
+
+    /// The children of this node.
+    pub fn children(&self) -> Vec<Node> {
+        self.0.borrow().children.clone()
+    }
+
+    /// The parent of this node, if it has one.
+    pub fn parent(&self) -> Option<Node> {
+        self.0.borrow().parent.upgrade().map(Node)
+    }
+
+    /// Find the first descendant of this node matching the given selector. Only `#id` and element
+    /// name selectors are supported.
+    pub fn query_selector(&self, selector: &str) -> Option<Node> {
+        for child in self.children() {
+            let matched = match selector.strip_prefix('#') {
+                Some(id) => child.attribute("id").as_deref() == Some(id),
+                None => child.name().as_deref() == Some(selector),
+            };
+            if matched {
+                return Some(child);
+            }
+            if let Some(node) = child.query_selector(selector) {
+                return Some(node);
+            }
+        }
+        None
+    }
+
+    /// Serialize the children of this node as an html string.
+    pub fn children_to_html(&self) -> String {
+        let mut html = String::new();
+        for child in self.0.borrow().children.iter() {
+            child.write_html(&mut html);
+        }
+        html
+    }
+
+    /// Serialize this node and its children as an html string.
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        self.write_html(&mut html);
+        html
+    }
+
+    fn write_html(&self, html: &mut String) {
+        let data = self.0.borrow();
+        match data.kind {
+            Kind::Text(ref text) => {
+                escape_to(text, false, html);
+            }
+            Kind::Element { ref name, namespace, ref attributes, ref inner_html, .. } => {
+                html.push('<');
+                html.push_str(name);
+                for (name, value) in attributes {
+                    html.push(' ');
+                    html.push_str(name);
+                    html.push_str("=\"");
+                    escape_to(value, true, html);
+                    html.push('"');
+                }
+                html.push('>');
+
+                if is_void(name, namespace) {
+                    return;
+                }
+
+                if let Some(inner_html) = inner_html {
+                    html.push_str(inner_html);
+                }
+                for child in data.children.iter() {
+                    child.write_html(html);
+                }
+
+                html.push_str("</");
+                html.push_str(name);
+                html.push('>');
+            }
+        }
+    }
+
+    fn root(&self) -> Node {
+        match self.parent() {
+            Some(parent) => parent.root(),
+            None => self.clone(),
+        }
+    }
+
+    fn detach(&self) {
+        let parent = self.0.borrow_mut().parent.upgrade();
+        if let Some(parent) = parent {
+            parent.borrow_mut().children.retain(|c| c != self);
+        }
+        self.0.borrow_mut().parent = Weak::new();
+    }
+}
+
+impl PartialEq for Node {
+    fn eq(&self, other: &Node) -> bool {
+        Rc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl fmt::Debug for Node {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Node({})", self.to_html())
+    }
+}
+
+impl Backend for MockDom {
+    type Node = Node;
+    type Element = Node;
+    type Text = Node;
+    type Listener = Listener;
+
+    fn create_element(&self, name: &str, namespace: Namespace) -> Node {
+        Node::element_ns(name, namespace)
+    }
+
+    fn create_text(&self, text: &str) -> Node {
+        Node::text(text)
+    }
+
+    fn set_text(&self, node: &Node, value: &str) {
+        if let Kind::Text(ref mut text) = node.0.borrow_mut().kind {
+            *text = value.to_owned();
+        }
+    }
+
+    fn set_attribute(&self, element: &Node, name: &str, value: &str) {
+        if let Kind::Element { ref mut attributes, .. } = element.0.borrow_mut().kind {
+            match attributes.iter_mut().find(|(n, _)| n == name) {
+                Some((_, v)) => *v = value.to_owned(),
+                None => attributes.push((name.to_owned(), value.to_owned())),
+            }
+        }
+    }
+
+    fn set_attribute_ns(&self, element: &Node, _namespace: &str, name: &str, value: &str) {
+        self.set_attribute(element, name, value);
+    }
+
+    fn remove_attribute(&self, element: &Node, name: &str) {
+        if let Kind::Element { ref mut attributes, .. } = element.0.borrow_mut().kind {
+            attributes.retain(|(n, _)| n != name);
+        }
+    }
+
+    fn add_class(&self, element: &Node, name: &str) {
+        let mut classes: Vec<String> = element.attribute("class")
+            .map(|c| c.split_whitespace().map(String::from).collect())
+            .unwrap_or_default();
+        if !classes.iter().any(|c| c == name) {
+            classes.push(name.to_owned());
+        }
+        self.set_attribute(element, "class", &classes.join(" "));
+    }
+
+    fn remove_class(&self, element: &Node, name: &str) {
+        if let Some(class) = element.attribute("class") {
+            let classes: Vec<&str> = class.split_whitespace()
+                .filter(|c| *c != name)
+                .collect();
+            if classes.is_empty() {
+                self.remove_attribute(element, "class");
+            }
+            else {
+                self.set_attribute(element, "class", &classes.join(" "));
+            }
+        }
+    }
+
+    fn set_style(&self, element: &Node, property: &str, value: &str) {
+        let mut styles = element.styles();
+        match styles.iter_mut().find(|(p, _)| p == property) {
+            Some((_, v)) => *v = value.to_owned(),
+            None => styles.push((property.to_owned(), value.to_owned())),
+        }
+        self.set_attribute(element, "style", &Node::style_attribute(&styles));
+    }
+
+    fn remove_style(&self, element: &Node, property: &str) {
+        if element.attribute("style").is_some() {
+            let mut styles = element.styles();
+            styles.retain(|(p, _)| p != property);
+            self.set_attribute(element, "style", &Node::style_attribute(&styles));
+        }
+    }
+
+    fn set_property(&self, element: &Node, name: &str, value: &PropertyValue) {
+        if let Kind::Element { ref mut properties, .. } = element.0.borrow_mut().kind {
+            match properties.iter_mut().find(|(n, _)| n == name) {
+                Some((_, v)) => *v = value.clone(),
+                None => properties.push((name.to_owned(), value.clone())),
+            }
+        }
+    }
+
+    fn remove_property(&self, element: &Node, name: &str) {
+        if let Kind::Element { ref mut properties, .. } = element.0.borrow_mut().kind {
+            properties.retain(|(n, _)| n != name);
+        }
+    }
+
+    fn set_inner_html(&self, element: &Node, html: &str) {
+        self.remove_children(element);
+        if let Kind::Element { ref mut inner_html, .. } = element.0.borrow_mut().kind {
+            *inner_html = Some(html.to_owned());
+        }
+    }
+
+    fn remove_children(&self, node: &Node) {
+        let children = std::mem::take(&mut node.0.borrow_mut().children);
+        for child in children {
+            child.0.borrow_mut().parent = Weak::new();
+        }
+        if let Kind::Element { ref mut inner_html, .. } = node.0.borrow_mut().kind {
+            *inner_html = None;
+        }
+    }
+
+    fn insert_before(&self, parent: &Node, child: &Node, sibling: Option<&Node>) {
+        child.detach();
+
+        let mut data = parent.0.borrow_mut();
+        let index = sibling
+            .and_then(|s| data.children.iter().position(|c| c == s))
+            .unwrap_or_else(|| data.children.len());
+        data.children.insert(index, child.clone());
+        child.0.borrow_mut().parent = Rc::downgrade(&parent.0);
+    }
+
+    fn parent(&self, node: &Node) -> Option<Node> {
+        node.parent()
+    }
+
+    fn remove(&self, node: &Node) {
+        node.detach();
+    }
+
+    fn as_element<'n>(&self, node: &'n Node) -> Option<&'n Node> {
+        if node.is_element() {
+            Some(node)
+        }
+        else {
+            None
+        }
+    }
+
+    fn as_text<'n>(&self, node: &'n Node) -> Option<&'n Node> {
+        if node.is_element() {
+            None
+        }
+        else {
+            Some(node)
+        }
+    }
+
+    fn children(&self, node: &Node) -> Vec<Node> {
+        node.children()
+    }
+
+    fn element_name(&self, element: &Node) -> String {
+        element.name().unwrap_or_default()
+    }
+
+    fn element_namespace(&self, element: &Node) -> Namespace {
+        element.namespace().unwrap_or_default()
+    }
+
+    fn text(&self, node: &Node) -> String {
+        node.text_value().unwrap_or_default()
+    }
+
+    fn attribute(&self, element: &Node, name: &str) -> Option<String> {
+        element.attribute(name)
+    }
+
+    fn attribute_names(&self, element: &Node) -> Vec<String> {
+        element.attributes()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect()
+    }
+
+    fn add_listener<Message, Command>(
+        &self,
+        element: &Node,
+        trigger: &str,
+        _handler: EventHandler<Message>,
+        _options: ListenerOptions,
+        _app: &Dispatcher<Message, Command>,
+    ) -> Listener
+    where
+        Message: Clone + PartialEq + fmt::Debug + 'static,
+        Command: SideEffect<Message> + 'static,
+    {
+        let listener = Rc::new(trigger.to_owned());
+        if let Kind::Element { ref mut listeners, .. } = element.0.borrow_mut().kind {
+            listeners.push(Rc::clone(&listener));
+        }
+        Listener(listener)
+    }
+
+    fn remove_listener(&self, element: &Node, _trigger: &str, listener: &Listener, _options: ListenerOptions) {
+        if let Kind::Element { ref mut listeners, .. } = element.0.borrow_mut().kind {
+            listeners.retain(|l| !Rc::ptr_eq(l, &listener.0));
+        }
+    }
+
+    fn portal_target(&self, target: &PortalTarget) -> Option<Node> {
+        match target {
+            PortalTarget::Selector(selector) => document().query_selector(selector),
+            PortalTarget::Element(_) => panic!("browser elements cannot be used in a mock dom"),
+        }
+    }
+
+    fn bind_hook<Message, Command>(
+        &self,
+        lifecycle: Lifecycle,
+        _hook: Hook<Message, Command>,
+        element: &Node,
+    ) -> BoundHook<Message, Command>
+    where
+        Message: 'static,
+        Command: 'static,
+    {
+        let element = element.clone();
+        Box::new(move || {
+            let root = element.root();
+            HOOK_CALLS.with(|calls| calls.borrow_mut().push(HookCall { lifecycle, element, root }));
+            (None, None)
+        })
+    }
+
+    fn component_node(&self, _node: web_sys::Node) -> Option<Node> {
+        // components render into the browser's dom, the mock dom can't host them
+        None
+    }
+}
+
+/// Renders successive virtual doms into a detached element of the mock dom.
+///
+/// Each virtual dom is diffed against the previous one and the resulting patch set is applied to
+/// the [`parent`] element, which can then be inspected. Queued lifecycle hooks are run after each
+/// patch set is applied, like an app does after rendering.
+///
+/// [`parent`]: #method.parent
+pub struct Fixture<Message = Msg, Command = Cmd> {
+    parent: Node,
+    app: Dispatcher<Message, Command>,
+    storage: Storage<Message, MockDom>,
+}
+
+impl Fixture {
+    /// Create a fixture rendering into a new element with the given name, using the test
+    /// application's dispatcher.
+    pub fn new(parent: &str) -> Self {
+        Fixture::with_dispatcher(parent, App::dispatcher())
+    }
+}
+
+impl<Message, Command> Fixture<Message, Command> where
+    Message: Clone + PartialEq + fmt::Debug + 'static,
+    Command: SideEffect<Message> + fmt::Debug + 'static,
+{
+    /// Create a fixture rendering into a new element with the given name, dispatching events and
+    /// hooks to the given dispatcher.
+    pub fn with_dispatcher(parent: &str, app: Dispatcher<Message, Command>) -> Self {
+        Fixture {
+            parent: Node::element(parent),
+            app,
+            storage: vec![],
+        }
+    }
+
+    /// The element the virtual dom is rendered into.
+    pub fn parent(&self) -> &Node {
+        &self.parent
+    }
+
+    /// The dispatcher the virtual dom is rendered with.
+    pub fn app(&self) -> &Dispatcher<Message, Command> {
+        &self.app
+    }
+
+    /// The storage for the current virtual dom.
+    pub fn storage(&self) -> &Storage<Message, MockDom> {
+        &self.storage
+    }
+
+    /// Render the first virtual dom.
+    pub fn create<K: Eq + Hash, D: DomIter<Message, Command, K>>(&mut self, new: &D) {
+        self.patch(None, Some(new), |_| ());
+    }
+
+    /// Hydrate the existing children of the parent element with the first virtual dom.
+    pub fn hydrate<K: Eq + Hash, D: DomIter<Message, Command, K>>(&mut self, new: &D) {
+        let patch_set = diff::diff(std::iter::empty(), new.dom_iter(), &mut self.storage);
+        let storage = patch_set.hydrate(&self.parent, &self.app);
+        self.storage = storage;
+        self.app.run_hooks();
+    }
+
+    /// Update the dom from the old virtual dom to the new one.
+    pub fn update<K: Eq + Hash, D: DomIter<Message, Command, K>>(&mut self, old: &D, new: &D) {
+        self.patch(Some(old), Some(new), |_| ());
+    }
+
+    /// Remove the dom rendered from the old virtual dom.
+    pub fn remove<K: Eq + Hash, D: DomIter<Message, Command, K>>(&mut self, old: &D) {
+        self.patch(Some(old), None, |_| ());
+    }
+
+    /// Diff the old virtual dom against the new one, either of which may be missing, and apply the
+    /// resulting patch set. The patch set is passed to the given function before it is applied and
+    /// the result of the function is returned.
+    pub fn patch<K, D, F, R>(&mut self, old: Option<&D>, new: Option<&D>, inspect: F) -> R where
+        K: Eq + Hash,
+        D: DomIter<Message, Command, K>,
+        F: FnOnce(&PatchSet<Message, Command, K, MockDom>) -> R,
+    {
+        let o = old.map(|d| d.dom_iter()).into_iter().flatten();
+        let n = new.map(|d| d.dom_iter()).into_iter().flatten();
+        let patch_set = diff::diff(o, n, &mut self.storage);
+        let result = inspect(&patch_set);
+        let storage = patch_set.apply(&self.parent, &self.app);
+        self.storage = storage;
+        self.app.run_hooks();
+        result
+    }
+}
@@ -9,15 +9,21 @@
 
 use std::fmt;
 use std::mem;
-use wasm_bindgen::prelude::*;
+use std::rc::Rc;
+use std::any::{Any, TypeId};
+use std::hash::Hash;
+use wasm_bindgen::JsValue;
+use crate::backend::{Backend, Web};
 pub use crate::component::Component;
 pub use crate::app::Dispatcher;
 
 /// This represents an event handler. The handler can either always map to a specific message, or a
-/// function can be provided that will transform the given [`web_sys::Event`] into a message. This
-/// function must be a plain fn pointer and cannot capture any state from the environment.
+/// function can be provided that will transform the given [`web_sys::Event`] into a message. These
+/// functions are plain fn pointers that can't capture any state from the environment, except for
+/// [`Callback`] closures, which are identified by a key.
 ///
 /// [`web_sys::Event`]: https://rustwasm.github.io/wasm-bindgen/api/web_sys/struct.Event.html
+/// [`Callback`]: struct.Callback.html
 #[derive(Debug, PartialEq, Copy, Clone)]
 pub enum EventHandler<'a, Message> {
     /// A message that will be generated when this event associated with this handler fires.
@@ -42,18 +48,396 @@ pub enum EventHandler<'a, Message> {
     ///
     /// [`web_sys::InputEvent`]: https://rustwasm.github.io/wasm-bindgen/api/web_sys/struct.InputEvent.html
     InputEvent(fn(web_sys::InputEvent) -> Option<Message>),
+
+    /// Intercept clicks on a link to a page within the app and navigate to it with
+    /// [`Navigate::Push`] instead of loading a new page.
+    ///
+    /// [`Navigate::Push`]: ../route/navigate/enum.Navigate.html#variant.Push
+    Link,
+
+    /// A closure that will convert a [`web_sys::Event`] into a message.
+    ///
+    /// [`web_sys::Event`]: https://rustwasm.github.io/wasm-bindgen/api/web_sys/struct.Event.html
+    Closure(&'a Callback<Message>),
+}
+
+/// Options for an event listener.
+///
+/// `capture`, `passive`, and `once` are passed to [`addEventListener`]. If `prevent_default` or
+/// `stop_propagation` are set, the corresponding method is called on the event before it is
+/// passed to the handler. Note that browsers ignore `preventDefault()` in passive listeners.
+///
+/// Listeners are replaced if their options change.
+///
+/// [`addEventListener`]: https://developer.mozilla.org/en-US/docs/Web/API/EventTarget/addEventListener
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone)]
+pub struct ListenerOptions {
+    /// Handle the event during the capture phase.
+    pub capture: bool,
+    /// The handler will never call `preventDefault()`, allowing the browser to scroll without
+    /// waiting for it.
+    pub passive: bool,
+    /// Remove the listener after it is called once.
+    pub once: bool,
+    /// Call `preventDefault()` on the event.
+    pub prevent_default: bool,
+    /// Call `stopPropagation()` on the event.
+    pub stop_propagation: bool,
+}
+
+impl ListenerOptions {
+    /// The default options.
+    pub fn new() -> Self {
+        ListenerOptions::default()
+    }
+
+    /// Handle the event during the capture phase.
+    #[must_use]
+    pub fn capture(mut self) -> Self {
+        self.capture = true;
+        self
+    }
+
+    /// Mark the listener as passive.
+    #[must_use]
+    pub fn passive(mut self) -> Self {
+        self.passive = true;
+        self
+    }
+
+    /// Remove the listener after it is called once.
+    #[must_use]
+    pub fn once(mut self) -> Self {
+        self.once = true;
+        self
+    }
+
+    /// Call `preventDefault()` on the event.
+    #[must_use]
+    pub fn prevent_default(mut self) -> Self {
+        self.prevent_default = true;
+        self
+    }
+
+    /// Call `stopPropagation()` on the event.
+    #[must_use]
+    pub fn stop_propagation(mut self) -> Self {
+        self.stop_propagation = true;
+        self
+    }
+}
+
+/// A closure that converts a [`web_sys::Event`] into a message.
+///
+/// Unlike plain fn pointers, callbacks can capture state from the environment, like the index or
+/// id of the item they are attached to. Closures can't be compared, so each callback is
+/// identified by a key instead. When diffing, the existing event listener is kept if the old and
+/// new callbacks have equal keys and were created from the same closure type, otherwise the
+/// listener is replaced. The key should therefore include everything the closure captures that
+/// affects the message it produces.
+///
+/// Cloning a callback is cheap, the closure is shared.
+///
+/// [`web_sys::Event`]: https://rustwasm.github.io/wasm-bindgen/api/web_sys/struct.Event.html
+pub struct Callback<Message> {
+    /// The key identifying the closure.
+    key: Rc<dyn Any>,
+    /// Compare two keys of the same type.
+    key_eq: fn(&dyn Any, &dyn Any) -> bool,
+    /// The type of the closure.
+    closure: TypeId,
+    /// The closure.
+    fun: Rc<dyn Fn(web_sys::Event) -> Option<Message>>,
+}
+
+impl<Message> Callback<Message> {
+    /// Create a callback identified by the given key.
+    pub fn new<K, F>(key: K, fun: F) -> Self
+    where
+        K: PartialEq + 'static,
+        F: Fn(web_sys::Event) -> Option<Message> + 'static,
+    {
+        Callback {
+            key: Rc::new(key),
+            key_eq: any_eq::<K>,
+            closure: TypeId::of::<F>(),
+            fun: Rc::new(fun),
+        }
+    }
+
+    /// Call the closure with the given event.
+    pub fn call(&self, event: web_sys::Event) -> Option<Message> {
+        (self.fun)(event)
+    }
+}
+
+impl<Message> Clone for Callback<Message> {
+    fn clone(&self) -> Self {
+        Callback {
+            key: Rc::clone(&self.key),
+            key_eq: self.key_eq,
+            closure: self.closure,
+            fun: Rc::clone(&self.fun),
+        }
+    }
+}
+
+impl<Message> PartialEq for Callback<Message> {
+    fn eq(&self, other: &Self) -> bool {
+        self.closure == other.closure
+            && (self.key_eq)(&*self.key, &*other.key)
+    }
+}
+
+impl<Message> fmt::Debug for Callback<Message> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Callback(_)")
+    }
+}
+
+/// The point in the life of an element at which a [`Hook`] runs.
+///
+/// Mount and update hooks run after the patch set has been fully applied and the new nodes have
+/// been attached, in the order their elements were patched. Unmount hooks run while the patch set
+/// is applied, just before their elements are removed, so they can still inspect the attached
+/// element. The same change to the dom always runs the same hooks in the same order.
+///
+/// [`Hook`]: enum.Hook.html
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Lifecycle {
+    /// The element was created and attached to the dom.
+    Mount,
+    /// The element was kept, and possibly moved or changed, when the dom was patched. Elements in
+    /// an unchanged lazy sub tree are not updated.
+    Update,
+    /// The element was removed from the dom.
+    Unmount,
+}
+
+/// A lifecycle hook, called with the browser's element at some point in its [`Lifecycle`].
+///
+/// Hooks are plain fn pointers, unlike event handlers there is no closure variant, so they can't
+/// capture any state. They can use the element directly, for example to initialize a third party
+/// widget, and produce a message or a command for the app.
+///
+/// [`Lifecycle`]: enum.Lifecycle.html
+#[derive(Debug)]
+pub enum Hook<Message, Command> {
+    /// A callback producing a message, which is dispatched to the app.
+    Msg(fn(&web_sys::Element) -> Option<Message>),
+    /// A callback producing a command, which is processed by the app.
+    Cmd(fn(&web_sys::Element) -> Option<Command>),
+}
+
+impl<Message, Command> Hook<Message, Command> {
+    /// Call the hook with the given element, returning the message or command it produces.
+    pub fn call(&self, element: &web_sys::Element) -> (Option<Message>, Option<Command>) {
+        match self {
+            Hook::Msg(f) => (f(element), None),
+            Hook::Cmd(f) => (None, f(element)),
+        }
+    }
+}
+
+/// A lifecycle hook bound to the element it runs with, see [`Backend::bind_hook`].
+///
+/// [`Backend::bind_hook`]: ../backend/trait.Backend.html#tymethod.bind_hook
+pub type BoundHook<Message, Command> = Box<dyn FnOnce() -> (Option<Message>, Option<Command>)>;
+
+impl<Message, Command> Clone for Hook<Message, Command> {
+    fn clone(&self) -> Self {
+        *self
+    }
+}
+
+impl<Message, Command> Copy for Hook<Message, Command> {}
+
+impl<Message, Command> PartialEq for Hook<Message, Command> {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Hook::Msg(a), Hook::Msg(b)) => *a as usize == *b as usize,
+            (Hook::Cmd(a), Hook::Cmd(b)) => *a as usize == *b as usize,
+            _ => false,
+        }
+    }
+}
+
+/// The element a portal renders its children into.
+#[derive(Debug, PartialEq, Clone)]
+pub enum PortalTarget {
+    /// The first element in the document matching a css selector, e.g. `"body"` or `"#modals"`.
+    Selector(String),
+    /// The given element.
+    Element(web_sys::Element),
+}
+
+impl From<&str> for PortalTarget {
+    fn from(selector: &str) -> Self {
+        PortalTarget::Selector(selector.into())
+    }
+}
+
+impl From<String> for PortalTarget {
+    fn from(selector: String) -> Self {
+        PortalTarget::Selector(selector)
+    }
+}
+
+impl From<web_sys::Element> for PortalTarget {
+    fn from(element: web_sys::Element) -> Self {
+        PortalTarget::Element(element)
+    }
+}
+
+/// The namespace of an element.
+///
+/// Elements without an explicit namespace inherit one when they are created: `svg` and `math`
+/// elements start the svg and MathML namespaces, their descendants stay in that namespace, and
+/// html integration points like `foreignObject` switch back to html. See [`Namespace::child`].
+///
+/// [`Namespace::child`]: enum.Namespace.html#method.child
+#[derive(Debug, Default, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum Namespace {
+    /// An html element.
+    #[default]
+    Html,
+    /// An svg element.
+    Svg,
+    /// A MathML element.
+    MathMl,
+}
+
+impl Namespace {
+    /// The namespace URI.
+    pub fn uri(self) -> &'static str {
+        match self {
+            Namespace::Html => "http://www.w3.org/1999/xhtml",
+            Namespace::Svg => "http://www.w3.org/2000/svg",
+            Namespace::MathMl => "http://www.w3.org/1998/Math/MathML",
+        }
+    }
+
+    /// The namespace with the given URI, if it is one we know about.
+    pub fn from_uri(uri: &str) -> Option<Self> {
+        [Namespace::Html, Namespace::Svg, Namespace::MathMl].iter()
+            .copied()
+            .find(|ns| ns.uri() == uri)
+    }
+
+    /// The namespace of an element named `name`, without an explicit namespace, created in a
+    /// `parent` element in this namespace.
+    pub fn child(self, parent: &str, name: &str) -> Self {
+        match (self, parent, name) {
+            (_, _, "svg") => Namespace::Svg,
+            (_, _, "math") => Namespace::MathMl,
+            (Namespace::Svg, "foreignObject", _) | (Namespace::Svg, "desc", _) | (Namespace::Svg, "title", _) => Namespace::Html,
+            (Namespace::MathMl, "mi", _) | (Namespace::MathMl, "mo", _) | (Namespace::MathMl, "mn", _)
+            | (Namespace::MathMl, "ms", _) | (Namespace::MathMl, "mtext", _)
+            if name != "mglyph" && name != "malignmark" => Namespace::Html,
+            (ns, _, _) => ns,
+        }
+    }
+}
+
+/// The namespace URI of a prefixed attribute name (e.g. `xlink:href`), if it has a known prefix.
+pub fn attribute_namespace(name: &str) -> Option<&'static str> {
+    match name.split_once(':') {
+        Some(("xlink", _)) => Some("http://www.w3.org/1999/xlink"),
+        Some(("xml", _)) => Some("http://www.w3.org/XML/1998/namespace"),
+        Some(("xmlns", _)) => Some("http://www.w3.org/2000/xmlns/"),
+        _ if name == "xmlns" => Some("http://www.w3.org/2000/xmlns/"),
+        _ => None,
+    }
+}
+
+/// The value of a DOM property.
+///
+/// Unlike attributes, which are always strings, properties are set directly on the DOM object and
+/// can hold any JS value.
+#[derive(Debug, PartialEq, Clone)]
+pub enum PropertyValue {
+    /// A boolean.
+    Bool(bool),
+    /// A number.
+    Number(f64),
+    /// A string.
+    String(String),
+    /// Any other JS value.
+    JsValue(JsValue),
+}
+
+impl From<bool> for PropertyValue {
+    fn from(value: bool) -> Self {
+        PropertyValue::Bool(value)
+    }
+}
+
+impl From<f64> for PropertyValue {
+    fn from(value: f64) -> Self {
+        PropertyValue::Number(value)
+    }
+}
+
+impl From<i32> for PropertyValue {
+    fn from(value: i32) -> Self {
+        PropertyValue::Number(value.into())
+    }
+}
+
+impl From<u32> for PropertyValue {
+    fn from(value: u32) -> Self {
+        PropertyValue::Number(value.into())
+    }
+}
+
+impl From<String> for PropertyValue {
+    fn from(value: String) -> Self {
+        PropertyValue::String(value)
+    }
+}
+
+impl From<&str> for PropertyValue {
+    fn from(value: &str) -> Self {
+        PropertyValue::String(value.into())
+    }
+}
+
+impl From<JsValue> for PropertyValue {
+    fn from(value: JsValue) -> Self {
+        PropertyValue::JsValue(value)
+    }
+}
+
+impl From<&PropertyValue> for JsValue {
+    fn from(value: &PropertyValue) -> Self {
+        match value {
+            PropertyValue::Bool(b) => JsValue::from_bool(*b),
+            PropertyValue::Number(n) => JsValue::from_f64(*n),
+            PropertyValue::String(s) => JsValue::from_str(s),
+            PropertyValue::JsValue(v) => v.clone(),
+        }
+    }
 }
 
 /// A DOM node or JS closure created when applying a patch.
-pub enum WebItem<Message> {
+///
+/// The concrete node and listener types are provided by the [`Backend`] the patch was applied
+/// with, by default this is the browser's dom.
+///
+/// [`Backend`]: ../backend/trait.Backend.html
+pub enum WebItem<Message, B: Backend = Web> {
     /// A DOM element.
-    Element(web_sys::Element),
+    Element(B::Element),
     /// A DOM text node.
-    Text(web_sys::Text),
+    Text(B::Text),
     /// A JS closure.
-    Closure(Closure<dyn FnMut(web_sys::Event)>),
+    Closure(B::Listener),
     /// A component.
     Component(Box<dyn Component<Message>>),
+    /// The start of a fragment, its children are siblings of the fragment in the DOM.
+    Fragment,
+    /// The start of a portal, its children are children of the given target element.
+    Portal(B::Element),
     /// A previously occupied, now empty storage entry.
     Taken,
     /// The end of a node.
@@ -63,7 +447,7 @@ pub enum WebItem<Message> {
     Up,
 }
 
-impl<Message> WebItem<Message> {
+impl<Message, B: Backend> WebItem<Message, B> {
     /// Swap this WebItem with WebItem::Taken and return the item.
     pub fn take(&mut self) -> Self {
         let mut taken = WebItem::Taken;
@@ -72,7 +456,7 @@ impl<Message> WebItem<Message> {
     }
 
     /// Possibly get a reference to the web_sys::Element in this WebItem.
-    pub fn as_element(&self) -> Option<&web_sys::Element> {
+    pub fn as_element(&self) -> Option<&B::Element> {
         match self {
             WebItem::Element(node) => Some(node),
             _ =>  None,
@@ -80,7 +464,7 @@ impl<Message> WebItem<Message> {
     }
 
     /// Possibly get a reference to the web_sys::Text in this WebItem.
-    pub fn as_text(&self) -> Option<&web_sys::Text> {
+    pub fn as_text(&self) -> Option<&B::Text> {
         match self {
             WebItem::Text(node) => Some(node),
             _ =>  None,
@@ -88,29 +472,39 @@ impl<Message> WebItem<Message> {
     }
 
     /// Possibly get a reference to the js_sys::Closure in this WebItem.
-    pub fn as_closure(&self) -> Option<&Closure<dyn FnMut(web_sys::Event)>> {
+    pub fn as_closure(&self) -> Option<&B::Listener> {
         match self {
             WebItem::Closure(closure) => Some(closure),
             _ =>  None,
         }
     }
 
+    /// Possibly get a reference to the target element of the portal in this WebItem.
+    pub fn as_portal(&self) -> Option<&B::Element> {
+        match self {
+            WebItem::Portal(target) => Some(target),
+            _ =>  None,
+        }
+    }
+
     /// Possibly get a reference to the Component in this WebItem.
-    pub fn as_component(&self) -> Option<&Box<dyn Component<Message>>> {
+    pub fn as_component(&self) -> Option<&dyn Component<Message>> {
         match self {
-            WebItem::Component(c) => Some(c),
+            WebItem::Component(c) => Some(&**c),
             _ =>  None,
         }
     }
 }
 
-impl<Message> fmt::Debug for WebItem<Message> {
+impl<Message, B: Backend> fmt::Debug for WebItem<Message, B> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         match self {
             WebItem::Element(node) => write!(f, "Element({:?})", node),
             WebItem::Text(text) => write!(f, "Text({:?})", text),
             WebItem::Closure(_) => write!(f, "Closure(_)"),
             WebItem::Component(_) => write!(f, "Component(_)"),
+            WebItem::Fragment => write!(f, "Fragment"),
+            WebItem::Portal(target) => write!(f, "Portal({:?})", target),
             WebItem::Taken => write!(f, "Taken"),
             WebItem::Up => write!(f, "Up"),
         }
@@ -122,7 +516,7 @@ impl<Message> fmt::Debug for WebItem<Message> {
 /// The list should match the traversal order of the vDOM tree we are operating on.
 ///
 /// [`WebItem`]: enum.WebItem.html
-pub type Storage<Message> = Vec<WebItem<Message>>;
+pub type Storage<Message, B = Web> = Vec<WebItem<Message, B>>;
 
 /// Items representing all of the data in the DOM tree.
 ///
@@ -143,6 +537,8 @@ pub enum DomItem<'a, Message, Command, K> {
         /// [`Hash`]: https://doc.rust-lang.org/std/hash/trait.Hash.html
         /// [`Hasher`]: https://doc.rust-lang.org/std/hash/trait.Hasher.html
         key: Option<&'a K>,
+        /// The namespace of this element, if not inherited from its parent.
+        namespace: Option<Namespace>,
     },
     /// A text node in the tree.
     Text(&'a str),
@@ -156,12 +552,52 @@ pub enum DomItem<'a, Message, Command, K> {
         /// The attribute value.
         value: &'a str,
     },
+    /// A class of the last node we saw.
+    ///
+    /// Classes are diffed individually, adding or removing a single class leaves the rest of the
+    /// element's classes untouched, including classes added outside of the virtual dom.
+    Class(&'a str),
+    /// An inline style property of the last node we saw.
+    ///
+    /// Style properties are diffed individually, like classes.
+    Style {
+        /// The css property name.
+        property: &'a str,
+        /// The value of the property.
+        value: &'a str,
+    },
+    /// A property of the last node we saw.
+    ///
+    /// Properties are set directly on the DOM object instead of through an attribute. This allows
+    /// setting values that have no attribute form, like `indeterminate` or `scrollTop`.
+    Property {
+        /// The property name.
+        name: &'a str,
+        /// The property value.
+        value: &'a PropertyValue,
+    },
+    /// A name the last node we saw can be looked up by.
+    ///
+    /// The element is recorded in the ref table of the app when the dom is patched, see
+    /// [`Dispatcher::node_ref`].
+    ///
+    /// [`Dispatcher::node_ref`]: ../app/dispatch/struct.Dispatcher.html#method.node_ref
+    Ref(&'a str),
+    /// A lifecycle hook of the last node we saw.
+    Hook {
+        /// When the hook runs.
+        lifecycle: Lifecycle,
+        /// The hook.
+        hook: Hook<Message, Command>,
+    },
     /// An event handler from the last node we saw.
     Event {
         /// The trigger for this event.
         trigger: &'a str,
         /// The handler for this event.
         handler: EventHandler<'a, Message>,
+        /// Options for the event listener.
+        options: ListenerOptions,
     },
     /// We are finished processing children nodes, the next node is a sibling.
     Up,
@@ -178,8 +614,98 @@ pub enum DomItem<'a, Message, Command, K> {
         /// A function to create the component if necessary.
         create: fn(Dispatcher<Message, Command>) -> Box<dyn Component<Message>>,
     },
+    /// A fragment, a group of sibling nodes without a containing element.
+    ///
+    /// The children of the fragment follow, up to the matching `Up`. They are added to the parent
+    /// of the fragment as if they were its direct children. A keyed fragment is moved as a unit.
+    Fragment {
+        /// An optional key for this fragment.
+        key: Option<&'a K>,
+    },
+    /// A portal, its children are rendered into the target element instead of the parent of the
+    /// portal.
+    ///
+    /// The children of the portal follow, up to the matching `Up`. Portals don't occupy a position
+    /// among their siblings.
+    Portal {
+        /// The element to render the children into.
+        target: &'a PortalTarget,
+    },
     /// For internal use. This is a reference to a keyed item.
     Key(&'a K),
+    /// A lazily rendered sub tree with a single root node.
+    ///
+    /// If the key and sub tree match the lazy sub tree at the same position in the old tree, the
+    /// sub tree is assumed to be unchanged. It will not be rendered or diffed, and the existing dom
+    /// nodes will be copied as is. Otherwise the sub tree is rendered and its root carries the key.
+    Lazy {
+        /// An optional key for the root of this sub tree.
+        key: Option<&'a K>,
+        /// The sub tree.
+        dom: LazyDom<'a, Message, Command, K>,
+    },
+}
+
+/// A function that renders a sub tree.
+type Render<'a, Message, Command, K> =
+    Box<dyn FnOnce() -> Box<dyn Iterator<Item = DomItem<'a, Message, Command, K>> + 'a> + 'a>;
+
+/// A lazily rendered sub tree.
+///
+/// The sub tree is only rendered if it is iterated. Lazy sub trees are identified by the input and
+/// the type of the function they are rendered with, two sub trees are equal if they are rendered
+/// by the same function from equal inputs.
+pub struct LazyDom<'a, Message, Command, K> {
+    /// The input the sub tree is rendered from.
+    input: &'a dyn Any,
+    /// The address of the function the sub tree is rendered with.
+    render: usize,
+    /// Compare two inputs of the same type.
+    input_eq: fn(&dyn Any, &dyn Any) -> bool,
+    /// Iterate over the rendered sub tree.
+    dom: Render<'a, Message, Command, K>,
+}
+
+impl<'a, Message, Command, K> LazyDom<'a, Message, Command, K> {
+    /// Create a lazy sub tree, rendered from the given input by the function at the given address.
+    /// The given closure iterates over the rendered sub tree, it won't be called until necessary.
+    pub fn new<T, F>(input: &'a T, render: usize, dom: F) -> Self where
+        T: PartialEq + 'static,
+        F: FnOnce() -> Box<dyn Iterator<Item = DomItem<'a, Message, Command, K>> + 'a> + 'a,
+    {
+        LazyDom {
+            input,
+            render,
+            input_eq: any_eq::<T>,
+            dom: Box::new(dom),
+        }
+    }
+
+    /// Return an iterator over the sub tree, rendering it if necessary.
+    pub fn dom_iter(self) -> Box<dyn Iterator<Item = DomItem<'a, Message, Command, K>> + 'a> {
+        (self.dom)()
+    }
+}
+
+/// Compare two values of type `T`.
+fn any_eq<T: PartialEq + 'static>(a: &dyn Any, b: &dyn Any) -> bool {
+    match (a.downcast_ref::<T>(), b.downcast_ref::<T>()) {
+        (Some(a), Some(b)) => a == b,
+        _ => false,
+    }
+}
+
+impl<'a, Message, Command, K> PartialEq for LazyDom<'a, Message, Command, K> {
+    fn eq(&self, other: &Self) -> bool {
+        self.render == other.render
+            && (self.input_eq)(self.input, other.input)
+    }
+}
+
+impl<'a, Message, Command, K> fmt::Debug for LazyDom<'a, Message, Command, K> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "LazyDom(_)")
+    }
 }
 
 /// This trait provides a way to iterate over a virtual dom representation.
@@ -8,12 +8,16 @@ use euca::vdom::WebItem;
 use euca::vdom::Storage;
 use euca::vdom::DomItem;
 use euca::vdom::DomIter;
+use euca::vdom::ListenerOptions;
 use euca::dom::Dom;
+use euca::dom::Handler;
 use euca::dom::DomVec;
 use euca::patch::Patch;
 use euca::patch::PatchSet;
 use euca::component::Component;
 use euca::diff;
+use euca::backend::Delegated;
+use euca::app::{AppBuilder, Application, Detach, Dispatcher, Update, Subscriptions, Render};
 
 use euca::test::{ App, Msg, Cmd };
 
@@ -78,19 +82,36 @@ impl<Message> Component<Message> for FakeComponent {
     fn pending(&mut self) -> Vec<web_sys::Node> { vec![] }
 }
 
+/// Replace lazy sub trees with the items they render.
+fn expand<'a, Message, Command, Key>(item: DomItem<'a, Message, Command, Key>)
+-> Box<dyn Iterator<Item = DomItem<'a, Message, Command, Key>> + 'a> where
+    Message: 'a,
+    Command: 'a,
+    Key: 'a,
+{
+    match item {
+        DomItem::Lazy { dom, .. } => Box::new(dom.dom_iter().flat_map(expand)),
+        i => Box::new(std::iter::once(i)),
+    }
+}
+
 fn gen_storage<'a, Message, Command, Key, Iter>(iter: Iter) -> Storage<Message> where
     Message: 'a,
+    Command: 'a,
     Key: 'a,
     Iter: Iterator<Item = DomItem<'a, Message, Command, Key>>,
 {
     iter
+        .flat_map(expand)
         // filter items that do not have storage
         .filter(|i| {
             match i {
                 DomItem::Element { .. } | DomItem::Text(_) | DomItem::Event { .. }
-                | DomItem::Component { .. } | DomItem::Up => true,
-                DomItem::Key(_) | DomItem::Attr { .. } | DomItem::UnsafeInnerHtml(_)
-                => false,
+                | DomItem::Component { .. } | DomItem::Fragment { .. } | DomItem::Portal { .. }
+                | DomItem::Up => true,
+                DomItem::Key(_) | DomItem::Attr { .. } | DomItem::Class(_) | DomItem::Style { .. }
+                | DomItem::Property { .. } | DomItem::Ref(_) | DomItem::Hook { .. } | DomItem::UnsafeInnerHtml(_)
+                | DomItem::Lazy { .. } => false,
             }
         })
         .map(|i| {
@@ -108,8 +129,11 @@ fn gen_storage<'a, Message, Command, Key, Iter>(iter: Iter) -> Storage<Message>
                 ),
                 DomItem::Up => WebItem::Up,
                 DomItem::Component { .. } => WebItem::Component(FakeComponent::new()),
-                DomItem::Attr { .. } | DomItem::Key(_)
-                | DomItem::UnsafeInnerHtml(_) => {
+                DomItem::Fragment { .. } => WebItem::Fragment,
+                DomItem::Portal { .. } => WebItem::Portal(e("div")),
+                DomItem::Attr { .. } | DomItem::Class(_) | DomItem::Style { .. }
+                | DomItem::Property { .. } | DomItem::Ref(_) | DomItem::Hook { .. } | DomItem::Key(_)
+                | DomItem::UnsafeInnerHtml(_) | DomItem::Lazy { .. } => {
                     unreachable!("attribute, inner html, and up nodes should have been filtered out")
                 },
             }
@@ -125,8 +149,9 @@ fn compare_patch_vecs<K: fmt::Debug + Eq + ?Sized>(left: &Vec<Patch<Msg, Cmd, &K
             (Patch::ReferenceKey(k1), Patch::ReferenceKey(k2)) => {
                 assert_eq!(k1, k2, "[{}] ReferenceKey keys don't match\n{}", i, dump);
             }
-            (Patch::CreateElement { element: e1 }, Patch::CreateElement { element: e2 }) => {
+            (Patch::CreateElement { element: e1, namespace: n1 }, Patch::CreateElement { element: e2, namespace: n2 }) => {
                 assert_eq!(e1, e2, "[{}] unexpected CreateElement\n{}", i, dump);
+                assert_eq!(n1, n2, "[{}] namespaces don't match for CreateElement\n{}", i, dump);
             }
             (Patch::CopyElement(WebItem::Element(e1)), Patch::CopyElement(WebItem::Element(e2))) => {
                 assert_eq!(e1.tag_name(), e2.tag_name(), "[{}] WebItems don't match for CopyElement\n{}", i, dump);
@@ -151,12 +176,20 @@ fn compare_patch_vecs<K: fmt::Debug + Eq + ?Sized>(left: &Vec<Patch<Msg, Cmd, &K
             (Patch::RemoveAttribute(a1), Patch::RemoveAttribute(a2)) => {
                 assert_eq!(a1, a2, "[{}] attribute names don't match\n{}", i, dump);
             }
-            (Patch::AddListener { trigger: t1, handler: h1 }, Patch::AddListener { trigger: t2, handler: h2 }) => {
+            (
+                Patch::AddListener { trigger: t1, handler: h1, options: o1 },
+                Patch::AddListener { trigger: t2, handler: h2, options: o2 },
+            ) => {
                 assert_eq!(t1, t2, "[{}] trigger names don't match\n{}", i, dump);
                 assert_eq!(h1, h2, "[{}] handlers don't match\n{}", i, dump);
+                assert_eq!(o1, o2, "[{}] listener options don't match\n{}", i, dump);
             }
-            (Patch::RemoveListener { trigger: t1, take: _ }, Patch::RemoveListener { trigger: t2, take: _ }) => {
+            (
+                Patch::RemoveListener { trigger: t1, take: _, options: o1 },
+                Patch::RemoveListener { trigger: t2, take: _, options: o2 },
+            ) => {
                 assert_eq!(t1, t2, "[{}] trigger names don't match\n{}", i, dump);
+                assert_eq!(o1, o2, "[{}] listener options don't match\n{}", i, dump);
             }
             (Patch::CopyListener(_), Patch::CopyListener(_)) => {}
             (Patch::RemoveElement(WebItem::Element(e1)), Patch::RemoveElement(WebItem::Element(e2))) => {
@@ -237,7 +270,7 @@ fn basic_diff() {
     compare!(
         patch_set,
         [
-            Patch::CreateElement { element: "span".into() },
+            Patch::CreateElement { element: "span".into(), namespace: None },
             Patch::Up,
         ]
     );
@@ -259,7 +292,7 @@ fn diff_add_text() {
     compare!(
         patch_set,
         [
-            Patch::CreateElement { element: "div".into() },
+            Patch::CreateElement { element: "div".into(), namespace: None },
             Patch::CreateText { text: "text".into() },
             Patch::Up,
             Patch::Up,
@@ -292,15 +325,15 @@ fn new_child_nodes() {
         patch_set,
         [
             Patch::CopyElement(leaked_e("div")),
-            Patch::CreateElement { element: "b".into() },
+            Patch::CreateElement { element: "b".into(), namespace: None },
             Patch::SetAttribute { name: "class", value: "item" },
             Patch::SetAttribute { name: "id", value: "id1" },
-            Patch::AddListener { trigger: "onclick", handler: euca::vdom::EventHandler::Msg(&()) },
+            Patch::AddListener { trigger: "onclick", handler: euca::vdom::EventHandler::Msg(&()), options: Default::default() },
             Patch::Up,
-            Patch::CreateElement { element: "i".into() },
+            Patch::CreateElement { element: "i".into(), namespace: None },
             Patch::SetAttribute { name: "class", value: "item" },
             Patch::SetAttribute { name: "id", value: "id2" },
-            Patch::AddListener { trigger: "onclick", handler: euca::vdom::EventHandler::Msg(&()) },
+            Patch::AddListener { trigger: "onclick", handler: euca::vdom::EventHandler::Msg(&()), options: Default::default() },
             Patch::Up,
             Patch::Up,
         ]
@@ -329,16 +362,16 @@ fn from_empty() {
     compare!(
         patch_set,
         [
-            Patch::CreateElement { element: "div" },
-            Patch::CreateElement { element: "b" },
+            Patch::CreateElement { element: "div", namespace: None },
+            Patch::CreateElement { element: "b", namespace: None },
             Patch::SetAttribute { name: "class", value: "item" },
             Patch::SetAttribute { name: "id", value: "id1" },
-            Patch::AddListener { trigger: "onclick", handler: euca::vdom::EventHandler::Msg(&()) },
+            Patch::AddListener { trigger: "onclick", handler: euca::vdom::EventHandler::Msg(&()), options: Default::default() },
             Patch::Up,
-            Patch::CreateElement { element: "i" },
+            Patch::CreateElement { element: "i", namespace: None },
             Patch::SetAttribute { name: "class", value: "item" },
             Patch::SetAttribute { name: "id", value: "id2" },
-            Patch::AddListener { trigger: "onclick", handler: euca::vdom::EventHandler::Msg(&()) },
+            Patch::AddListener { trigger: "onclick", handler: euca::vdom::EventHandler::Msg(&()), options: Default::default() },
             Patch::Up,
             Patch::Up,
         ]
@@ -431,7 +464,7 @@ fn basic_diff_with_element() {
         patch_set,
         [
             Patch::RemoveElement(leaked_e("div")),
-            Patch::CreateElement { element: "span".into() },
+            Patch::CreateElement { element: "span".into(), namespace: None },
             Patch::Up,
         ]
     );
@@ -539,7 +572,7 @@ fn old_child_nodes_with_element_and_child() {
         [
             Patch::CopyElement(leaked_e("div")),
             Patch::RemoveElement(leaked_e("b")),
-            Patch::CreateElement { element: "i".into() },
+            Patch::CreateElement { element: "i".into(), namespace: None },
             Patch::Up,
             Patch::RemoveElement(leaked_e("i")),
             Patch::Up,
@@ -603,7 +636,7 @@ fn assorted_child_nodes() {
             Patch::Up,
             Patch::CopyElement(leaked_e("p")),
             Patch::RemoveText(leaked_t("paragraph1")),
-            Patch::CreateElement { element: "b".into() },
+            Patch::CreateElement { element: "b".into(), namespace: None },
             Patch::CreateText { text: "bold" },
             Patch::Up,
             Patch::Up,
@@ -611,8 +644,8 @@ fn assorted_child_nodes() {
             Patch::Up,
             Patch::Up,
             Patch::RemoveElement(leaked_e("p")),
-            Patch::CreateElement { element: "button".into() },
-            Patch::AddListener { trigger: "click", handler: euca::vdom::EventHandler::Msg(&()) },
+            Patch::CreateElement { element: "button".into(), namespace: None },
+            Patch::AddListener { trigger: "click", handler: euca::vdom::EventHandler::Msg(&()), options: Default::default() },
             Patch::CreateText { text: "submit" },
             Patch::Up,
             Patch::Up,
@@ -648,7 +681,7 @@ fn diff_old_child_nodes_with_new_element() {
         patch_set,
         [
             Patch::RemoveElement(leaked_e("span")),
-            Patch::CreateElement { element: "div".into() },
+            Patch::CreateElement { element: "div".into(), namespace: None },
             Patch::Up,
         ]
     );
@@ -682,7 +715,7 @@ fn basic_patch_with_element() {
 
     let parent = e("div");
     let app = App::dispatcher();
-    let mut storage = vec![];
+    let mut storage: Storage<_> = vec![];
 
     // first gen create element
     let o = gen1.into_iter();
@@ -715,7 +748,7 @@ fn basic_event_test() {
     let parent = e("div");
     let messages = Rc::new(RefCell::new(vec![]));
     let app = App::dispatcher_with_vec(Rc::clone(&messages));
-    let mut storage = vec![];
+    let mut storage: Storage<_> = vec![];
 
     let o = gen1.into_iter();
     let n = gen2.dom_iter();
@@ -742,7 +775,7 @@ fn listener_copy() {
     let parent = e("div");
     let messages = Rc::new(RefCell::new(vec![]));
     let app = App::dispatcher_with_vec(Rc::clone(&messages));
-    let mut storage = vec![];
+    let mut storage: Storage<_> = vec![];
 
     let o = gen1.into_iter();
     let n = gen2.dom_iter();
@@ -777,6 +810,236 @@ fn listener_copy() {
     assert_eq!(messages.borrow().len(), 2);
 }
 
+#[wasm_bindgen_test]
+fn closure_listener() {
+    let clicked = Rc::new(RefCell::new(vec![]));
+    let button = |id: u32| {
+        let clicked = Rc::clone(&clicked);
+        Dom::<_, _, &()>::elem("button").callback("click", id, move |_| {
+            clicked.borrow_mut().push(id);
+            Some(())
+        })
+    };
+
+    let gen1 = button(1);
+    let gen2 = button(2);
+
+    let parent = e("div");
+    let messages = Rc::new(RefCell::new(vec![]));
+    let app = App::dispatcher_with_vec(Rc::clone(&messages));
+    let mut storage: Storage<_> = vec![];
+
+    let click = |storage: &Storage<_>| match storage[0] {
+        WebItem::Element(ref node) => {
+            node.dyn_ref::<web_sys::HtmlElement>()
+                .expect("expected html element")
+                .click();
+        },
+        _ => panic!("expected node to be created"),
+    };
+
+    let patch_set = diff::diff(iter::empty(), gen1.dom_iter(), &mut storage);
+    storage = patch_set.apply(&parent, &app);
+    click(&storage);
+
+    let patch_set = diff::diff(gen1.dom_iter(), gen2.dom_iter(), &mut storage);
+    storage = patch_set.apply(&parent, &app);
+    click(&storage);
+
+    assert_eq!(*clicked.borrow(), vec![1, 2]);
+    assert_eq!(messages.borrow().len(), 2);
+}
+
+#[wasm_bindgen_test]
+fn listener_prevent_default() {
+    let options = ListenerOptions::new().prevent_default().stop_propagation();
+    let gen = Dom::<_, _, &()>::elem("div")
+        .event("click", ())
+        .push(Dom::elem("input")
+            .attr("type", "checkbox")
+            .on_with_options("click", Handler::Msg(()), options)
+        );
+
+    let parent = e("div");
+    let messages = Rc::new(RefCell::new(vec![]));
+    let app = App::dispatcher_with_vec(Rc::clone(&messages));
+    let mut storage: Storage<_> = vec![];
+
+    let patch_set = diff::diff(iter::empty(), gen.dom_iter(), &mut storage);
+    storage = patch_set.apply(&parent, &app);
+
+    let input = match storage[2] {
+        WebItem::Element(ref node) => {
+            node.dyn_ref::<web_sys::HtmlInputElement>()
+                .expect("expected input element")
+                .clone()
+        },
+        _ => panic!("expected node to be created"),
+    };
+    input.click();
+
+    assert!(!input.checked(), "default action should have been prevented");
+    assert_eq!(messages.borrow().len(), 1, "event should not have propagated to the parent");
+}
+
+#[wasm_bindgen_test]
+fn delegated_listeners() {
+    let clicked = Rc::new(RefCell::new(vec![]));
+    let handler = |id: u32| {
+        let clicked = Rc::clone(&clicked);
+        move |_| {
+            clicked.borrow_mut().push(id);
+            Some(())
+        }
+    };
+
+    let gen1 = Dom::<_, _, &()>::elem("div")
+        .callback("click", 1, handler(1))
+        .push(Dom::elem("button").callback("click", 2, handler(2)));
+    let gen2 = Dom::<_, _, &()>::elem("div")
+        .push(Dom::elem("button").callback("click", 2, handler(2)));
+
+    let parent = e("div");
+    let backend = Delegated::new(parent.clone());
+    let messages = Rc::new(RefCell::new(vec![]));
+    let app = App::dispatcher_with_vec(Rc::clone(&messages));
+    let mut storage: Storage<_, Delegated> = vec![];
+
+    let click = |storage: &Storage<_, Delegated>, i: usize| match storage[i] {
+        WebItem::Element(ref node) => {
+            node.dyn_ref::<web_sys::HtmlElement>()
+                .expect("expected html element")
+                .click();
+        },
+        _ => panic!("expected node to be created"),
+    };
+
+    let patch_set = diff::diff(iter::empty(), gen1.dom_iter(), &mut storage);
+    storage = patch_set.apply_with(&backend, &parent, &app);
+    click(&storage, 2);
+    assert_eq!(*clicked.borrow(), vec![2, 1], "handlers should run from the target up");
+
+    let patch_set = diff::diff(gen1.dom_iter(), gen2.dom_iter(), &mut storage);
+    storage = patch_set.apply_with(&backend, &parent, &app);
+    click(&storage, 1);
+    assert_eq!(*clicked.borrow(), vec![2, 1, 2], "removed handler should not run");
+
+    assert_eq!(messages.borrow().len(), 3);
+}
+
+#[wasm_bindgen_test]
+fn delegated_portal() {
+    let clicked = Rc::new(RefCell::new(vec![]));
+    let handler = |id: u32| {
+        let clicked = Rc::clone(&clicked);
+        move |_| {
+            clicked.borrow_mut().push(id);
+            Some(())
+        }
+    };
+
+    let target = e("div");
+    let gen = Dom::<_, _, &()>::elem("div")
+        .push(Dom::portal(target.clone(), vec![
+            Dom::elem("button").callback("click", 1, handler(1)),
+        ]));
+
+    let parent = e("div");
+    let backend = Delegated::new(parent.clone());
+    let app = App::dispatcher();
+    let mut storage: Storage<_, Delegated> = vec![];
+
+    let patch_set = diff::diff(iter::empty(), gen.dom_iter(), &mut storage);
+    storage = patch_set.apply_with(&backend, &parent, &app);
+    assert_eq!(target.child_element_count(), 1, "portal content should be in the target");
+    assert_eq!(parent.first_element_child().expect("expected div").child_element_count(), 0);
+
+    match storage[2] {
+        WebItem::Element(ref node) => {
+            node.dyn_ref::<web_sys::HtmlElement>()
+                .expect("expected html element")
+                .click();
+        },
+        _ => panic!("expected node to be created"),
+    }
+    assert_eq!(*clicked.borrow(), vec![1], "handler in portal should run");
+
+    let patch_set = diff::diff(gen.dom_iter(), iter::empty(), &mut storage);
+    patch_set.apply_with(&backend, &parent, &app);
+    assert_eq!(target.child_element_count(), 0, "portal content should be removed");
+}
+
+/// A model that only renders into a portal.
+struct PortalModel {
+    target: web_sys::Element,
+    items: u32,
+}
+
+impl Update<u32> for PortalModel {
+    fn simple_update(&mut self, items: u32) {
+        self.items = items;
+    }
+}
+
+impl Subscriptions<u32> for PortalModel {}
+
+impl Render<Dom<u32, (), ()>> for PortalModel {
+    fn render(&self) -> Dom<u32, (), ()> {
+        Dom::portal(self.target.clone(), (0..self.items).map(|_| Dom::elem("p")))
+    }
+}
+
+#[wasm_bindgen_test]
+fn portal_only_app() {
+    let parent = e("div");
+    let target = e("div");
+
+    let app = AppBuilder::default()
+        .attach(parent.clone(), PortalModel { target: target.clone(), items: 1 });
+    assert_eq!(target.child_element_count(), 1, "portal content should be in the target");
+    assert_eq!(parent.child_element_count(), 0, "app should have no nodes in its parent");
+
+    // the app has no nodes of its own, it must still be able to render and detach
+    let dispatcher = Dispatcher::from(&app);
+    Application::update(&mut **app.borrow_mut(), 2);
+    Application::render(&mut **app.borrow_mut(), &dispatcher);
+    assert_eq!(target.child_element_count(), 2);
+
+    app.detach();
+    assert_eq!(target.child_element_count(), 0, "portal content should be removed");
+}
+
+#[wasm_bindgen_test]
+fn node_refs() {
+    let gen = Dom::<_, _, &()>::elem("div")
+        .push(Dom::elem("input").node_ref("input"));
+
+    // only elements in the document can be looked up
+    let parent = e("div");
+    web_sys::window().expect("expected window")
+        .document().expect("expected document")
+        .body().expect("expected body")
+        .append_child(&parent).expect("failed to append parent");
+
+    let app = App::dispatcher();
+    let mut storage: Storage<_> = vec![];
+
+    let patch_set = diff::diff(iter::empty(), gen.dom_iter(), &mut storage);
+    storage = patch_set.apply(&parent, &app);
+
+    let input = parent.query_selector("input")
+        .expect("error querying for element")
+        .expect("expected input");
+    assert_eq!(app.node_ref("input"), Some(input));
+    assert_eq!(app.node_ref("other"), None);
+
+    let patch_set = diff::diff(gen.dom_iter(), iter::empty(), &mut storage);
+    patch_set.apply(&parent, &app);
+    assert_eq!(app.node_ref("input"), None, "removed elements should not be found");
+
+    parent.remove();
+}
+
 #[wasm_bindgen_test]
 fn replace_element_with_text() {
     let old = Dom::<_, _, &()>::elem("div");
@@ -811,7 +1074,7 @@ fn replace_text_with_element() {
         patch_set,
         [
             Patch::RemoveText(leaked_t("div")),
-            Patch::CreateElement { element: "div".into() },
+            Patch::CreateElement { element: "div".into(), namespace: None },
             Patch::Up,
         ]
     );
@@ -967,7 +1230,7 @@ fn inner_html_replace_with_children() {
         [
             Patch::CopyElement(leaked_e("div")),
             Patch::UnsetInnerHtml,
-            Patch::CreateElement { element: "div" },
+            Patch::CreateElement { element: "div", namespace: None },
             Patch::SetInnerHtml("html"),
             Patch::Up,
             Patch::Up,
@@ -1024,7 +1287,7 @@ fn inner_html_remove_parent_node() {
 
     let parent = e("div");
     let app = App::dispatcher();
-    let mut storage = vec![];
+    let mut storage: Storage<_> = vec![];
 
     let n = gen1.dom_iter();
     let patch_set = diff::diff(iter::empty(), n, &mut storage);
@@ -1239,7 +1502,7 @@ fn diff_remove_nested_component() {
                 Patch::CopyElement(leaked_e("div")),
                 Patch::Up,
                 Patch::RemoveComponent(FakeComponent::leaked()),
-                Patch::CreateElement { element: "div" },
+                Patch::CreateElement { element: "div", namespace: None },
                 Patch::Up,
                 Patch::RemoveElement(leaked_e("div")),
               Patch::Up,
@@ -1270,10 +1533,13 @@ fn diff_keyed_element_equal() {
         patch_set,
         [
             Patch::CopyElement(leaked_e("div")),
-              Patch::MoveElement(leaked_e("div")),
-              Patch::Up,
+              Patch::ReferenceKey(&"yup"),
             Patch::Up,
-        ]
+        ],
+        &"yup" => [
+            Patch::CopyElement(leaked_e("div")),
+            Patch::Up,
+        ],
     );
 }
 
@@ -1302,7 +1568,7 @@ fn diff_keyed_element_not_equal() {
             Patch::RemoveElement(leaked_e("div")),
         ],
         &"nope" => [
-            Patch::CreateElement { element: "div" },
+            Patch::CreateElement { element: "div", namespace: None },
             Patch::Up,
         ],
     );
@@ -1332,12 +1598,12 @@ fn diff_keyed_element_move_key() {
             Patch::CopyElement(leaked_e("div")),
               Patch::RemoveElement(leaked_e("div")),
               Patch::ReferenceKey(&"yup"),
-              Patch::CreateElement { element: "div" },
+              Patch::CreateElement { element: "div", namespace: None },
               Patch::Up,
             Patch::Up,
         ],
         &"yup" => [
-            Patch::MoveElement(leaked_e("div")),
+            Patch::CopyElement(leaked_e("div")),
             Patch::Up,
         ],
     );
@@ -1357,12 +1623,12 @@ fn diff_keyed_from_empty() {
     compare!(
         patch_set,
         [
-            Patch::CreateElement { element: "div" },
+            Patch::CreateElement { element: "div", namespace: None },
               Patch::ReferenceKey(&"yup"),
             Patch::Up,
         ],
         &"yup" => [
-            Patch::CreateElement { element: "div" },
+            Patch::CreateElement { element: "div", namespace: None },
             Patch::Up,
         ],
     );
@@ -1457,16 +1723,16 @@ fn diff_duplicate_key() {
         patch_set,
         [
             Patch::CopyElement(leaked_e("div")),
-              Patch::CreateElement { element: "div" },
+              Patch::CreateElement { element: "div", namespace: None },
               Patch::Up,
               Patch::RemoveElement(leaked_e("span")),
               Patch::ReferenceKey(&"yup"),
-              Patch::CreateElement { element: "span" },
+              Patch::CreateElement { element: "span", namespace: None },
               Patch::Up,
             Patch::Up,
         ],
         &"yup" => [
-            Patch::MoveElement(leaked_e("div")),
+            Patch::CopyElement(leaked_e("div")),
             Patch::Up,
         ],
     );
@@ -1515,7 +1781,7 @@ fn diff_remove_event_handler() {
         patch_set,
         [
             Patch::CopyElement(leaked_e("div")),
-              Patch::RemoveListener { trigger: "onclick", take: leaked_closure() },
+              Patch::RemoveListener { trigger: "onclick", take: leaked_closure(), options: Default::default() },
               Patch::CreateText { text: "text".into() },
               Patch::Up,
               Patch::RemoveText(leaked_t("text")),